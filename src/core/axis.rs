@@ -0,0 +1,40 @@
+use vek::Vec3;
+
+/// the vertical axis used by a file format. Meshes in corale are z-up, so y-up data is turned a quarter turn about x
+/// on the way in and out. A turn rather than a swap of y and z keeps faces wound the same way
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UpAxis {
+    Y,
+    #[default]
+    Z,
+}
+
+impl UpAxis {
+    /// turns a position or direction from corale's z-up space into this convention
+    pub fn from_z_up<T>(self, v: Vec3<T>) -> Vec3<T> where T: std::ops::Neg<Output = T> {
+        match self {
+            UpAxis::Y => Vec3::new(v.x, v.z, -v.y),
+            UpAxis::Z => v,
+        }
+    }
+
+    /// turns a position or direction in this convention back into corale's z-up space
+    pub fn to_z_up<T>(self, v: Vec3<T>) -> Vec3<T> where T: std::ops::Neg<Output = T> {
+        match self {
+            UpAxis::Y => Vec3::new(v.x, -v.z, v.y),
+            UpAxis::Z => v,
+        }
+    }
+}
+
+#[test]
+fn up_axis_test() {
+    let v = Vec3::new(1., 2., 3.);
+    assert_eq!(UpAxis::Y.from_z_up(v), Vec3::new(1., 3., -2.));
+    assert_eq!(UpAxis::Y.to_z_up(UpAxis::Y.from_z_up(v)), v);
+    assert_eq!(UpAxis::Z.from_z_up(v), v);
+
+    // a turn keeps the handedness of a frame
+    let (x, y) = (UpAxis::Y.from_z_up(Vec3::unit_x()), UpAxis::Y.from_z_up(Vec3::unit_y()));
+    assert_eq!(x.cross(y), UpAxis::Y.from_z_up(Vec3::<f64>::unit_z()));
+}
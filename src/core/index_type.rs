@@ -1,6 +1,8 @@
 use std::fmt;
 use std::hash::Hash;
 
+/// # Safety
+/// implementors must round-trip every index they can represent through `new` and `index`.
 pub unsafe trait IndexType: Copy + Default + Hash + Ord + fmt::Debug + 'static {
     fn new(x: usize) -> Self;
     fn index(&self) -> usize;
@@ -19,7 +21,7 @@ unsafe impl IndexType for usize {
     }
     #[inline(always)]
     fn max() -> Self {
-        ::std::usize::MAX
+        usize::MAX
     }
 }
 
@@ -34,7 +36,7 @@ unsafe impl IndexType for u32 {
     }
     #[inline(always)]
     fn max() -> Self {
-        ::std::u32::MAX
+        u32::MAX
     }
 }
 
@@ -49,7 +51,7 @@ unsafe impl IndexType for u16 {
     }
     #[inline(always)]
     fn max() -> Self {
-        ::std::u16::MAX
+        u16::MAX
     }
 }
 
@@ -64,6 +66,6 @@ unsafe impl IndexType for u8 {
     }
     #[inline(always)]
     fn max() -> Self {
-        ::std::u8::MAX
+        u8::MAX
     }
 }
\ No newline at end of file
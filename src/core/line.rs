@@ -4,6 +4,7 @@ use vek::Vec3;
 pub struct Line<'a, T> where T: GeoNum {
     start: &'a Vertex<T>,
     target: &'a Vertex<T>,
+    #[allow(dead_code)]
     directed: bool,
 }
 
@@ -16,10 +16,10 @@ pub fn lerp<T>(a: T, b: T, amount: T) -> T where T: OrdNum {
     }
     if a > b {
         let range: T = a - b;
-        return b + ( range * amount);
+        b + ( range * amount)
     } else {
         let range = b - a;
-        return a + ( range * amount);
+        a + ( range * amount)
     }
 }
 
@@ -35,10 +35,10 @@ pub fn inverse_lerp<T>(a: T, b: T, value: T) -> T where T: OrdNum {
     }
     if a > b {
         let range: T = a - b;
-        return (value - b) / range;
+        (value - b) / range
     } else {
         let range = b - a;
-        return (value - a) / range;
+        (value - a) / range
     }
 }
 
@@ -1,3 +1,4 @@
+mod axis;
 mod direction;
 mod index_type;
 mod point;
@@ -7,12 +8,14 @@ mod line;
 pub mod maths;
 
 pub use point::{Point, PointIndex};
+pub use axis::UpAxis;
 pub use direction::Direction;
 pub use index_type::IndexType;
 pub use vertex::Vertex;
 pub use line::Line;
 
 use num::{Num, CheckedMul, FromPrimitive, ToPrimitive, Signed, Integer, Float};
+use vek::ops::MulAdd;
 use std::fmt::{Debug, Display};
 use std::ops::{SubAssign, AddAssign};
 use std::{f32,f64};
@@ -20,7 +23,7 @@ use std::{f32,f64};
 pub type DefaultIx = usize;
 pub trait OrdNum: Num + PartialOrd + Clone + Copy + Display + Debug + FromPrimitive + ToPrimitive {}
 pub trait GridNum : OrdNum + Integer + Signed + AddAssign + SubAssign + CheckedMul {}
-pub trait GeoNum : OrdNum + Float + Signed {
+pub trait GeoNum : OrdNum + Float + Signed + MulAdd<Self, Self, Output = Self> {
     fn pi() -> Self;
     fn rad() -> Self;
 }
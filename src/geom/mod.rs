@@ -2,10 +2,14 @@ mod bounding_box;
 mod collider;
 mod shapes;
 mod area;
+mod plane;
+mod polyline;
 
 pub mod polygon;
 
 pub use shapes::*;
 pub use collider::{BoxCollider,Collider};
 pub use bounding_box::BoundingBox;
-pub use area::Area;
\ No newline at end of file
+pub use area::Area;
+pub use plane::Plane;
+pub use polyline::Polyline;
\ No newline at end of file
@@ -0,0 +1,46 @@
+use vek::Vec3;
+use crate::core::GeoNum;
+
+/// an infinite plane, held as a unit normal and its distance from the origin along that normal
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plane<T> where T: GeoNum {
+    normal: Vec3<T>,
+    offset: T,
+}
+
+impl<T> Plane<T> where T: GeoNum {
+    /// the plane through point facing along normal. The normal need not be unit length
+    pub fn new(point: Vec3<T>, normal: Vec3<T>) -> Self {
+        let normal = normal.normalized();
+        Self {
+            normal,
+            offset: normal.dot(point),
+        }
+    }
+
+    pub fn normal(&self) -> Vec3<T> {
+        self.normal
+    }
+
+    pub fn offset(&self) -> T {
+        self.offset
+    }
+
+    /// signed distance from the plane, positive on the side the normal faces
+    pub fn distance(&self, point: Vec3<T>) -> T {
+        self.normal.dot(point) - self.offset
+    }
+
+    /// the closest point on the plane
+    pub fn project(&self, point: Vec3<T>) -> Vec3<T> {
+        point - self.normal * self.distance(point)
+    }
+
+    /// the same plane facing the other way
+    pub fn flipped(&self) -> Self {
+        Self {
+            normal: -self.normal,
+            offset: -self.offset,
+        }
+    }
+}
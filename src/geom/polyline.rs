@@ -0,0 +1,34 @@
+use vek::Vec3;
+use crate::core::GeoNum;
+
+/// an ordered run of points, either closed into a loop or left open at both ends
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polyline<T> where T: GeoNum {
+    points: Vec<Vec3<T>>,
+    closed: bool,
+}
+
+impl<T> Polyline<T> where T: GeoNum {
+    pub fn new(points: Vec<Vec3<T>>, closed: bool) -> Self {
+        Self {
+            points,
+            closed,
+        }
+    }
+
+    pub fn points(&self) -> &Vec<Vec3<T>> {
+        &self.points
+    }
+
+    /// true when the last point joins back to the first
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// total length, including the closing segment of a loop
+    pub fn length(&self) -> T {
+        let n = self.points.len();
+        let segments = if self.closed { n } else { n.saturating_sub(1) };
+        (0..segments).fold(T::zero(), |sum, i| sum + self.points[i].distance(self.points[(i + 1) % n]))
+    }
+}
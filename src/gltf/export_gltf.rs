@@ -0,0 +1,491 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use crate::core::*;
+use crate::mesh::*;
+use crate::scene::Scene;
+use vek::{Mat4, Vec3};
+
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// writes the meshes as a .gltf file and a .bin buffer beside it at file_path, appending the extensions.
+/// each mesh becomes a node of the default scene
+pub fn export<T>(meshes: &[&Mesh<T>], file_path: String) -> io::Result<()>
+    where T: GeoNum
+{
+    let bin_path = format!("{}.bin", file_path);
+    let bin_uri = Path::new(&bin_path).file_name().unwrap().to_string_lossy().into_owned();
+
+    let mut json = BufWriter::new(File::create(format!("{}.gltf", file_path))?);
+    let mut bin = BufWriter::new(File::create(&bin_path)?);
+    write(meshes, &mut json, &mut bin, &bin_uri)?;
+    json.flush()?;
+    bin.flush()
+}
+
+/// writes the meshes as a single binary .glb file at file_path, appending the extension
+pub fn export_glb<T>(meshes: &[&Mesh<T>], file_path: String) -> io::Result<()>
+    where T: GeoNum
+{
+    let mut writer = BufWriter::new(File::create(format!("{}.glb", file_path))?);
+    write_glb(meshes, &mut writer)?;
+    writer.flush()
+}
+
+/// streams the gltf json into one writer and its binary buffer into another. bin_uri is how the json refers to the buffer
+pub fn write<T, J, B>(meshes: &[&Mesh<T>], json: &mut J, bin: &mut B, bin_uri: &str) -> io::Result<()>
+    where
+        T: GeoNum,
+        J: Write,
+        B: Write
+{
+    let document = Document::from_meshes(meshes);
+    json.write_all(document.json(Some(bin_uri)).as_bytes())?;
+    bin.write_all(&document.buffer)
+}
+
+/// streams the meshes as a single binary .glb into any writer
+pub fn write_glb<T, W>(meshes: &[&Mesh<T>], writer: &mut W) -> io::Result<()>
+    where
+        T: GeoNum,
+        W: Write
+{
+    Document::from_meshes(meshes).write_glb(writer)
+}
+
+/// writes the scene as a .gltf file and a .bin buffer beside it at file_path, keeping its node hierarchy and transforms
+pub fn export_scene<T>(scene: &Scene<T>, file_path: String) -> io::Result<()>
+    where T: GeoNum
+{
+    let bin_path = format!("{}.bin", file_path);
+    let bin_uri = Path::new(&bin_path).file_name().unwrap().to_string_lossy().into_owned();
+
+    let mut json = BufWriter::new(File::create(format!("{}.gltf", file_path))?);
+    let mut bin = BufWriter::new(File::create(&bin_path)?);
+    write_scene(scene, &mut json, &mut bin, &bin_uri)?;
+    json.flush()?;
+    bin.flush()
+}
+
+/// writes the scene as a single binary .glb file at file_path, keeping its node hierarchy and transforms
+pub fn export_scene_glb<T>(scene: &Scene<T>, file_path: String) -> io::Result<()>
+    where T: GeoNum
+{
+    let mut writer = BufWriter::new(File::create(format!("{}.glb", file_path))?);
+    write_scene_glb(scene, &mut writer)?;
+    writer.flush()
+}
+
+/// streams the scene's gltf json into one writer and its binary buffer into another
+pub fn write_scene<T, J, B>(scene: &Scene<T>, json: &mut J, bin: &mut B, bin_uri: &str) -> io::Result<()>
+    where
+        T: GeoNum,
+        J: Write,
+        B: Write
+{
+    let document = Document::from_scene(scene);
+    json.write_all(document.json(Some(bin_uri)).as_bytes())?;
+    bin.write_all(&document.buffer)
+}
+
+/// streams the scene as a single binary .glb into any writer
+pub fn write_scene_glb<T, W>(scene: &Scene<T>, writer: &mut W) -> io::Result<()>
+    where
+        T: GeoNum,
+        W: Write
+{
+    Document::from_scene(scene).write_glb(writer)
+}
+
+/// a gltf document under construction. The json sections are kept as pre-rendered objects
+struct Document {
+    buffer: Vec<u8>,
+    views: Vec<String>,
+    accessors: Vec<String>,
+    meshes: Vec<String>,
+    nodes: Vec<String>,
+    roots: Vec<usize>,
+}
+
+impl Document {
+    fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            views: Vec::new(),
+            accessors: Vec::new(),
+            meshes: Vec::new(),
+            nodes: Vec::new(),
+            roots: Vec::new(),
+        }
+    }
+
+    fn from_meshes<T: GeoNum>(meshes: &[&Mesh<T>]) -> Self {
+        let mut document = Self::new();
+        for mesh in meshes.iter() {
+            let mut fields = Vec::new();
+            if let Some(m) = document.add_mesh(mesh) {
+                fields.push(format!("\"mesh\":{}", m));
+            }
+            if let Some(name) = mesh.name() {
+                fields.push(format!("\"name\":{}", json_string(&name)));
+            }
+            document.roots.push(document.nodes.len());
+            document.nodes.push(format!("{{{}}}", fields.join(",")));
+        }
+        document
+    }
+
+    /// mirrors the scene's nodes one to one, so node indices carry straight over
+    fn from_scene<T: GeoNum>(scene: &Scene<T>) -> Self {
+        let mut document = Self::new();
+        for node in scene.nodes().iter() {
+            let mut fields = Vec::new();
+            if let Some(m) = node.mesh().and_then(|mesh| document.add_mesh(mesh)) {
+                fields.push(format!("\"mesh\":{}", m));
+            }
+            if !node.children().is_empty() {
+                let children: Vec<String> = node.children().iter().map(|c| c.index().to_string()).collect();
+                fields.push(format!("\"children\":[{}]", children.join(",")));
+            }
+            let transform = node.transform();
+            if transform != Mat4::identity() {
+                let values: Vec<String> = convert_matrix(transform).into_col_array().iter()
+                    .map(|v| number(v.to_f32().unwrap()))
+                    .collect();
+                fields.push(format!("\"matrix\":[{}]", values.join(",")));
+            }
+            if let Some(name) = node.name() {
+                fields.push(format!("\"name\":{}", json_string(&name)));
+            }
+            document.nodes.push(format!("{{{}}}", fields.join(",")));
+        }
+        document.roots = scene.roots().iter().map(|r| r.index()).collect();
+        document
+    }
+
+    fn write_glb<W: Write>(self, writer: &mut W) -> io::Result<()> {
+        let mut json = self.json(None).into_bytes();
+        while !json.len().is_multiple_of(4) {
+            json.push(b' ');
+        }
+        let mut buffer = self.buffer;
+        while !buffer.len().is_multiple_of(4) {
+            buffer.push(0);
+        }
+
+        // a document without buffers has no binary chunk at all
+        let binary = if buffer.is_empty() { 0 } else { 8 + buffer.len() };
+        let length = 12 + 8 + json.len() + binary;
+        writer.write_all(b"glTF")?;
+        writer.write_all(&2u32.to_le_bytes())?;
+        writer.write_all(&(length as u32).to_le_bytes())?;
+
+        writer.write_all(&(json.len() as u32).to_le_bytes())?;
+        writer.write_all(b"JSON")?;
+        writer.write_all(&json)?;
+
+        if binary > 0 {
+            writer.write_all(&(buffer.len() as u32).to_le_bytes())?;
+            writer.write_all(b"BIN\0")?;
+            writer.write_all(&buffer)?;
+        }
+        Ok(())
+    }
+
+    /// adds the mesh data to the buffer and returns the index of its gltf mesh. A gltf mesh needs at least one
+    /// vertex, so a mesh without any is left out and None returned
+    fn add_mesh<T: GeoNum>(&mut self, mesh: &Mesh<T>) -> Option<usize> {
+        if mesh.verticies().is_empty() {
+            return None;
+        }
+        let positions: Vec<Vec3<f32>> = mesh.verticies().iter()
+            .map(|v| UpAxis::Y.from_z_up(v.to_vec3()).map(|x| x.to_f32().unwrap()))
+            .collect();
+        let mut min = positions.first().copied().unwrap_or_else(Vec3::zero);
+        let mut max = min;
+        for p in positions.iter() {
+            min = Vec3::partial_min(min, *p);
+            max = Vec3::partial_max(max, *p);
+        }
+
+        let mut attributes = Vec::new();
+        let position = self.add_floats(positions.iter().flat_map(|p| vec![p.x, p.y, p.z]), positions.len(), "VEC3", ARRAY_BUFFER,
+            Some(format!(",\"min\":[{},{},{}],\"max\":[{},{},{}]",
+                number(min.x), number(min.y), number(min.z), number(max.x), number(max.y), number(max.z))));
+        attributes.push(format!("\"POSITION\":{}", position));
+
+        if let Some(normals) = mesh.normals() {
+            let values = normals.iter().flat_map(|n| {
+                let n = UpAxis::Y.from_z_up(*n);
+                vec![n.x.to_f32().unwrap(), n.y.to_f32().unwrap(), n.z.to_f32().unwrap()]
+            });
+            let normal = self.add_floats(values, normals.len(), "VEC3", ARRAY_BUFFER, None);
+            attributes.push(format!("\"NORMAL\":{}", normal));
+        }
+
+        if let Some(tangents) = mesh.tangents() {
+            // turning to y-up keeps the frame's handedness but flipping v below mirrors the uvs, so w changes sign
+            let values = tangents.iter().flat_map(|t| {
+                let v: Vec3<T> = UpAxis::Y.from_z_up(Vec3::from(*t));
+                vec![v.x.to_f32().unwrap(), v.y.to_f32().unwrap(), v.z.to_f32().unwrap(), -t.w.to_f32().unwrap()]
+            });
+            let tangent = self.add_floats(values, tangents.len(), "VEC4", ARRAY_BUFFER, None);
+            attributes.push(format!("\"TANGENT\":{}", tangent));
+        }
+
+        if let Some(uvs) = mesh.uvs() {
+            // gltf puts the texture origin at the top left rather than the bottom left
+            let values = uvs.iter().flat_map(|uv| vec![uv.x.to_f32().unwrap(), 1. - uv.y.to_f32().unwrap()]);
+            let uv = self.add_floats(values, uvs.len(), "VEC2", ARRAY_BUFFER, None);
+            attributes.push(format!("\"TEXCOORD_0\":{}", uv));
+        }
+
+        let indices: Vec<u32> = mesh.faces().iter()
+            .flat_map(|f| f.triangles())
+            .flat_map(|t| vec![t[0].index() as u32, t[1].index() as u32, t[2].index() as u32])
+            .collect();
+        let mut primitive = format!("\"attributes\":{{{}}}", attributes.join(","));
+        // empty buffer views are not allowed, so a mesh without faces is written as a bare point list
+        if indices.is_empty() {
+            primitive.push_str(",\"mode\":0");
+        } else {
+            let offset = self.buffer.len();
+            for i in indices.iter() {
+                self.buffer.extend_from_slice(&i.to_le_bytes());
+            }
+            let view = self.add_view(offset, ELEMENT_ARRAY_BUFFER);
+            let index = self.accessors.len();
+            self.accessors.push(format!("{{\"bufferView\":{},\"componentType\":{},\"count\":{},\"type\":\"SCALAR\"}}",
+                view, UNSIGNED_INT, indices.len()));
+            primitive.push_str(&format!(",\"indices\":{},\"mode\":4", index));
+        }
+
+        let m = self.meshes.len();
+        self.meshes.push(format!("{{\"primitives\":[{{{}}}]{}}}", primitive, name_field(mesh.name())));
+        Some(m)
+    }
+
+    fn add_floats<I>(&mut self, values: I, count: usize, kind: &str, target: u32, extra: Option<String>) -> usize
+        where I: Iterator<Item = f32>
+    {
+        let offset = self.buffer.len();
+        for value in values {
+            self.buffer.extend_from_slice(&value.to_le_bytes());
+        }
+        let view = self.add_view(offset, target);
+        let accessor = self.accessors.len();
+        self.accessors.push(format!("{{\"bufferView\":{},\"componentType\":{},\"count\":{},\"type\":\"{}\"{}}}",
+            view, FLOAT, count, kind, extra.unwrap_or_default()));
+        accessor
+    }
+
+    /// wraps everything written since offset in a buffer view, then pads the buffer so the next view stays aligned
+    fn add_view(&mut self, offset: usize, target: u32) -> usize {
+        let length = self.buffer.len() - offset;
+        while !self.buffer.len().is_multiple_of(4) {
+            self.buffer.push(0);
+        }
+        let view = self.views.len();
+        self.views.push(format!("{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{},\"target\":{}}}", offset, length, target));
+        view
+    }
+
+    fn json(&self, bin_uri: Option<&str>) -> String {
+        let uri = bin_uri.map(|u| format!(",\"uri\":{}", json_string(u))).unwrap_or_default();
+        let roots: Vec<String> = self.roots.iter().map(|r| r.to_string()).collect();
+        let scene = if roots.is_empty() { String::new() } else { format!("\"nodes\":[{}]", roots.join(",")) };
+        let mut sections = vec![
+            String::from("\"asset\":{\"version\":\"2.0\",\"generator\":\"corale\"}"),
+            String::from("\"scene\":0"),
+            format!("\"scenes\":[{{{}}}]", scene),
+        ];
+        // gltf does not allow empty arrays, so sections with nothing in them are left out
+        for (name, items) in [("nodes", &self.nodes), ("meshes", &self.meshes), ("accessors", &self.accessors), ("bufferViews", &self.views)].iter() {
+            if !items.is_empty() {
+                sections.push(format!("\"{}\":[{}]", name, items.join(",")));
+            }
+        }
+        if !self.buffer.is_empty() {
+            sections.push(format!("\"buffers\":[{{\"byteLength\":{}{}}}]", self.buffer.len(), uri));
+        }
+        format!("{{{}}}", sections.join(","))
+    }
+}
+
+/// moves a z-up transform into gltf's y-up space by turning it on both sides, its columns as the points it
+/// produces and its rows as the points it takes
+fn convert_matrix<T: GeoNum>(matrix: Mat4<T>) -> Mat4<T> {
+    let turn = |c: [T; 4]| {
+        let v = UpAxis::Y.from_z_up(Vec3::new(c[0], c[1], c[2]));
+        [v.x, v.y, v.z, c[3]]
+    };
+    let mut cols = matrix.into_col_arrays();
+    for col in cols.iter_mut() {
+        *col = turn(*col);
+    }
+    let mut rows = Mat4::from_col_arrays(cols).into_row_arrays();
+    for row in rows.iter_mut() {
+        *row = turn(*row);
+    }
+    Mat4::from_row_arrays(rows)
+}
+
+/// formats a json number, writing the negative zeros that turning to y-up leaves behind as plain zeros
+fn number(value: f32) -> String {
+    if value == 0. { String::from("0") } else { value.to_string() }
+}
+
+fn name_field(name: Option<String>) -> String {
+    name.map(|n| format!(",\"name\":{}", json_string(&n))).unwrap_or_default()
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[test]
+fn glb_test() {
+    let mut quad = Mesh::<f64>::new();
+    quad.set_name("quad \"one\"".into());
+    quad.make_face(vec![
+        Vertex::new(0., 0., 0.),
+        Vertex::new(1., 0., 0.),
+        Vertex::new(1., 1., 0.),
+        Vertex::new(0., 1., 0.),
+    ]);
+    let mut tri = Mesh::<f64>::new();
+    tri.make_face(vec![Vertex::new(0., 0., 0.), Vertex::new(1., 0., 0.), Vertex::new(0., 0., 1.)]);
+    tri.set_normals(vec![Vec3::unit_y(); 3]);
+    tri.set_tangents(vec![vek::Vec4::new(1., 0., 0., 1.); 3]);
+
+    let mut data = Vec::new();
+    write_glb(&[&quad, &tri], &mut data).unwrap();
+
+    let u32_at = |i: usize| u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]) as usize;
+    assert_eq!(&data[0..4], b"glTF");
+    assert_eq!(u32_at(4), 2);
+    assert_eq!(u32_at(8), data.len());
+
+    let json_len = u32_at(12);
+    assert!(json_len.is_multiple_of(4));
+    assert_eq!(&data[16..20], b"JSON");
+    let json = std::str::from_utf8(&data[20..20 + json_len]).unwrap();
+    assert!(json.contains("\"scenes\":[{\"nodes\":[0,1]}]"));
+    assert!(json.contains("\"name\":\"quad \\\"one\\\"\""));
+    assert!(json.contains("\"NORMAL\":3"));
+    assert!(json.contains("\"TANGENT\":4"));
+    assert!(!json.contains("\"uri\""));
+
+    // 4 + 3 positions, 3 normals, 3 tangents and 6 + 3 indices
+    let bin_len = u32_at(20 + json_len);
+    assert_eq!(&data[24 + json_len..28 + json_len], b"BIN\0");
+    assert_eq!(bin_len, 7 * 12 + 3 * 12 + 3 * 16 + 9 * 4);
+}
+
+#[test]
+fn scene_test() {
+    use crate::scene::Node;
+
+    let mut tri = Mesh::<f64>::new();
+    tri.make_face(vec![Vertex::new(0., 0., 0.), Vertex::new(1., 0., 0.), Vertex::new(0., 0., 1.)]);
+    let mut scene = Scene::new();
+    let mut group = Node::new(Some("group".into()));
+    group.set_transform(Mat4::translation_3d(Vec3::new(1., 2., 3.)));
+    let group = scene.add_node(group, None);
+    scene.add_mesh(tri, Some(group));
+
+    let mut json = Vec::new();
+    let mut bin = Vec::new();
+    write_scene(&scene, &mut json, &mut bin, "scene.bin").unwrap();
+    let json = String::from_utf8(json).unwrap();
+
+    assert!(json.contains("\"scenes\":[{\"nodes\":[0]}]"));
+    assert!(json.contains("\"nodes\":[{\"children\":[1],\"matrix\":[1,0,0,0,0,1,0,0,0,0,1,0,1,3,-2,1],\"name\":\"group\"},{\"mesh\":0}]"));
+    assert!(json.contains("\"uri\":\"scene.bin\""));
+    assert_eq!(bin.len(), 3 * 12 + 3 * 4);
+}
+
+#[test]
+fn structure_test() {
+    // splits a glb into its json and the binary chunk, if it has one
+    let read = |data: &[u8]| {
+        let u32_at = |i: usize| u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]) as usize;
+        assert_eq!(u32_at(8), data.len());
+        let json_len = u32_at(12);
+        let json = String::from_utf8(data[20..20 + json_len].to_vec()).unwrap();
+        if data.len() == 20 + json_len {
+            return (json, None);
+        }
+        assert_eq!(&data[24 + json_len..28 + json_len], b"BIN\0");
+        (json, Some(data[28 + json_len..28 + json_len + u32_at(20 + json_len)].to_vec()))
+    };
+
+    // a triangle with normals and uvs, followed by a quad
+    let mut tri = Mesh::<f64>::new();
+    tri.make_face(vec![Vertex::new(0., 0., 0.), Vertex::new(2., 0., 1.), Vertex::new(0., 3., -1.)]);
+    let normal = (Vec3::new(2., 0., 1.)).cross(Vec3::new(0., 3., -1.)).normalized();
+    tri.set_normals(vec![normal; 3]);
+    tri.set_uvs(vec![vek::Vec2::new(0., 0.), vek::Vec2::new(1., 0.), vek::Vec2::new(0., 1.)]);
+    let mut quad = Mesh::<f64>::new();
+    quad.make_face(vec![Vertex::new(0., 0., 0.), Vertex::new(1., 0., 0.), Vertex::new(1., 1., 0.), Vertex::new(0., 1., 0.)]);
+
+    let mut data = Vec::new();
+    write_glb(&[&tri, &quad], &mut data).unwrap();
+    let (json, bin) = read(&data);
+    let bin = bin.unwrap();
+    assert!(json.contains("\"attributes\":{\"POSITION\":0,\"NORMAL\":1,\"TEXCOORD_0\":2},\"indices\":3,\"mode\":4"));
+    // min and max are those of the positions turned y-up
+    assert!(json.contains("{\"bufferView\":0,\"componentType\":5126,\"count\":3,\"type\":\"VEC3\",\"min\":[0,-1,-3],\"max\":[2,1,0]}"));
+    assert!(json.contains("{\"bufferView\":5,\"componentType\":5125,\"count\":6,\"type\":\"SCALAR\"}"));
+    // views are packed back to back on four byte boundaries, and the buffer holds exactly them
+    assert!(json.contains("\"bufferViews\":[{\"buffer\":0,\"byteOffset\":0,\"byteLength\":36,\"target\":34962},\
+{\"buffer\":0,\"byteOffset\":36,\"byteLength\":36,\"target\":34962},{\"buffer\":0,\"byteOffset\":72,\"byteLength\":24,\"target\":34962},\
+{\"buffer\":0,\"byteOffset\":96,\"byteLength\":12,\"target\":34963},{\"buffer\":0,\"byteOffset\":108,\"byteLength\":48,\"target\":34962},\
+{\"buffer\":0,\"byteOffset\":156,\"byteLength\":24,\"target\":34963}]"));
+    assert!(json.contains("\"buffers\":[{\"byteLength\":180}]"));
+    assert_eq!(bin.len(), 180);
+
+    // the triangle still winds counter-clockwise round its normal once turned y-up
+    let float = |o: usize| f32::from_le_bytes([bin[o], bin[o + 1], bin[o + 2], bin[o + 3]]);
+    let vec3 = |view: usize, i: usize| Vec3::new(float(view + i * 12), float(view + i * 12 + 4), float(view + i * 12 + 8));
+    let index = |k: usize| u32::from_le_bytes([bin[96 + k * 4], bin[97 + k * 4], bin[98 + k * 4], bin[99 + k * 4]]) as usize;
+    let (a, b, c) = (vec3(0, index(0)), vec3(0, index(1)), vec3(0, index(2)));
+    let facing = (b - a).cross(c - a).normalized();
+    assert!(facing.dot(vec3(36, index(0))) > 0.999);
+    assert!(facing.y > 0.);
+
+    // points without faces are written as a point list, and meshes without any verticies are left out
+    let mut points = Mesh::<f64>::new();
+    points.add_vertex(Vertex::new(1., 2., 3.));
+    points.add_vertex(Vertex::new(-1., 0., 5.));
+    let mut nothing = Mesh::<f64>::new();
+    nothing.set_name("nothing".into());
+
+    let mut data = Vec::new();
+    write_glb(&[&points, &nothing], &mut data).unwrap();
+    let (json, bin) = read(&data);
+    assert!(json.contains("\"nodes\":[{\"mesh\":0},{\"name\":\"nothing\"}],\"meshes\":[{\"primitives\":[{\"attributes\":{\"POSITION\":0},\"mode\":0}]}]"));
+    assert!(json.contains("\"min\":[-1,3,-2],\"max\":[1,5,0]"));
+    assert_eq!(bin.unwrap().len(), 24);
+
+    // with no data at all there are no empty sections and no binary chunk
+    let mut data = Vec::new();
+    write_glb(&[&nothing], &mut data).unwrap();
+    let (json, bin) = read(&data);
+    assert!(bin.is_none());
+    assert!(!json.contains("[]"));
+    assert!(!json.contains("\"accessors\"") && !json.contains("\"meshes\"") && !json.contains("\"buffers\""));
+}
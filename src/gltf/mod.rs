@@ -0,0 +1,3 @@
+mod export_gltf;
+
+pub use export_gltf::{export, export_glb, export_scene, export_scene_glb, write, write_glb, write_scene, write_scene_glb};
@@ -104,7 +104,7 @@ impl<I, T> GridMap<I, T> where T: GridNum, I: PartialEq + Clone {
         if self.index_valid(index) {
             return self.items.contains_key(&index);
         }
-        return false;
+        false
     }
 
     fn diag_neighbors(&self, pos: Vec3<T>) -> Vec<PointIndex> {
@@ -216,9 +216,9 @@ impl<I, T> Grid3D<I, T> for GridMap<I, T> where T: GridNum, I: PartialEq + Clone
 
     fn neighbors(&self, pos: Vec3<T>, diagonal: bool) -> Vec<PointIndex> {
         if diagonal {
-            return self.diag_neighbors(pos);
+            self.diag_neighbors(pos)
         } else {
-            return self.cross_neighbors(pos);
+            self.cross_neighbors(pos)
         }
     }
 }
@@ -19,6 +19,6 @@ impl<T, I> GridObject<T, I> where T: GridNum, I: PartialEq + Clone {
     }
 
     pub fn position(&self) -> Vec3<T> {
-        self.position.clone()
+        self.position
     }
 }
\ No newline at end of file
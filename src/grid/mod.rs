@@ -23,6 +23,10 @@ pub trait Grid3D<I, T>: Cube<T>
 
     fn len(&self) -> usize;
 
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     fn item(&self, index: PointIndex) -> Option<&GridObject<T, I>>;
     fn item_mut(&mut self, index: PointIndex) -> Option<&mut GridObject<T, I>>;
 
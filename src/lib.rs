@@ -2,6 +2,10 @@ pub mod grid;
 pub mod core;
 pub mod geom;
 pub mod mesh;
+pub mod scene;
 
 //#[cfg(wavefront)]
-pub mod wavefront;
\ No newline at end of file
+pub mod wavefront;
+pub mod stl;
+pub mod ply;
+pub mod gltf;
\ No newline at end of file
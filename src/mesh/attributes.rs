@@ -0,0 +1,153 @@
+use vek::{Rgba, Vec2, Vec3, Vec4};
+use crate::core::GeoNum;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// per-vertex data that travels alongside the verticies of a mesh. Each channel is either absent or holds exactly one entry per vertex
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Attributes<T> where T: GeoNum {
+    pub normals: Option<Vec<Vec3<T>>>,
+    pub uvs: Option<Vec<Vec2<T>>>,
+    pub colours: Option<Vec<Rgba<T>>>,
+    pub tangents: Option<Vec<Vec4<T>>>,
+    pub custom: BTreeMap<String, Vec<T>>,
+}
+
+impl<T> Attributes<T> where T: GeoNum {
+    pub fn new() -> Self {
+        Self {
+            normals: None,
+            uvs: None,
+            colours: None,
+            tangents: None,
+            custom: BTreeMap::new(),
+        }
+    }
+
+    /// pads every present channel with a blank entry for a freshly added vertex
+    pub fn push_default(&mut self) {
+        if let Some(normals) = self.normals.as_mut() {
+            normals.push(Vec3::zero());
+        }
+        if let Some(uvs) = self.uvs.as_mut() {
+            uvs.push(Vec2::zero());
+        }
+        if let Some(colours) = self.colours.as_mut() {
+            colours.push(Rgba::new(T::one(), T::one(), T::one(), T::one()));
+        }
+        if let Some(tangents) = self.tangents.as_mut() {
+            tangents.push(Vec4::new(T::zero(), T::zero(), T::zero(), T::one()));
+        }
+        for channel in self.custom.values_mut() {
+            channel.push(T::zero());
+        }
+    }
+
+    /// repeats the entries of an existing vertex for a freshly added copy of it
+    pub fn push_copy(&mut self, source: usize) {
+        if let Some(normals) = self.normals.as_mut() {
+            normals.push(normals[source]);
+        }
+        if let Some(uvs) = self.uvs.as_mut() {
+            uvs.push(uvs[source]);
+        }
+        if let Some(colours) = self.colours.as_mut() {
+            colours.push(colours[source]);
+        }
+        if let Some(tangents) = self.tangents.as_mut() {
+            tangents.push(tangents[source]);
+        }
+        for channel in self.custom.values_mut() {
+            channel.push(channel[source]);
+        }
+    }
+
+    /// adds entries part way between two existing verticies for a vertex made on the edge between them
+    pub fn push_lerp(&mut self, a: usize, b: usize, t: T) {
+        let s = T::one() - t;
+        if let Some(normals) = self.normals.as_mut() {
+            let n = normals[a] * s + normals[b] * t;
+            let length = n.magnitude();
+            normals.push(if length > T::zero() { n / length } else { n });
+        }
+        if let Some(uvs) = self.uvs.as_mut() {
+            uvs.push(uvs[a] * s + uvs[b] * t);
+        }
+        if let Some(colours) = self.colours.as_mut() {
+            colours.push(colours[a] * s + colours[b] * t);
+        }
+        if let Some(tangents) = self.tangents.as_mut() {
+            // the handedness is a sign, so it is taken from whichever end is nearer rather than blended
+            let w = if t < T::from_f64(0.5).unwrap() { tangents[a].w } else { tangents[b].w };
+            let v = Vec3::from(tangents[a]) * s + Vec3::from(tangents[b]) * t;
+            let length = v.magnitude();
+            let v = if length > T::zero() { v / length } else { v };
+            tangents.push(Vec4::new(v.x, v.y, v.z, w));
+        }
+        for channel in self.custom.values_mut() {
+            channel.push(channel[a] * s + channel[b] * t);
+        }
+    }
+
+    /// adds entries averaged evenly over several existing verticies for a vertex made among them
+    pub fn push_average(&mut self, sources: &[usize]) {
+        let share = T::one() / T::from_usize(sources.len()).unwrap();
+        if let Some(normals) = self.normals.as_mut() {
+            let n = sources.iter().fold(Vec3::zero(), |sum, &i| sum + normals[i]);
+            let length = n.magnitude();
+            normals.push(if length > T::zero() { n / length } else { n });
+        }
+        if let Some(uvs) = self.uvs.as_mut() {
+            uvs.push(sources.iter().fold(Vec2::zero(), |sum, &i| sum + uvs[i]) * share);
+        }
+        if let Some(colours) = self.colours.as_mut() {
+            colours.push(sources.iter().fold(Rgba::zero(), |sum, &i| sum + colours[i]) * share);
+        }
+        if let Some(tangents) = self.tangents.as_mut() {
+            // the handedness is a sign, so it is taken from the first source rather than blended
+            let v = sources.iter().fold(Vec3::zero(), |sum, &i| sum + Vec3::from(tangents[i]));
+            let length = v.magnitude();
+            let v = if length > T::zero() { v / length } else { v };
+            tangents.push(Vec4::new(v.x, v.y, v.z, tangents[sources[0]].w));
+        }
+        for channel in self.custom.values_mut() {
+            channel.push(sources.iter().fold(T::zero(), |sum, &i| sum + channel[i]) * share);
+        }
+    }
+
+    /// extends every channel with the entries of other. len and other_len are the vertex counts on each side;
+    /// a channel only one side has is padded with blank entries on the other
+    pub fn append(&mut self, other: &Self, len: usize, other_len: usize) {
+        merge(&mut self.normals, other.normals.as_ref(), len, other_len, Vec3::zero());
+        merge(&mut self.uvs, other.uvs.as_ref(), len, other_len, Vec2::zero());
+        merge(&mut self.colours, other.colours.as_ref(), len, other_len, Rgba::new(T::one(), T::one(), T::one(), T::one()));
+        merge(&mut self.tangents, other.tangents.as_ref(), len, other_len, Vec4::new(T::zero(), T::zero(), T::zero(), T::one()));
+        let names: BTreeSet<String> = self.custom.keys().chain(other.custom.keys()).cloned().collect();
+        for name in names {
+            let mut mine = self.custom.remove(&name);
+            merge(&mut mine, other.custom.get(&name), len, other_len, T::zero());
+            self.custom.insert(name, mine.unwrap());
+        }
+    }
+
+    /// builds a new set of channels where entry i is copied from sources[i]
+    pub fn gather(&self, sources: &[usize]) -> Self {
+        Self {
+            normals: self.normals.as_ref().map(|c| sources.iter().map(|&i| c[i]).collect()),
+            uvs: self.uvs.as_ref().map(|c| sources.iter().map(|&i| c[i]).collect()),
+            colours: self.colours.as_ref().map(|c| sources.iter().map(|&i| c[i]).collect()),
+            tangents: self.tangents.as_ref().map(|c| sources.iter().map(|&i| c[i]).collect()),
+            custom: self.custom.iter().map(|(k, c)| (k.clone(), sources.iter().map(|&i| c[i]).collect())).collect(),
+        }
+    }
+}
+
+fn merge<V: Copy>(mine: &mut Option<Vec<V>>, theirs: Option<&Vec<V>>, len: usize, other_len: usize, blank: V) {
+    if mine.is_none() && theirs.is_none() {
+        return;
+    }
+    let channel = mine.get_or_insert_with(|| vec![blank; len]);
+    match theirs {
+        Some(values) => channel.extend_from_slice(values),
+        None => channel.extend(std::iter::repeat_n(blank, other_len)),
+    }
+}
@@ -0,0 +1,291 @@
+use super::{Face, FaceIndex, Mesh, Primitive};
+use super::topology::{edge_faces, edge_key, EdgeKey};
+use crate::core::{GeoNum, PointIndex, Vertex};
+use std::collections::{HashMap, HashSet};
+use vek::Vec3;
+
+/// cuts the given edges back by width on each of their faces and joins the cut with a strip of faces, rounded
+/// with the given number of segments. Verticies where bevelled edges meet are capped with a patch. Edges that do
+/// not sit between exactly two consistently wound faces are left alone.
+///
+/// The faces of the mesh keep their indices; the indices of the strips and patches are returned. The corners the
+/// bevel moves away from are removed along with any other vertex no face uses, so vertex indices can shift
+pub fn bevel<T>(mesh: &mut Mesh<T>, edges: &[(PointIndex, PointIndex)], width: T, segments: usize) -> Vec<FaceIndex>
+    where T: GeoNum
+{
+    let faces = mesh.faces().clone();
+    let uses = edge_faces(&faces);
+    let bevelled: HashSet<EdgeKey> = edges.iter()
+        .map(|&(a, b)| edge_key(a.index(), b.index()))
+        .filter(|key| uses.get(key).map(|u| u.len() == 2 && u[0].1 != u[1].1).unwrap_or(false))
+        .collect();
+    if bevelled.is_empty() {
+        return Vec::new();
+    }
+
+    let width = width.to_f64().unwrap();
+    let segments = segments.max(1);
+    let mut bevel = Bevel::new(mesh, width);
+    let mut directed = HashMap::new();
+    for (f, face) in faces.iter().enumerate() {
+        let v = face.verticies();
+        for i in 0..v.len() {
+            directed.insert((v[i].index(), v[(i + 1) % v.len()].index()), f);
+        }
+    }
+
+    // each face's corners pulled in off the bevelled edges, found by intersecting its offset edge lines
+    let mut corners: Vec<Vec<usize>> = Vec::new();
+    for face in faces.iter() {
+        let v: Vec<usize> = face.verticies().iter().map(|p| p.index()).collect();
+        let n = v.len();
+        let normal = bevel.normal(&v);
+        corners.push((0..n).map(|i| {
+            let (prev, here, next) = (v[(i + n - 1) % n], v[i], v[(i + 1) % n]);
+            let d1 = if bevelled.contains(&edge_key(prev, here)) { width } else { 0. };
+            let d2 = if bevelled.contains(&edge_key(here, next)) { width } else { 0. };
+            if d1 == 0. && d2 == 0. {
+                return here;
+            }
+            let n1 = normal.cross(bevel.position(here) - bevel.position(prev)).normalized();
+            let n2 = normal.cross(bevel.position(next) - bevel.position(here)).normalized();
+            let c = n1.dot(n2);
+            let offset = if 1. - c * c < 1e-12 {
+                n1 * d1.max(d2)
+            } else {
+                n1 * ((d1 - c * d2) / (1. - c * c)) + n2 * ((d2 - c * d1) / (1. - c * c))
+            };
+            bevel.point(here, bevel.position(here) + offset)
+        }).collect());
+    }
+    let corner_at = |f: usize, v: usize| {
+        let i = faces[f].verticies().iter().position(|p| p.index() == v).unwrap();
+        corners[f][i]
+    };
+
+    // a profile across each end of each bevelled edge, running from the face that follows the edge from its
+    // smaller vertex to the face that follows it back
+    let mut profiles: HashMap<(EdgeKey, usize), (usize, Vec<usize>)> = HashMap::new();
+    let mut strips = Vec::new();
+    let mut keys: Vec<&EdgeKey> = bevelled.iter().collect();
+    keys.sort_unstable();
+    for &key in keys {
+        let (first, second) = if uses[&key][0].1 { (uses[&key][0].0, uses[&key][1].0) } else { (uses[&key][1].0, uses[&key][0].0) };
+        let mut ends = Vec::new();
+        for &v in [key.0, key.1].iter() {
+            let (start, end) = (corner_at(first, v), corner_at(second, v));
+            let (p0, control, p2) = (bevel.position(start), bevel.position(v), bevel.position(end));
+            let mut profile = vec![start];
+            for k in 1..segments {
+                let t = k as f64 / segments as f64;
+                let p = p0 * ((1. - t) * (1. - t)) + control * (2. * t * (1. - t)) + p2 * (t * t);
+                profile.push(bevel.point(v, p));
+            }
+            profile.push(end);
+            profiles.insert((key, v), (first, profile.clone()));
+            ends.push(profile);
+        }
+        for k in 0..segments {
+            strips.push(vec![ends[1][k], ends[0][k], ends[0][k + 1], ends[1][k + 1]]);
+        }
+    }
+
+    // faces follow their new corners, picking up any corner of a neighbour that now lands part way along a shared edge
+    let mut outlines: Vec<Vec<usize>> = faces.iter().enumerate().map(|(f, face)| {
+        let v: Vec<usize> = face.verticies().iter().map(|p| p.index()).collect();
+        let n = v.len();
+        let mut outline = Vec::new();
+        for i in 0..n {
+            let (here, next) = (v[i], v[(i + 1) % n]);
+            let (start, end) = (corners[f][i], corners[f][(i + 1) % n]);
+            outline.push(start);
+            if bevelled.contains(&edge_key(here, next)) {
+                continue;
+            }
+            let (origin, dir) = (bevel.position(here), bevel.position(next) - bevel.position(here));
+            let along = |p: usize| (bevel.position(p) - origin).dot(dir) / dir.magnitude_squared();
+            let (t0, t1) = (along(start), along(end));
+            let mut between: Vec<(f64, usize)> = uses[&edge_key(here, next)].iter()
+                .filter(|&&(g, _)| g != f)
+                .flat_map(|&(g, _)| vec![corner_at(g, here), corner_at(g, next)])
+                .filter(|&p| p != start && p != end)
+                .map(|p| (along(p), p))
+                .filter(|&(t, _)| t > t0 + 1e-12 && t < t1 - 1e-12)
+                .collect();
+            between.sort_by(|a, b| a.0.total_cmp(&b.0));
+            outline.extend(between.into_iter().map(|(_, p)| p));
+        }
+        dedup_ring(outline)
+    }).collect();
+
+    // the gap left around each vertex that moved, walked face to face across the edges leaving the vertex
+    let mut patches = Vec::new();
+    let mut touched: Vec<usize> = bevelled.iter().flat_map(|&(a, b)| vec![a, b]).collect();
+    touched.sort_unstable();
+    touched.dedup();
+    for v in touched {
+        let fan: Vec<usize> = (0..faces.len()).filter(|&f| faces[f].verticies().iter().any(|p| p.index() == v)).collect();
+        let neighbour = |f: usize, step: isize| {
+            let verts = faces[f].verticies();
+            let n = verts.len() as isize;
+            let i = verts.iter().position(|p| p.index() == v).unwrap() as isize;
+            verts[((i + step + n) % n) as usize].index()
+        };
+        let start = fan.iter().copied()
+            .find(|&f| !directed.contains_key(&(v, neighbour(f, -1))))
+            .unwrap_or(fan[0]);
+
+        let mut path = Vec::new();
+        let mut visited = HashSet::new();
+        let mut f = start;
+        let closed = loop {
+            visited.insert(f);
+            path.push(corner_at(f, v));
+            let next = neighbour(f, 1);
+            if let Some((first, profile)) = profiles.get(&(edge_key(v, next), v)) {
+                let inner = &profile[1..profile.len() - 1];
+                if *first == f {
+                    path.extend(inner.iter());
+                } else {
+                    path.extend(inner.iter().rev());
+                }
+            }
+            match directed.get(&(next, v)) {
+                Some(&g) if g == start => break true,
+                Some(&g) if !visited.contains(&g) => f = g,
+                _ => break false,
+            }
+        };
+        if !closed {
+            path.push(v);
+        }
+        // the walk turns clockwise seen from outside
+        path.reverse();
+        let patch = dedup_ring(path);
+        if patch.len() < 3 {
+            continue;
+        }
+
+        // a single face left holding the vertex lies flat against the patch, so it simply grows to cover it
+        let held: Vec<usize> = fan.iter().copied().filter(|&f| corner_at(f, v) == v).collect();
+        if closed && held.len() == 1 {
+            let at = patch.iter().position(|&p| p == v).unwrap();
+            let cover: Vec<usize> = (1..patch.len()).map(|k| patch[(at + k) % patch.len()]).collect();
+            let outline = &mut outlines[held[0]];
+            let i = outline.iter().position(|&p| p == v).unwrap();
+            outline.splice(i..=i, cover);
+            *outline = dedup_ring(std::mem::take(outline));
+        } else if segments > 1 && patch.len() > 4 {
+            let centre = patch.iter().fold(Vec3::zero(), |sum, &p| sum + bevel.position(p)) / patch.len() as f64;
+            let middle = bevel.point(v, centre);
+            for k in 0..patch.len() {
+                patches.push(vec![patch[k], patch[(k + 1) % patch.len()], middle]);
+            }
+        } else {
+            patches.push(patch);
+        }
+    }
+
+    let mut all: Vec<Face> = outlines.into_iter().map(to_face).collect();
+    let mut created = Vec::new();
+    for face in strips.into_iter().map(dedup_ring).chain(patches).filter(|f| f.len() >= 3) {
+        created.push(FaceIndex::new(all.len()));
+        all.push(to_face(face));
+    }
+    mesh.set_faces(all);
+    mesh.remove_unused_verticies();
+    created
+}
+
+fn to_face(verts: Vec<usize>) -> Face {
+    Face::new(verts.into_iter().map(PointIndex::new).collect())
+}
+
+/// drops repeated neighbours, including the wrap from last to first
+fn dedup_ring(mut ring: Vec<usize>) -> Vec<usize> {
+    ring.dedup();
+    while ring.len() > 1 && ring.first() == ring.last() {
+        ring.pop();
+    }
+    ring
+}
+
+/// the mesh being bevelled, handing out new verticies as they are needed
+struct Bevel<'a, T> where T: GeoNum {
+    mesh: &'a mut Mesh<T>,
+    positions: Vec<Vec3<f64>>,
+    /// the verticies already made from each original vertex
+    made: HashMap<usize, Vec<usize>>,
+    tolerance: f64,
+}
+
+impl<'a, T> Bevel<'a, T> where T: GeoNum {
+    fn new(mesh: &'a mut Mesh<T>, width: f64) -> Self {
+        let positions: Vec<Vec3<f64>> = mesh.verticies().iter()
+            .map(|p| Vec3::new(p.x.to_f64().unwrap(), p.y.to_f64().unwrap(), p.z.to_f64().unwrap()))
+            .collect();
+        Self { mesh, positions, made: HashMap::new(), tolerance: width.abs() * 1e-9 }
+    }
+
+    fn position(&self, v: usize) -> Vec3<f64> {
+        self.positions[v]
+    }
+
+    fn normal(&self, verts: &[usize]) -> Vec3<f64> {
+        let mut normal = Vec3::zero();
+        for i in 0..verts.len() {
+            let (a, b) = (self.positions[verts[i]], self.positions[verts[(i + 1) % verts.len()]]);
+            normal += a.cross(b);
+        }
+        normal.normalized()
+    }
+
+    /// a vertex split off from source at the given position, reusing one already made there
+    fn point(&mut self, source: usize, position: Vec3<f64>) -> usize {
+        if self.positions[source].distance(position) <= self.tolerance {
+            return source;
+        }
+        let (positions, tolerance) = (&self.positions, self.tolerance);
+        let made = self.made.entry(source).or_default();
+        if let Some(&existing) = made.iter().find(|&&i| positions[i].distance(position) <= tolerance) {
+            return existing;
+        }
+        let t = |v: f64| T::from_f64(v).unwrap();
+        let index = self.mesh.duplicate_vertex(PointIndex::new(source), Vertex::new(t(position.x), t(position.y), t(position.z))).index();
+        self.positions.push(position);
+        made.push(index);
+        index
+    }
+}
+
+#[test]
+fn bevel_test() {
+    use super::fixture::{closed, cube};
+
+    // one edge: a single chamfer strip, with the end faces growing to meet it
+    let mut single = cube(0., 1.);
+    let strips = bevel(&mut single, &[(PointIndex::new(5), PointIndex::new(7))], 0.25, 1);
+    assert_eq!(strips.len(), 1);
+    assert_eq!(single.faces().len(), 7);
+    // the two bevelled corners are each replaced by one on either face
+    assert_eq!(single.verticies().len(), 10);
+    assert!(closed(&single));
+    assert!((single.volume() - (1. - 0.25 * 0.25 / 2.)).abs() < 1e-12);
+
+    // every edge: twelve strips and eight corner triangles
+    let mut all = cube(0., 1.);
+    let edges: Vec<(PointIndex, PointIndex)> = all.faces().iter()
+        .flat_map(super::topology::face_edges)
+        .map(|(a, b)| (PointIndex::new(a), PointIndex::new(b)))
+        .collect();
+    let created = bevel(&mut all, &edges, 0.1, 1);
+    assert_eq!(created.len(), 20);
+    assert_eq!(all.verticies().len(), 24);
+    assert!(closed(&all));
+    assert!(super::validate(&all).is_valid());
+
+    let mut round = cube(0., 1.);
+    bevel(&mut round, &edges, 0.1, 3);
+    assert!(closed(&round));
+    assert!(round.volume() < 1. && round.volume() > all.volume());
+}
@@ -0,0 +1,197 @@
+use super::{Face, Mesh, Primitive};
+use super::topology::{edge_faces, edge_key, EdgeKey};
+use super::triangulate::triangulate;
+use crate::core::{GeoNum, Point, PointIndex};
+use crate::geom::{BoundingBox, Cube, Plane};
+use std::collections::{HashMap, HashSet};
+use vek::{Vec2, Vec3};
+
+/// cuts away everything behind the plane, keeping the side its normal faces. Faces crossing the plane are split
+/// along it, with the new verticies shared between neighbours and their attributes blended from the edge they
+/// cut. With cap set, each closed loop left along the cut is filled with new faces so a closed mesh stays closed
+pub fn clip<T>(mesh: &mut Mesh<T>, plane: &Plane<T>, cap: bool) where T: GeoNum {
+    let distances: Vec<T> = mesh.verticies().iter().map(|v| plane.distance(v.to_vec3())).collect();
+    let inside = |v: usize| distances[v] >= T::zero();
+    let mut on_plane: HashSet<usize> = (0..distances.len()).filter(|&v| distances[v] == T::zero()).collect();
+
+    let mut cuts: HashMap<EdgeKey, PointIndex> = HashMap::new();
+    let mut faces = Vec::new();
+    for face in mesh.faces().clone() {
+        let verts = face.verticies();
+        let mut kept = Vec::new();
+        for i in 0..verts.len() {
+            let (a, b) = (verts[i], verts[(i + 1) % verts.len()]);
+            if inside(a.index()) {
+                kept.push(a);
+            }
+            if inside(a.index()) != inside(b.index()) {
+                let key = edge_key(a.index(), b.index());
+                let cut = *cuts.entry(key).or_insert_with(|| {
+                    let (p, q) = (key.0, key.1);
+                    let t = distances[p] / (distances[p] - distances[q]);
+                    mesh.split_edge(PointIndex::new(p), PointIndex::new(q), t)
+                });
+                on_plane.insert(cut.index());
+                kept.push(cut);
+            }
+        }
+        kept.dedup();
+        if kept.len() >= 3 {
+            faces.push(Face::new(kept));
+        }
+    }
+
+    if cap {
+        let caps = cap_faces(mesh, &faces, &on_plane, plane.normal());
+        faces.extend(caps);
+    }
+    mesh.set_faces(faces);
+    mesh.remove_unused_verticies();
+}
+
+/// cuts the mesh in two along the plane, returning the part in front of it and the part behind it
+pub fn split<T>(mesh: &Mesh<T>, plane: &Plane<T>, cap: bool) -> (Mesh<T>, Mesh<T>) where T: GeoNum {
+    let mut front = mesh.clone();
+    clip(&mut front, plane, cap);
+    let mut back = mesh.clone();
+    clip(&mut back, &plane.flipped(), cap);
+    (front, back)
+}
+
+/// trims the mesh to the inside of a box, splitting the faces that cross its sides
+pub fn crop<T>(mesh: &mut Mesh<T>, bounds: &BoundingBox<T>, cap: bool) where T: GeoNum {
+    let (min, max) = (bounds.min(), bounds.max());
+    for axis in [Vec3::unit_x(), Vec3::unit_y(), Vec3::unit_z()].iter() {
+        clip(mesh, &Plane::new(min, *axis), cap);
+        clip(mesh, &Plane::new(max, -*axis), cap);
+    }
+}
+
+/// faces filling each loop of open edges lying on the plane. They face back along the normal, toward the cut
+/// away side, and share the cut's verticies
+fn cap_faces<T: GeoNum>(mesh: &Mesh<T>, faces: &[Face], on_plane: &HashSet<usize>, normal: Vec3<T>) -> Vec<Face> {
+    let mut next: HashMap<usize, usize> = HashMap::new();
+    for (key, uses) in edge_faces(faces) {
+        if uses.len() != 1 || !on_plane.contains(&key.0) || !on_plane.contains(&key.1) {
+            continue;
+        }
+        // the cap runs against the face it meets
+        let (a, b) = if uses[0].1 { (key.1, key.0) } else { (key.0, key.1) };
+        next.insert(a, b);
+    }
+
+    let mut loops: Vec<Vec<usize>> = Vec::new();
+    let mut starts: Vec<usize> = next.keys().copied().collect();
+    starts.sort_unstable();
+    let mut seen = HashSet::new();
+    for start in starts {
+        let mut ring = Vec::new();
+        let mut v = start;
+        while seen.insert(v) {
+            ring.push(v);
+            v = match next.get(&v) {
+                Some(&n) => n,
+                None => break,
+            };
+        }
+        if v == start && ring.len() >= 3 {
+            loops.push(ring);
+        }
+    }
+
+    // flatten onto the plane, looking along the normal so outer loops wind anticlockwise
+    let n = Vec3::new(normal.x.to_f64().unwrap(), normal.y.to_f64().unwrap(), normal.z.to_f64().unwrap());
+    let u = if n.x.abs() < 0.9 { Vec3::unit_x() } else { Vec3::unit_y() }.cross(n).normalized();
+    let w = u.cross(n);
+    let flat: HashMap<usize, Vec2<f64>> = loops.iter().flatten().map(|&v| {
+        let p = mesh.verticies()[v].to_vec3();
+        let p = Vec3::new(p.x.to_f64().unwrap(), p.y.to_f64().unwrap(), p.z.to_f64().unwrap());
+        (v, Vec2::new(p.dot(u), p.dot(w)))
+    }).collect();
+
+    // gather every loop's points into one list so the triangulator can index them
+    let mut points = Vec::new();
+    let mut index = HashMap::new();
+    let rings: Vec<Vec<usize>> = loops.iter().map(|ring| ring.iter().map(|v| {
+        *index.entry(*v).or_insert_with(|| {
+            points.push(flat[v]);
+            points.len() - 1
+        })
+    }).collect()).collect();
+    let vertex: Vec<usize> = {
+        let mut vertex = vec![0; points.len()];
+        for (&v, &i) in index.iter() {
+            vertex[i] = v;
+        }
+        vertex
+    };
+
+    let area = |ring: &Vec<usize>| (0..ring.len()).map(|i| {
+        let (a, b) = (points[ring[i]], points[ring[(i + 1) % ring.len()]]);
+        a.x * b.y - b.x * a.y
+    }).sum::<f64>() / 2.;
+    let inside = |p: Vec2<f64>, ring: &Vec<usize>| {
+        let mut within = false;
+        for i in 0..ring.len() {
+            let (a, b) = (points[ring[i]], points[ring[(i + 1) % ring.len()]]);
+            if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+                within = !within;
+            }
+        }
+        within
+    };
+
+    let (outers, holes): (Vec<&Vec<usize>>, Vec<&Vec<usize>>) = rings.iter().partition(|r| area(r) > 0.);
+    let mut faces = Vec::new();
+    for outer in outers.iter() {
+        // each hole belongs to the smallest loop around it
+        let own: Vec<Vec<usize>> = holes.iter()
+            .filter(|h| {
+                outers.iter()
+                    .filter(|o| inside(points[h[0]], o))
+                    .min_by(|a, b| area(a).total_cmp(&area(b)))
+                    .map(|o| std::ptr::eq(*o, *outer))
+                    .unwrap_or(false)
+            })
+            .map(|h| h.to_vec())
+            .collect();
+        for tri in triangulate(&points, outer, &own) {
+            faces.push(Face::new(tri.iter().map(|&i| PointIndex::new(vertex[i])).collect()));
+        }
+    }
+    faces
+}
+
+#[test]
+fn clip_test() {
+    use super::fixture::{closed, cube};
+    use crate::geom::Area;
+
+    let mut open = cube(0., 1.);
+    clip(&mut open, &Plane::new(Vec3::new(0., 0., 0.25), Vec3::new(0., 0., 1.)), false);
+    assert_eq!(open.faces().len(), 5);
+    assert!((open.area() - 4.).abs() < 1e-12);
+
+    let (front, back) = split(&cube(0., 1.), &Plane::new(Vec3::new(0.5, 0.5, 0.5), Vec3::new(1., 1., 1.)), true);
+    assert!(closed(&front) && closed(&back));
+    assert!((front.volume() - 0.5).abs() < 1e-12);
+    assert!((back.volume() - 0.5).abs() < 1e-12);
+
+    // a hollow box: the cut leaves a square ring, so the cap needs a hole
+    let mut hollow = cube(0., 3.);
+    let mut inner = cube(1., 2.);
+    inner.set_faces(inner.faces().iter().map(|f| { let mut f = f.clone(); f.flip(); f }).collect());
+    hollow.append(&inner);
+    let (top, bottom) = split(&hollow, &Plane::new(Vec3::new(0., 0., 1.5), Vec3::unit_z()), true);
+    assert!(closed(&top) && closed(&bottom));
+    assert!((top.volume() - 13.).abs() < 1e-12);
+
+    let mut tile = cube(0., 4.);
+    crop(&mut tile, &BoundingBox::new(Vec3::new(1., 1., -1.), Vec3::new(2., 3., 5.)), false);
+    // only the strips of the top and bottom inside the box are left
+    assert!((tile.area() - 4.).abs() < 1e-12);
+    let mut block = cube(0., 4.);
+    crop(&mut block, &BoundingBox::new(Vec3::new(1., 1., -1.), Vec3::new(2., 3., 5.)), true);
+    assert!(closed(&block));
+    assert!((block.volume() - 8.).abs() < 1e-12);
+}
@@ -0,0 +1,415 @@
+use super::{weld, Face, Mesh, Primitive};
+use super::topology::{edge_faces, face_edges};
+use crate::core::{GeoNum, PointIndex, Vertex};
+use std::error;
+use std::fmt;
+use vek::Vec3;
+
+/// reasons a boolean operation can refuse its inputs
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CsgError {
+    /// the left hand mesh has open or non-manifold edges
+    LeftNotClosed,
+    /// the right hand mesh has open or non-manifold edges
+    RightNotClosed,
+}
+
+impl fmt::Display for CsgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CsgError::LeftNotClosed => write!(f, "Left hand mesh is not closed"),
+            CsgError::RightNotClosed => write!(f, "Right hand mesh is not closed"),
+        }
+    }
+}
+
+impl error::Error for CsgError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// everything inside either mesh
+pub fn union<T>(a: &Mesh<T>, b: &Mesh<T>) -> Result<Mesh<T>, CsgError> where T: GeoNum {
+    let (mut a, mut b, eps) = prepare(a, b)?;
+    a.clip_to(&b, eps);
+    b.clip_to(&a, eps);
+    b.invert();
+    b.clip_to(&a, eps);
+    b.invert();
+    a.build(b.all_polygons(), eps);
+    Ok(finish(a.all_polygons(), eps))
+}
+
+/// everything inside both meshes
+pub fn intersection<T>(a: &Mesh<T>, b: &Mesh<T>) -> Result<Mesh<T>, CsgError> where T: GeoNum {
+    let (mut a, mut b, eps) = prepare(a, b)?;
+    a.invert();
+    b.clip_to(&a, eps);
+    b.invert();
+    a.clip_to(&b, eps);
+    b.clip_to(&a, eps);
+    a.build(b.all_polygons(), eps);
+    a.invert();
+    Ok(finish(a.all_polygons(), eps))
+}
+
+/// everything inside a but not inside b
+pub fn difference<T>(a: &Mesh<T>, b: &Mesh<T>) -> Result<Mesh<T>, CsgError> where T: GeoNum {
+    let (mut a, mut b, eps) = prepare(a, b)?;
+    a.invert();
+    a.clip_to(&b, eps);
+    b.clip_to(&a, eps);
+    b.invert();
+    b.clip_to(&a, eps);
+    b.invert();
+    a.build(b.all_polygons(), eps);
+    a.invert();
+    Ok(finish(a.all_polygons(), eps))
+}
+
+/// a mesh is closed when every edge is shared by exactly two faces running along it in opposite directions
+pub(crate) fn is_closed<T: GeoNum>(mesh: &Mesh<T>) -> bool {
+    !mesh.faces().is_empty() && edge_faces(mesh.faces()).values().all(|uses| uses.len() == 2 && uses[0].1 != uses[1].1)
+}
+
+fn prepare<T: GeoNum>(a: &Mesh<T>, b: &Mesh<T>) -> Result<(Node, Node, f64), CsgError> {
+    if !is_closed(a) {
+        return Err(CsgError::LeftNotClosed);
+    }
+    if !is_closed(b) {
+        return Err(CsgError::RightNotClosed);
+    }
+    let (pa, pb) = (polygons(a), polygons(b));
+
+    // classification tolerance scales with the size of the inputs
+    let mut extent: f64 = 0.;
+    for p in pa.iter().chain(pb.iter()) {
+        for v in p.verts.iter() {
+            extent = extent.max(v.x.abs()).max(v.y.abs()).max(v.z.abs());
+        }
+    }
+    let eps = (extent * 1e-9).max(1e-12);
+
+    let mut na = Node::new();
+    na.build(pa, eps);
+    let mut nb = Node::new();
+    nb.build(pb, eps);
+    Ok((na, nb, eps))
+}
+
+fn polygons<T: GeoNum>(mesh: &Mesh<T>) -> Vec<Polygon> {
+    let verts = mesh.verticies();
+    mesh.faces().iter().filter_map(|f| {
+        let points = f.verticies().iter()
+            .map(|v| {
+                let p = verts[v.index()];
+                Vec3::new(p.x.to_f64().unwrap(), p.y.to_f64().unwrap(), p.z.to_f64().unwrap())
+            })
+            .collect();
+        Polygon::new(points)
+    }).collect()
+}
+
+/// turns the surviving polygons back into a mesh, sharing verticies and closing the t-junctions left by splitting
+fn finish<T: GeoNum>(polygons: Vec<Polygon>, eps: f64) -> Mesh<T> {
+    let mut mesh = Mesh::new();
+    for p in polygons.iter() {
+        mesh.make_face(p.verts.iter()
+            .map(|v| Vertex::new(T::from_f64(v.x).unwrap(), T::from_f64(v.y).unwrap(), T::from_f64(v.z).unwrap()))
+            .collect());
+    }
+    weld(&mut mesh, T::from_f64(eps * 10.).unwrap());
+    close_t_junctions(&mut mesh, eps * 10.);
+    mesh
+}
+
+/// splitting leaves verticies sitting in the middle of a neighbouring face's edge. Inserting them into that edge
+/// gives both sides the same run of edges, so the result is closed
+fn close_t_junctions<T: GeoNum>(mesh: &mut Mesh<T>, eps: f64) {
+    let points: Vec<Vec3<f64>> = mesh.verticies().iter()
+        .map(|p| Vec3::new(p.x.to_f64().unwrap(), p.y.to_f64().unwrap(), p.z.to_f64().unwrap()))
+        .collect();
+    let edges = edge_faces(mesh.faces());
+    let mut open: Vec<usize> = edges.iter()
+        .filter(|(_, uses)| uses.len() == 1)
+        .flat_map(|(e, _)| vec![e.0, e.1])
+        .collect();
+    open.sort_unstable();
+    open.dedup();
+    if open.is_empty() {
+        return;
+    }
+
+    let faces = mesh.faces().iter().map(|face| {
+        let mut verts = Vec::new();
+        for (a, b) in face_edges(face) {
+            verts.push(PointIndex::new(a));
+            if edges[&super::topology::edge_key(a, b)].len() != 1 {
+                continue;
+            }
+            let (pa, pb) = (points[a], points[b]);
+            let dir = pb - pa;
+            let length = dir.magnitude_squared();
+            let mut inside: Vec<(f64, usize)> = open.iter()
+                .filter(|&&v| v != a && v != b)
+                .filter_map(|&v| {
+                    let t = (points[v] - pa).dot(dir) / length;
+                    let closest = pa + dir * t;
+                    if t > 0. && t < 1. && (points[v] - closest).magnitude() <= eps {
+                        Some((t, v))
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            inside.sort_by(|x, y| x.0.total_cmp(&y.0));
+            verts.extend(inside.into_iter().map(|(_, v)| PointIndex::new(v)));
+        }
+        Face::new(verts)
+    }).collect();
+    mesh.set_faces(faces);
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Plane {
+    normal: Vec3<f64>,
+    w: f64,
+}
+
+impl Plane {
+    fn flip(&mut self) {
+        self.normal = -self.normal;
+        self.w = -self.w;
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Polygon {
+    verts: Vec<Vec3<f64>>,
+    plane: Plane,
+}
+
+impl Polygon {
+    /// None for polygons too small to have a plane
+    fn new(verts: Vec<Vec3<f64>>) -> Option<Self> {
+        let mut normal = Vec3::<f64>::zero();
+        let mut centre = Vec3::<f64>::zero();
+        for i in 0..verts.len() {
+            let (a, b) = (verts[i], verts[(i + 1) % verts.len()]);
+            normal.x += (a.y - b.y) * (a.z + b.z);
+            normal.y += (a.z - b.z) * (a.x + b.x);
+            normal.z += (a.x - b.x) * (a.y + b.y);
+            centre += a;
+        }
+        let length = normal.magnitude();
+        if verts.len() < 3 || length == 0. {
+            return None;
+        }
+        let normal = normal / length;
+        let centre = centre / verts.len() as f64;
+        Some(Self { plane: Plane { normal, w: normal.dot(centre) }, verts })
+    }
+
+    fn flip(&mut self) {
+        self.verts.reverse();
+        self.plane.flip();
+    }
+}
+
+const COPLANAR: u8 = 0;
+const FRONT: u8 = 1;
+const BACK: u8 = 2;
+const SPANNING: u8 = 3;
+
+/// sorts a polygon against a plane, cutting it in two if it straddles the plane
+fn split(plane: &Plane, polygon: Polygon, eps: f64, coplanar_front: &mut Vec<Polygon>, coplanar_back: &mut Vec<Polygon>,
+    front: &mut Vec<Polygon>, back: &mut Vec<Polygon>)
+{
+    let mut kind = COPLANAR;
+    let types: Vec<u8> = polygon.verts.iter().map(|v| {
+        let t = plane.normal.dot(*v) - plane.w;
+        let ty = if t < -eps { BACK } else if t > eps { FRONT } else { COPLANAR };
+        kind |= ty;
+        ty
+    }).collect();
+
+    match kind {
+        COPLANAR => {
+            if plane.normal.dot(polygon.plane.normal) > 0. {
+                coplanar_front.push(polygon);
+            } else {
+                coplanar_back.push(polygon);
+            }
+        },
+        FRONT => front.push(polygon),
+        BACK => back.push(polygon),
+        _ => {
+            let (mut f, mut b) = (Vec::new(), Vec::new());
+            let n = polygon.verts.len();
+            for i in 0..n {
+                let j = (i + 1) % n;
+                let (ti, tj) = (types[i], types[j]);
+                let (vi, vj) = (polygon.verts[i], polygon.verts[j]);
+                if ti != BACK {
+                    f.push(vi);
+                }
+                if ti != FRONT {
+                    b.push(vi);
+                }
+                if (ti | tj) == SPANNING {
+                    let t = (plane.w - plane.normal.dot(vi)) / plane.normal.dot(vj - vi);
+                    let v = vi + (vj - vi) * t;
+                    f.push(v);
+                    b.push(v);
+                }
+            }
+            // keep the parent's plane so slivers do not drift off it
+            if f.len() >= 3 {
+                front.push(Polygon { verts: f, plane: polygon.plane });
+            }
+            if b.len() >= 3 {
+                back.push(Polygon { verts: b, plane: polygon.plane });
+            }
+        },
+    }
+}
+
+/// a node of a binary space partitioning tree built from polygons
+#[derive(Debug)]
+struct Node {
+    plane: Option<Plane>,
+    front: Option<Box<Node>>,
+    back: Option<Box<Node>>,
+    polygons: Vec<Polygon>,
+}
+
+impl Node {
+    fn new() -> Self {
+        Self {
+            plane: None,
+            front: None,
+            back: None,
+            polygons: Vec::new(),
+        }
+    }
+
+    /// swaps solid space and empty space
+    fn invert(&mut self) {
+        for p in self.polygons.iter_mut() {
+            p.flip();
+        }
+        if let Some(plane) = self.plane.as_mut() {
+            plane.flip();
+        }
+        if let Some(front) = self.front.as_mut() {
+            front.invert();
+        }
+        if let Some(back) = self.back.as_mut() {
+            back.invert();
+        }
+        std::mem::swap(&mut self.front, &mut self.back);
+    }
+
+    /// removes the parts of the polygons that fall inside this tree
+    fn clip_polygons(&self, polygons: Vec<Polygon>, eps: f64) -> Vec<Polygon> {
+        let plane = match self.plane {
+            Some(p) => p,
+            None => return polygons,
+        };
+        let (mut front, mut back) = (Vec::new(), Vec::new());
+        for p in polygons {
+            let (mut cf, mut cb) = (Vec::new(), Vec::new());
+            split(&plane, p, eps, &mut cf, &mut cb, &mut front, &mut back);
+            front.append(&mut cf);
+            back.append(&mut cb);
+        }
+        let front = match &self.front {
+            Some(node) => node.clip_polygons(front, eps),
+            None => front,
+        };
+        let back = match &self.back {
+            Some(node) => node.clip_polygons(back, eps),
+            None => Vec::new(),
+        };
+        front.into_iter().chain(back).collect()
+    }
+
+    /// removes the parts of this tree's polygons that fall inside other
+    fn clip_to(&mut self, other: &Node, eps: f64) {
+        let polygons = std::mem::take(&mut self.polygons);
+        self.polygons = other.clip_polygons(polygons, eps);
+        if let Some(front) = self.front.as_mut() {
+            front.clip_to(other, eps);
+        }
+        if let Some(back) = self.back.as_mut() {
+            back.clip_to(other, eps);
+        }
+    }
+
+    fn all_polygons(&self) -> Vec<Polygon> {
+        let mut polygons = self.polygons.clone();
+        if let Some(front) = &self.front {
+            polygons.extend(front.all_polygons());
+        }
+        if let Some(back) = &self.back {
+            polygons.extend(back.all_polygons());
+        }
+        polygons
+    }
+
+    fn build(&mut self, polygons: Vec<Polygon>, eps: f64) {
+        if polygons.is_empty() {
+            return;
+        }
+        let plane = *self.plane.get_or_insert(polygons[0].plane);
+        let (mut front, mut back) = (Vec::new(), Vec::new());
+        let (mut cf, mut cb) = (Vec::new(), Vec::new());
+        for p in polygons {
+            split(&plane, p, eps, &mut cf, &mut cb, &mut front, &mut back);
+        }
+        self.polygons.append(&mut cf);
+        self.polygons.append(&mut cb);
+        if !front.is_empty() {
+            self.front.get_or_insert_with(|| Box::new(Node::new())).build(front, eps);
+        }
+        if !back.is_empty() {
+            self.back.get_or_insert_with(|| Box::new(Node::new())).build(back, eps);
+        }
+    }
+}
+
+#[test]
+fn csg_test() {
+    use super::fixture::cube;
+
+    let volume = |mesh: &Mesh<f64>| {
+        let v = mesh.verticies();
+        mesh.faces().iter().flat_map(|f| f.triangles()).map(|t| {
+            let (a, b, c) = (v[t[0].index()], v[t[1].index()], v[t[2].index()]);
+            a.x * (b.y * c.z - b.z * c.y) - a.y * (b.x * c.z - b.z * c.x) + a.z * (b.x * c.y - b.y * c.x)
+        }).sum::<f64>() / 6.
+    };
+    let (a, b) = (cube(0., 2.), cube(1., 3.));
+
+    let joined = union(&a, &b).unwrap();
+    assert!((volume(&joined) - 15.).abs() < 1e-9);
+    assert!(is_closed(&joined));
+
+    let common = intersection(&a, &b).unwrap();
+    assert!((volume(&common) - 1.).abs() < 1e-9);
+    assert!(is_closed(&common));
+
+    let cut = difference(&a, &b).unwrap();
+    assert!((volume(&cut) - 7.).abs() < 1e-9);
+    assert!(is_closed(&cut));
+
+    // coplanar faces: two cubes sitting side by side share a wall
+    let beside = union(&cube(0., 1.), &{ let mut c = cube(0., 1.); c.translate(Vec3::new(1., 0., 0.)); c }).unwrap();
+    assert!((volume(&beside) - 2.).abs() < 1e-9);
+    assert!(is_closed(&beside));
+
+    let mut open = cube(0., 1.);
+    open.set_faces(open.faces()[1..].to_vec());
+    assert_eq!(union(&open, &a).unwrap_err(), CsgError::LeftNotClosed);
+}
@@ -0,0 +1,257 @@
+use super::{Filter, Mesh, Primitive};
+use super::topology::boundary_verticies;
+use crate::core::{GeoNum, Point};
+use vek::Vec3;
+
+/// how a mesh bends at one vertex. Curvatures are positive where the surface bulges out towards its face normals
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Curvature<T> where T: GeoNum {
+    mean: T,
+    gaussian: T,
+    max: T,
+    min: T,
+    max_direction: Vec3<T>,
+    min_direction: Vec3<T>,
+}
+
+impl<T> Curvature<T> where T: GeoNum {
+    /// the average of the principal curvatures
+    pub fn mean(&self) -> T {
+        self.mean
+    }
+
+    /// the product of the principal curvatures. Positive on domes and bowls, negative on saddles and zero where
+    /// the surface could be unrolled flat
+    pub fn gaussian(&self) -> T {
+        self.gaussian
+    }
+
+    /// the larger principal curvature
+    pub fn max(&self) -> T {
+        self.max
+    }
+
+    /// the smaller principal curvature
+    pub fn min(&self) -> T {
+        self.min
+    }
+
+    /// unit tangent the surface bends most along
+    pub fn max_direction(&self) -> Vec3<T> {
+        self.max_direction
+    }
+
+    /// unit tangent the surface bends least along, at right angles to max_direction
+    pub fn min_direction(&self) -> Vec3<T> {
+        self.min_direction
+    }
+}
+
+impl<T> Mesh<T> where T: GeoNum {
+    /// estimates the curvature at every vertex after Meyer, Desbrun, Schröder and Barr. Mean curvature comes from
+    /// the cotangent Laplacian and Gaussian curvature from the angle defect, both over the mixed Voronoi area
+    /// around the vertex. Principal directions are fitted to the normal curvature along each edge leaving it.
+    /// Polygons are split into triangles, and verticies on open edges measure their angle defect from a half turn
+    pub fn curvature(&self) -> Vec<Curvature<T>> {
+        let points: Vec<Vec3<f64>> = self.verticies().iter().map(|v| {
+            let p = v.to_vec3();
+            Vec3::new(p.x.to_f64().unwrap(), p.y.to_f64().unwrap(), p.z.to_f64().unwrap())
+        }).collect();
+        let count = points.len();
+        let boundary = boundary_verticies(count, self.faces());
+
+        let mut area = vec![0.; count];
+        let mut angles = vec![0.; count];
+        let mut laplacian = vec![Vec3::<f64>::zero(); count];
+        let mut normals = vec![Vec3::<f64>::zero(); count];
+        let mut neighbours: Vec<Vec<usize>> = vec![Vec::new(); count];
+        for tri in self.faces().iter().flat_map(|f| f.triangles()) {
+            let i = [tri[0].index(), tri[1].index(), tri[2].index()];
+            let p = [points[i[0]], points[i[1]], points[i[2]]];
+            let normal = (p[1] - p[0]).cross(p[2] - p[0]);
+            let double_area = normal.magnitude();
+            if double_area == 0. {
+                continue;
+            }
+            let corner = |k: usize| {
+                let (a, b) = (p[(k + 1) % 3] - p[k], p[(k + 2) % 3] - p[k]);
+                (a.dot(b) / double_area, a.cross(b).magnitude().atan2(a.dot(b)))
+            };
+            let corners = [corner(0), corner(1), corner(2)];
+            let obtuse = (0..3).find(|&k| corners[k].0 < 0.);
+
+            for k in 0..3 {
+                let (v, a, b) = (i[k], (k + 1) % 3, (k + 2) % 3);
+                normals[v] += normal;
+                angles[v] += corners[k].1;
+                // the cotangent at each far corner weighs the edge opposite it
+                laplacian[v] += (p[k] - p[a]) * corners[b].0 + (p[k] - p[b]) * corners[a].0;
+                area[v] += match obtuse {
+                    None => ((p[k] - p[a]).magnitude_squared() * corners[b].0 + (p[k] - p[b]).magnitude_squared() * corners[a].0) / 8.,
+                    Some(o) if o == k => double_area / 4.,
+                    Some(_) => double_area / 8.,
+                };
+                for &n in [i[a], i[b]].iter() {
+                    if !neighbours[v].contains(&n) {
+                        neighbours[v].push(n);
+                    }
+                }
+            }
+        }
+
+        let t = |v: f64| T::from_f64(v).unwrap();
+        let t3 = |v: Vec3<f64>| Vec3::new(t(v.x), t(v.y), t(v.z));
+        (0..count).map(|v| {
+            let normal = if normals[v].magnitude_squared() > 0. { normals[v].normalized() } else { Vec3::unit_z() };
+            let (mean, gaussian) = if area[v] > 0. {
+                let full = if boundary[v] { std::f64::consts::PI } else { 2. * std::f64::consts::PI };
+                (laplacian[v].dot(normal) / (4. * area[v]), (full - angles[v]) / area[v])
+            } else {
+                (0., 0.)
+            };
+            let spread = (mean * mean - gaussian).max(0.).sqrt();
+            let (max_direction, min_direction) = principal_directions(&points, v, normal, &neighbours[v]);
+            Curvature {
+                mean: t(mean),
+                gaussian: t(gaussian),
+                max: t(mean + spread),
+                min: t(mean - spread),
+                max_direction: t3(max_direction),
+                min_direction: t3(min_direction),
+            }
+        }).collect()
+    }
+}
+
+/// fits a curvature tensor in the tangent plane to the normal curvature along each edge leaving the vertex, as
+/// Taubin does, and returns its eigenvectors largest first
+fn principal_directions(points: &[Vec3<f64>], v: usize, normal: Vec3<f64>, neighbours: &[usize]) -> (Vec3<f64>, Vec3<f64>) {
+    let u = if normal.x.abs() < 0.9 { Vec3::unit_x() } else { Vec3::unit_y() }.cross(normal).normalized();
+    let w = normal.cross(u);
+
+    // least squares for the tensor [a b; b c] from kappa = a cos² + 2b cos sin + c sin²
+    let mut normal_matrix = [[0.; 3]; 3];
+    let mut rhs = [0.; 3];
+    for &n in neighbours.iter() {
+        let edge = points[n] - points[v];
+        let length = edge.magnitude_squared();
+        let along = edge - normal * edge.dot(normal);
+        if length == 0. || along.magnitude_squared() == 0. {
+            continue;
+        }
+        let kappa = -2. * edge.dot(normal) / length;
+        let (cos, sin) = (along.dot(u), along.dot(w));
+        let norm = (cos * cos + sin * sin).sqrt();
+        let (cos, sin) = (cos / norm, sin / norm);
+        let row = [cos * cos, 2. * cos * sin, sin * sin];
+        for r in 0..3 {
+            for c in 0..3 {
+                normal_matrix[r][c] += row[r] * row[c];
+            }
+            rhs[r] += row[r] * kappa;
+        }
+    }
+    let (a, b, c) = match solve3(normal_matrix, rhs) {
+        Some(x) => (x[0], x[1], x[2]),
+        None => return (u, w),
+    };
+
+    // the larger eigenvalue's eigenvector of the symmetric 2x2 tensor
+    let angle = 0.5 * (2. * b).atan2(a - c);
+    let max = u * angle.cos() + w * angle.sin();
+    (max, normal.cross(max))
+}
+
+/// solves a 3x3 linear system by Cramer's rule, or None when it is singular
+fn solve3(m: [[f64; 3]; 3], b: [f64; 3]) -> Option<[f64; 3]> {
+    let det = |m: [[f64; 3]; 3]| {
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    };
+    let d = det(m);
+    let scale = m.iter().flatten().fold(0f64, |s, x| s.max(x.abs()));
+    if d.abs() <= 1e-12 * scale.powi(3) {
+        return None;
+    }
+    let mut x = [0.; 3];
+    for (k, xk) in x.iter_mut().enumerate() {
+        let mut mk = m;
+        for r in 0..3 {
+            mk[r][k] = b[r];
+        }
+        *xk = det(mk) / d;
+    }
+    Some(x)
+}
+
+/// filter that stores the curvature of every vertex in custom channels, named curvature_mean, curvature_gaussian,
+/// curvature_max and curvature_min, for effects such as weathering to read
+#[derive(Debug, Default)]
+pub struct CurvatureChannels;
+
+impl CurvatureChannels {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<T> Filter<T> for CurvatureChannels where T: GeoNum {
+    fn apply(&self, mesh: &mut Mesh<T>) {
+        let curvature = mesh.curvature();
+        mesh.set_channel("curvature_mean".into(), curvature.iter().map(|c| c.mean).collect());
+        mesh.set_channel("curvature_gaussian".into(), curvature.iter().map(|c| c.gaussian).collect());
+        mesh.set_channel("curvature_max".into(), curvature.iter().map(|c| c.max).collect());
+        mesh.set_channel("curvature_min".into(), curvature.iter().map(|c| c.min).collect());
+    }
+}
+
+#[test]
+fn curvature_test() {
+    use super::Face;
+    use crate::core::{PointIndex, Vertex};
+
+    // a grid wrapped round by the given function, its rows joined at the seam
+    let wrapped = |rows: usize, columns: usize, place: &dyn Fn(f64, f64) -> Vertex<f64>| {
+        let mut mesh = Mesh::<f64>::new();
+        for r in 0..=rows {
+            for c in 0..columns {
+                mesh.add_vertex(place(r as f64 / rows as f64, c as f64 / columns as f64));
+            }
+        }
+        for r in 0..rows {
+            for c in 0..columns {
+                let i = |r: usize, c: usize| PointIndex::new(r * columns + c % columns);
+                mesh.add_face(Face::new(vec![i(r, c), i(r, c + 1), i(r + 1, c + 1), i(r + 1, c)]));
+            }
+        }
+        mesh
+    };
+    let turn = 2. * std::f64::consts::PI;
+
+    // a tube of radius 2 bends by a half across it and not at all along it
+    let tube = wrapped(8, 48, &|h, a| Vertex::new(2. * (a * turn).cos(), 2. * (a * turn).sin(), h * 4.));
+    let curvature = tube.curvature();
+    let c = curvature[4 * 48 + 5];
+    assert!((c.mean() - 0.25).abs() < 0.01);
+    assert!(c.gaussian().abs() < 1e-9);
+    assert!((c.max() - 0.5).abs() < 0.02 && c.min().abs() < 0.02);
+    assert!(c.min_direction().z.abs() > 0.999);
+    assert!(c.max_direction().z.abs() < 0.01);
+
+    // a ball of radius 2 bends by a half every way, its latitude rings run between the poles
+    let ball = wrapped(32, 64, &|h, a| {
+        let (lat, lon) = ((h - 0.5) * std::f64::consts::PI, a * turn);
+        Vertex::new(2. * lat.cos() * lon.cos(), 2. * lat.cos() * lon.sin(), 2. * lat.sin())
+    });
+    let curvature = ball.curvature();
+    for &v in [12 * 64 + 3, 16 * 64 + 40, 20 * 64 + 17].iter() {
+        let c = curvature[v];
+        assert!((c.mean() - 0.5).abs() < 0.02);
+        assert!((c.gaussian() - 0.25).abs() < 0.02);
+    }
+
+    let mut channels = tube.clone();
+    CurvatureChannels::new().apply(&mut channels);
+    assert_eq!(channels.channel("curvature_mean").unwrap()[4 * 48 + 5], tube.curvature()[4 * 48 + 5].mean());
+}
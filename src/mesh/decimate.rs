@@ -0,0 +1,408 @@
+use super::{Face, Filter, Mesh, Primitive};
+use super::topology::edge_key;
+use crate::core::{GeoNum, PointIndex, Vertex};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use vek::Vec3;
+
+/// weight given to the planes that hold boundary edges in place, relative to ordinary face planes
+const BOUNDARY_WEIGHT: f64 = 1000.;
+
+/// settings for quadric error decimation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DecimateOptions<T> where T: GeoNum {
+    /// stop once the mesh has this many triangles or fewer
+    pub target_faces: usize,
+    /// stop before any collapse whose error would exceed this
+    pub max_error: Option<T>,
+    /// pin open edges so the outline of the mesh keeps its shape
+    pub preserve_boundary: bool,
+    /// never move verticies that sit on an attribute seam, where several verticies share a position
+    pub preserve_seams: bool,
+}
+
+impl<T> DecimateOptions<T> where T: GeoNum {
+    pub fn new(target_faces: usize) -> Self {
+        Self {
+            target_faces,
+            max_error: None,
+            preserve_boundary: true,
+            preserve_seams: true,
+        }
+    }
+}
+
+/// simplifies the mesh by collapsing edges in order of their quadric error (Garland & Heckbert).
+/// faces are triangulated first, so the result is always a triangle mesh. Returns the number of triangles removed
+pub fn decimate<T>(mesh: &mut Mesh<T>, options: &DecimateOptions<T>) -> usize where T: GeoNum {
+    let mut state = State::new(mesh, options);
+    let before = state.live;
+    let max_error = options.max_error.map(|e| e.to_f64().unwrap());
+
+    while state.live > options.target_faces {
+        let candidate = match state.heap.pop() {
+            Some(c) => c,
+            None => break,
+        };
+        if !state.current(&candidate) {
+            continue;
+        }
+        if max_error.map(|m| candidate.cost > m).unwrap_or(false) {
+            break;
+        }
+        state.collapse(&candidate);
+    }
+
+    let removed = before - state.live;
+    state.write(mesh);
+    removed
+}
+
+/// filter that decimates a mesh with the given options
+#[derive(Debug)]
+pub struct Decimate<T> where T: GeoNum {
+    options: DecimateOptions<T>,
+}
+
+impl<T> Decimate<T> where T: GeoNum {
+    pub fn new(options: DecimateOptions<T>) -> Self {
+        Self {
+            options
+        }
+    }
+}
+
+impl<T> Filter<T> for Decimate<T> where T: GeoNum {
+    fn apply(&self, mesh: &mut Mesh<T>) {
+        decimate(mesh, &self.options);
+    }
+}
+
+/// a symmetric 4x4 matrix stored as its upper triangle
+#[derive(Clone, Copy, Debug, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn plane(n: Vec3<f64>, d: f64, weight: f64) -> Self {
+        let (a, b, c) = (n.x, n.y, n.z);
+        Quadric([a * a, a * b, a * c, a * d, b * b, b * c, b * d, c * c, c * d, d * d].map(|x| x * weight))
+    }
+
+    fn add(&self, other: &Quadric) -> Quadric {
+        let mut q = self.0;
+        for (a, b) in q.iter_mut().zip(other.0.iter()) {
+            *a += b;
+        }
+        Quadric(q)
+    }
+
+    fn error(&self, v: Vec3<f64>) -> f64 {
+        let q = &self.0;
+        q[0] * v.x * v.x + 2. * q[1] * v.x * v.y + 2. * q[2] * v.x * v.z + 2. * q[3] * v.x
+            + q[4] * v.y * v.y + 2. * q[5] * v.y * v.z + 2. * q[6] * v.y
+            + q[7] * v.z * v.z + 2. * q[8] * v.z + q[9]
+    }
+
+    /// the position that minimises the error, if the quadric is well conditioned enough to have one
+    fn optimum(&self) -> Option<Vec3<f64>> {
+        let q = &self.0;
+        let (a, b, c, d, e, f) = (q[0], q[1], q[2], q[4], q[5], q[7]);
+        let det = a * (d * f - e * e) - b * (b * f - e * c) + c * (b * e - d * c);
+        let scale = a.abs() + d.abs() + f.abs();
+        if det.abs() <= 1e-10 * scale * scale * scale || scale == 0. {
+            return None;
+        }
+        let (x, y, z) = (-q[3], -q[6], -q[8]);
+        Some(Vec3::new(
+            (x * (d * f - e * e) - b * (y * f - e * z) + c * (y * e - d * z)) / det,
+            (a * (y * f - e * z) - x * (b * f - e * c) + c * (b * z - y * c)) / det,
+            (a * (d * z - y * e) - b * (b * z - y * c) + x * (b * e - d * c)) / det,
+        ))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Candidate {
+    cost: f64,
+    keep: usize,
+    remove: usize,
+    position: Vec3<f64>,
+    stamps: (usize, usize),
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost.total_cmp(&other.cost) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    /// reversed so the binary heap pops the cheapest collapse first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+struct State {
+    positions: Vec<Vec3<f64>>,
+    quadrics: Vec<Quadric>,
+    locked: Vec<bool>,
+    stamps: Vec<usize>,
+    tris: Vec<[usize; 3]>,
+    alive: Vec<bool>,
+    vertex_tris: Vec<Vec<usize>>,
+    heap: BinaryHeap<Candidate>,
+    live: usize,
+}
+
+impl State {
+    fn new<T: GeoNum>(mesh: &Mesh<T>, options: &DecimateOptions<T>) -> Self {
+        let positions: Vec<Vec3<f64>> = mesh.verticies().iter()
+            .map(|v| Vec3::new(v.x.to_f64().unwrap(), v.y.to_f64().unwrap(), v.z.to_f64().unwrap()))
+            .collect();
+        let tris: Vec<[usize; 3]> = mesh.faces().iter()
+            .flat_map(|f| f.triangles())
+            .map(|t| [t[0].index(), t[1].index(), t[2].index()])
+            .filter(|t| t[0] != t[1] && t[1] != t[2] && t[0] != t[2])
+            .collect();
+
+        let mut state = Self {
+            quadrics: vec![Quadric::default(); positions.len()],
+            locked: vec![false; positions.len()],
+            stamps: vec![0; positions.len()],
+            alive: vec![true; tris.len()],
+            vertex_tris: vec![Vec::new(); positions.len()],
+            heap: BinaryHeap::new(),
+            live: tris.len(),
+            positions,
+            tris,
+        };
+
+        let mut edge_uses: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (t, tri) in state.tris.iter().enumerate() {
+            let normal = state.normal(tri, None);
+            let d = -normal.dot(state.positions[tri[0]]);
+            let plane = Quadric::plane(normal, d, 1.);
+            for &v in tri.iter() {
+                state.quadrics[v] = state.quadrics[v].add(&plane);
+                state.vertex_tris[v].push(t);
+            }
+            for i in 0..3 {
+                edge_uses.entry(edge_key(tri[i], tri[(i + 1) % 3])).or_default().push(t);
+            }
+        }
+
+        let boundary: Vec<(usize, usize, usize)> = edge_uses.iter()
+            .filter(|(_, uses)| uses.len() == 1)
+            .map(|(e, uses)| (e.0, e.1, uses[0]))
+            .collect();
+
+        if options.preserve_boundary {
+            for &(a, b, t) in boundary.iter() {
+                let normal = state.normal(&state.tris[t], None);
+                let edge = state.positions[b] - state.positions[a];
+                let side = edge.cross(normal);
+                let length = side.magnitude();
+                if length > 0. {
+                    let side = side / length;
+                    let plane = Quadric::plane(side, -side.dot(state.positions[a]), BOUNDARY_WEIGHT * edge.magnitude_squared());
+                    state.quadrics[a] = state.quadrics[a].add(&plane);
+                    state.quadrics[b] = state.quadrics[b].add(&plane);
+                }
+            }
+        }
+
+        if options.preserve_seams {
+            // a seam vertex is an open-edge vertex that shares its position with another vertex
+            let mut by_position: HashMap<[u64; 3], Vec<usize>> = HashMap::new();
+            for &(a, b, _) in boundary.iter() {
+                for v in [a, b].iter() {
+                    let p = state.positions[*v];
+                    by_position.entry([p.x.to_bits(), p.y.to_bits(), p.z.to_bits()]).or_default().push(*v);
+                }
+            }
+            for group in by_position.values() {
+                let mut unique = group.clone();
+                unique.sort_unstable();
+                unique.dedup();
+                if unique.len() > 1 {
+                    for v in unique {
+                        state.locked[v] = true;
+                    }
+                }
+            }
+        }
+
+        let edges: Vec<(usize, usize)> = edge_uses.keys().copied().collect();
+        for (a, b) in edges {
+            state.push(a, b);
+        }
+        state
+    }
+
+    fn normal(&self, tri: &[usize; 3], replace: Option<(usize, Vec3<f64>)>) -> Vec3<f64> {
+        let p = |v: usize| match replace {
+            Some((r, pos)) if r == v => pos,
+            _ => self.positions[v],
+        };
+        let n = (p(tri[1]) - p(tri[0])).cross(p(tri[2]) - p(tri[0]));
+        let length = n.magnitude();
+        if length > 0. { n / length } else { n }
+    }
+
+    fn push(&mut self, a: usize, b: usize) {
+        let (keep, remove) = match (self.locked[a], self.locked[b]) {
+            (true, true) => return,
+            (false, true) => (b, a),
+            _ => (a, b),
+        };
+        let q = self.quadrics[keep].add(&self.quadrics[remove]);
+        let position = if self.locked[keep] {
+            self.positions[keep]
+        } else {
+            let (pa, pb) = (self.positions[keep], self.positions[remove]);
+            let mut options = vec![pa, pb, (pa + pb) / 2.];
+            if let Some(optimum) = q.optimum() {
+                options.insert(0, optimum);
+            }
+            options.into_iter()
+                .min_by(|x, y| q.error(*x).total_cmp(&q.error(*y)))
+                .unwrap()
+        };
+        self.heap.push(Candidate {
+            cost: q.error(position).max(0.),
+            keep,
+            remove,
+            position,
+            stamps: (self.stamps[keep], self.stamps[remove]),
+        });
+    }
+
+    fn current(&self, c: &Candidate) -> bool {
+        self.stamps[c.keep] == c.stamps.0 && self.stamps[c.remove] == c.stamps.1
+    }
+
+    fn neighbours(&self, v: usize) -> HashSet<usize> {
+        self.vertex_tris[v].iter()
+            .flat_map(|&t| self.tris[t].iter().copied())
+            .filter(|&n| n != v)
+            .collect()
+    }
+
+    fn collapse(&mut self, c: &Candidate) {
+        let (keep, remove) = (c.keep, c.remove);
+        let shared: Vec<usize> = self.vertex_tris[remove].iter()
+            .copied()
+            .filter(|t| self.tris[*t].contains(&keep))
+            .collect();
+
+        // link condition: the two ends may only share the neighbours opposite the edge, or the surface pinches
+        let opposite: HashSet<usize> = shared.iter()
+            .flat_map(|&t| self.tris[t].iter().copied())
+            .filter(|&v| v != keep && v != remove)
+            .collect();
+        let common: HashSet<usize> = self.neighbours(keep).intersection(&self.neighbours(remove)).copied().collect();
+        if common != opposite {
+            return;
+        }
+
+        // refuse collapses that would flip a surviving triangle over
+        for &v in [keep, remove].iter() {
+            for &t in self.vertex_tris[v].iter() {
+                if shared.contains(&t) {
+                    continue;
+                }
+                let before = self.normal(&self.tris[t], None);
+                let mut moved = self.tris[t];
+                for x in moved.iter_mut() {
+                    if *x == remove {
+                        *x = keep;
+                    }
+                }
+                let after = self.normal(&moved, Some((keep, c.position)));
+                if before.dot(after) <= 0. {
+                    return;
+                }
+            }
+        }
+
+        for &t in shared.iter() {
+            self.alive[t] = false;
+            self.live -= 1;
+            for &v in self.tris[t].iter() {
+                self.vertex_tris[v].retain(|x| *x != t);
+            }
+        }
+        let moved: Vec<usize> = self.vertex_tris[remove].drain(..).collect();
+        for &t in moved.iter() {
+            for x in self.tris[t].iter_mut() {
+                if *x == remove {
+                    *x = keep;
+                }
+            }
+            self.vertex_tris[keep].push(t);
+        }
+
+        self.positions[keep] = c.position;
+        self.quadrics[keep] = self.quadrics[keep].add(&self.quadrics[remove]);
+        self.stamps[keep] += 1;
+        self.stamps[remove] += 1;
+
+        for n in self.neighbours(keep) {
+            self.stamps[n] += 1;
+        }
+        for n in self.neighbours(keep) {
+            for m in self.neighbours(n) {
+                self.push(n, m);
+            }
+        }
+    }
+
+    fn write<T: GeoNum>(self, mesh: &mut Mesh<T>) {
+        for (v, p) in self.positions.iter().enumerate() {
+            if let Some(vertex) = mesh.vertex_mut(PointIndex::new(v)) {
+                *vertex = Vertex::new(T::from_f64(p.x).unwrap(), T::from_f64(p.y).unwrap(), T::from_f64(p.z).unwrap());
+            }
+        }
+        let faces = self.tris.iter().zip(self.alive.iter())
+            .filter(|(_, alive)| **alive)
+            .map(|(t, _)| Face::new(t.iter().map(|&v| PointIndex::new(v)).collect()))
+            .collect();
+        mesh.set_faces(faces);
+        mesh.remove_unused_verticies();
+    }
+}
+
+#[test]
+fn decimate_test() {
+    // a finely divided flat grid should fold down to very few triangles without moving off the plane
+    let mut mesh = Mesh::<f64>::new();
+    let n = 10;
+    for y in 0..=n {
+        for x in 0..=n {
+            mesh.add_vertex(Vertex::new(x as f64, y as f64, 0.));
+        }
+    }
+    for y in 0..n {
+        for x in 0..n {
+            let i = y * (n + 1) + x;
+            mesh.add_face(Face::new(vec![PointIndex::new(i), PointIndex::new(i + 1), PointIndex::new(i + n + 2), PointIndex::new(i + n + 1)]));
+        }
+    }
+
+    let removed = decimate(&mut mesh, &DecimateOptions::new(20));
+    assert!(removed >= 180);
+    assert!(mesh.faces().len() <= 20);
+    assert!(mesh.verticies().iter().all(|v| v.z == 0.));
+    // corners of the boundary must survive
+    assert!(mesh.verticies().contains(&Vertex::new(10., 10., 0.)));
+    assert!(super::validate(&mesh).is_valid());
+}
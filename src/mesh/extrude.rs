@@ -0,0 +1,165 @@
+use super::{Face, FaceIndex, Mesh, Primitive};
+use super::topology::face_edges;
+use crate::core::{GeoNum, Point, PointIndex, Vertex};
+use std::collections::{HashMap, HashSet};
+use vek::Vec3;
+
+/// which way extruded faces travel
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExtrudeDirection<T> where T: GeoNum {
+    /// along the averaged normal of the selected faces at each vertex, by the given distance
+    Normal(T),
+    /// every vertex by the same offset
+    Vector(Vec3<T>),
+}
+
+/// pulls a set of faces out of the mesh, joining them back to where they came from with side walls.
+/// Faces that share edges move together as one region, so only the region's outline gets walls.
+/// The extruded faces keep their indices; the indices of the new side walls are returned
+pub fn extrude<T>(mesh: &mut Mesh<T>, faces: &[FaceIndex], direction: ExtrudeDirection<T>) -> Vec<FaceIndex>
+    where T: GeoNum
+{
+    let selected: HashSet<usize> = faces.iter().map(|f| f.index()).collect();
+    let mut all = mesh.faces().clone();
+
+    let mut directed = HashSet::new();
+    for &f in selected.iter() {
+        directed.extend(face_edges(&all[f]));
+    }
+    let outline: Vec<(usize, usize)> = selected.iter()
+        .flat_map(|&f| face_edges(&all[f]))
+        .filter(|&(a, b)| !directed.contains(&(b, a)))
+        .collect();
+
+    // verticies on the outline or shared with faces that stay put are copied, the rest simply move
+    let mut stays = vec![false; mesh.verticies().len()];
+    for &(a, b) in outline.iter() {
+        stays[a] = true;
+        stays[b] = true;
+    }
+    for (f, face) in all.iter().enumerate() {
+        if !selected.contains(&f) {
+            for v in face.verticies() {
+                stays[v.index()] = true;
+            }
+        }
+    }
+
+    let mut normals: HashMap<usize, Vec3<T>> = HashMap::new();
+    for &f in selected.iter() {
+        let n = mesh.newell(&all[f]);
+        for v in all[f].verticies() {
+            let sum = normals.entry(v.index()).or_insert_with(Vec3::zero);
+            *sum = *sum + n;
+        }
+    }
+
+    let mut moved: HashMap<usize, usize> = HashMap::new();
+    let mut order: Vec<usize> = normals.keys().copied().collect();
+    order.sort_unstable();
+    for v in order {
+        let offset = match direction {
+            ExtrudeDirection::Normal(distance) => {
+                let n = normals[&v];
+                let length = n.magnitude();
+                if length > T::zero() { n * (distance / length) } else { Vec3::zero() }
+            },
+            ExtrudeDirection::Vector(offset) => offset,
+        };
+        let position = Vertex::from_vec3(mesh.verticies()[v].to_vec3() + offset);
+        let index = if stays[v] {
+            mesh.duplicate_vertex(PointIndex::new(v), position).index()
+        } else {
+            *mesh.vertex_mut(PointIndex::new(v)).unwrap() = position;
+            v
+        };
+        moved.insert(v, index);
+    }
+
+    for &f in selected.iter() {
+        all[f] = Face::new(all[f].verticies().iter().map(|v| PointIndex::new(moved[&v.index()])).collect());
+    }
+    let mut created = Vec::new();
+    for (a, b) in outline {
+        created.push(FaceIndex::new(all.len()));
+        all.push(Face::new(vec![PointIndex::new(a), PointIndex::new(b), PointIndex::new(moved[&b]), PointIndex::new(moved[&a])]));
+    }
+    mesh.set_faces(all);
+    created
+}
+
+/// shrinks each of the faces inside itself, keeping its edges the given distance from the originals, and fills the
+/// gap with a ring of new faces. Each face is inset on its own. The inset faces keep their indices; the indices of
+/// the new ring faces are returned
+pub fn inset<T>(mesh: &mut Mesh<T>, faces: &[FaceIndex], thickness: T) -> Vec<FaceIndex>
+    where T: GeoNum
+{
+    let mut all = mesh.faces().clone();
+    let mut created = Vec::new();
+    for f in faces.iter().map(|f| f.index()) {
+        let verts = all[f].verticies();
+        let n = verts.len();
+        let normal = mesh.face_normal(&all[f]);
+        let points: Vec<Vec3<T>> = verts.iter().map(|v| mesh.verticies()[v.index()].to_vec3()).collect();
+
+        let inner: Vec<PointIndex> = (0..n).map(|i| {
+            let (prev, here, next) = (points[(i + n - 1) % n], points[i], points[(i + 1) % n]);
+            let offset = mitre(normal, here - prev, next - here, thickness);
+            mesh.duplicate_vertex(verts[i], Vertex::from_vec3(here + offset))
+        }).collect();
+
+        for i in 0..n {
+            let j = (i + 1) % n;
+            created.push(FaceIndex::new(all.len()));
+            all.push(Face::new(vec![verts[i], verts[j], inner[j], inner[i]]));
+        }
+        all[f] = Face::new(inner);
+    }
+    mesh.set_faces(all);
+    created
+}
+
+/// the offset of a corner that keeps both of its edges the given distance further inside the face
+pub(crate) fn mitre<T: GeoNum>(normal: Vec3<T>, incoming: Vec3<T>, outgoing: Vec3<T>, distance: T) -> Vec3<T> {
+    let n1 = normal.cross(incoming).normalized();
+    let n2 = normal.cross(outgoing).normalized();
+    let bisector = n1 + n2;
+    let denominator = T::one() + n1.dot(n2);
+    if denominator <= T::epsilon() {
+        return n1 * distance;
+    }
+    bisector * (distance / denominator)
+}
+
+#[test]
+fn extrude_inset_test() {
+    use crate::geom::Area;
+
+    // two quads side by side in the xy plane
+    let mut mesh = Mesh::<f64>::new();
+    for y in 0..2 {
+        for x in 0..3 {
+            mesh.add_vertex(Vertex::new(x as f64, y as f64, 0.));
+        }
+    }
+    let quad = |v: [usize; 4]| Face::new(v.iter().map(|&i| PointIndex::new(i)).collect());
+    mesh.add_face(quad([0, 1, 4, 3]));
+    mesh.add_face(quad([1, 2, 5, 4]));
+
+    let walls = extrude(&mut mesh, &[FaceIndex::new(0), FaceIndex::new(1)], ExtrudeDirection::Normal(2.));
+    // the shared edge between the quads gets no wall
+    assert_eq!(walls.len(), 6);
+    assert_eq!(mesh.verticies().len(), 12);
+    for v in mesh.faces()[0].verticies() {
+        assert_eq!(mesh.verticies()[v.index()].z, 2.);
+    }
+    // an open box: two 1 x 1 lids and six walls of height 2
+    assert!((mesh.area() - 14.).abs() < 1e-12);
+    assert!(super::validate(&mesh).is_valid());
+
+    let ring = inset(&mut mesh, &[FaceIndex::new(0)], 0.25);
+    assert_eq!(ring.len(), 4);
+    assert!((mesh.face_area(&mesh.faces()[0]) - 0.25).abs() < 1e-12);
+    assert!((mesh.area() - 14.).abs() < 1e-12);
+    assert!(super::validate(&mesh).is_valid());
+}
@@ -38,6 +38,29 @@ impl Face {
     pub fn consume(self) -> Vec<PointIndex> {
         self.verts
     }
+
+    /// number of corners in this face
+    pub fn len(&self) -> usize {
+        self.verts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.verts.is_empty()
+    }
+
+    /// reverses the winding of the face
+    pub fn flip(&mut self) {
+        self.verts.reverse();
+    }
+
+    /// splits the face into a fan of triangles around its first corner. Only exact for convex faces
+    pub fn triangles(&self) -> Vec<[PointIndex; 3]> {
+        let mut tris = Vec::new();
+        for i in 1..self.verts.len().saturating_sub(1) {
+            tris.push([self.verts[0], self.verts[i], self.verts[i + 1]]);
+        }
+        tris
+    }
 }
 
 impl<Ix: IndexType> FaceIndex<Ix> {
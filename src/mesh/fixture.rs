@@ -0,0 +1,25 @@
+use super::{Face, Mesh};
+use crate::core::{PointIndex, Vertex};
+use vek::Vec3;
+
+pub(crate) use super::csg::is_closed as closed;
+
+/// an axis aligned box from min to max with its six quads facing outwards. Vertex i takes its x, y and z from
+/// bits 1, 2 and 4 of i, and the quads run bottom, top, then the sides at y = min, y = max, x = min and x = max
+pub(crate) fn cuboid(min: Vec3<f64>, max: Vec3<f64>) -> Mesh<f64> {
+    let mut mesh = Mesh::new();
+    for i in 0..8 {
+        let pick = |bit: usize, min: f64, max: f64| if i & bit == 0 { min } else { max };
+        mesh.add_vertex(Vertex::new(pick(1, min.x, max.x), pick(2, min.y, max.y), pick(4, min.z, max.z)));
+    }
+    let quads = [[0, 2, 3, 1], [4, 5, 7, 6], [0, 1, 5, 4], [2, 6, 7, 3], [0, 4, 6, 2], [1, 3, 7, 5]];
+    for q in quads.iter() {
+        mesh.add_face(Face::new(q.iter().map(|&i| PointIndex::new(i)).collect()));
+    }
+    mesh
+}
+
+/// a cube spanning min to max along every axis, laid out as cuboid
+pub(crate) fn cube(min: f64, max: f64) -> Mesh<f64> {
+    cuboid(Vec3::broadcast(min), Vec3::broadcast(max))
+}
@@ -0,0 +1,431 @@
+use super::{Mesh, Primitive, SurfacePoint};
+use crate::core::{GeoNum, PointIndex};
+use crate::geom::Polyline;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use vek::Vec3;
+
+/// distances measured along the surface of a mesh from a set of sources, found by fast marching. Sources are
+/// verticies or points anywhere on the surface. Each triangle's update unfolds the two known corners into a virtual
+/// point source, so distances across flat and gently curved stretches come out close to exact rather than following
+/// the zig zag of the edges
+#[derive(Clone, Debug)]
+pub struct GeodesicField<T> where T: GeoNum {
+    points: Vec<Vec3<f64>>,
+    triangles: Vec<[usize; 3]>,
+    adjacent: Vec<Vec<usize>>,
+    distances: Vec<f64>,
+    /// sources that lie inside a triangle, with where they are in it
+    inner: Vec<(usize, Vec3<f64>)>,
+    marker: std::marker::PhantomData<T>,
+}
+
+/// where a traced path is on the surface
+#[derive(Clone, Copy, Debug)]
+enum Location {
+    Vertex(usize),
+    /// part way from the first vertex to the second
+    Edge(usize, usize, f64),
+    /// inside a triangle, weighted over its corners
+    Face(usize, [f64; 3]),
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Trial {
+    distance: f64,
+    vertex: usize,
+}
+
+impl PartialEq for Trial {
+    fn eq(&self, other: &Self) -> bool {
+        self.distance.total_cmp(&other.distance) == Ordering::Equal
+    }
+}
+
+impl Eq for Trial {}
+
+impl PartialOrd for Trial {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Trial {
+    /// reversed so the binary heap pops the nearest vertex first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.distance.total_cmp(&self.distance)
+    }
+}
+
+impl<T> GeodesicField<T> where T: GeoNum {
+    /// marches outward from the source verticies over a snapshot of the mesh. Polygons are split into triangles
+    pub fn new(mesh: &Mesh<T>, sources: &[PointIndex]) -> Self {
+        let mut field = Self::snapshot(mesh);
+        let count = field.points.len();
+        let seeds: Vec<(usize, f64)> = sources.iter().filter(|s| s.index() < count).map(|s| (s.index(), 0.)).collect();
+        field.march(seeds);
+        field
+    }
+
+    /// marches outward from points on the surface, such as those found by SurfaceQuery. Points whose triangle is
+    /// not part of the mesh are ignored
+    pub fn from_surface_points(mesh: &Mesh<T>, sources: &[SurfacePoint<T>]) -> Self {
+        let mut field = Self::snapshot(mesh);
+        let mut seeds = Vec::new();
+        for source in sources.iter() {
+            if let Some(Location::Face(t, beta)) = field.locate(source) {
+                let point = field.position(Location::Face(t, beta));
+                for &c in field.triangles[t].iter() {
+                    seeds.push((c, field.points[c].distance(point)));
+                }
+                field.inner.push((t, point));
+            }
+        }
+        field.march(seeds);
+        field
+    }
+
+    fn snapshot(mesh: &Mesh<T>) -> Self {
+        let points: Vec<Vec3<f64>> = mesh.verticies().iter()
+            .map(|p| Vec3::new(p.x.to_f64().unwrap(), p.y.to_f64().unwrap(), p.z.to_f64().unwrap()))
+            .collect();
+        let triangles: Vec<[usize; 3]> = mesh.faces().iter()
+            .flat_map(|f| f.triangles())
+            .map(|t| [t[0].index(), t[1].index(), t[2].index()])
+            .filter(|t| t[0] != t[1] && t[1] != t[2] && t[2] != t[0])
+            .collect();
+        let mut adjacent = vec![Vec::new(); points.len()];
+        for (t, tri) in triangles.iter().enumerate() {
+            for &v in tri.iter() {
+                adjacent[v].push(t);
+            }
+        }
+        Self {
+            distances: vec![f64::INFINITY; points.len()],
+            points,
+            triangles,
+            adjacent,
+            inner: Vec::new(),
+            marker: std::marker::PhantomData,
+        }
+    }
+
+    /// finds the triangle a surface point lies in, with its weights in that triangle's corner order
+    fn locate(&self, at: &SurfacePoint<T>) -> Option<Location> {
+        let corners = at.triangle();
+        let weights = at.barycentric();
+        let first = self.adjacent.get(corners[0].index())?;
+        let t = first.iter().copied().find(|&t| corners.iter().all(|c| self.triangles[t].contains(&c.index())))?;
+        let mut beta = [0.; 3];
+        for (k, b) in beta.iter_mut().enumerate() {
+            let w = [weights.x, weights.y, weights.z][corners.iter().position(|c| c.index() == self.triangles[t][k]).unwrap()];
+            *b = w.to_f64().unwrap();
+        }
+        Some(Location::Face(t, beta))
+    }
+
+    fn march(&mut self, seeds: Vec<(usize, f64)>) {
+        let mut accepted = vec![false; self.points.len()];
+        let mut heap = BinaryHeap::new();
+        for (vertex, distance) in seeds {
+            if distance < self.distances[vertex] {
+                self.distances[vertex] = distance;
+                heap.push(Trial { distance, vertex });
+            }
+        }
+
+        while let Some(Trial { distance, vertex }) = heap.pop() {
+            if accepted[vertex] || distance > self.distances[vertex] {
+                continue;
+            }
+            accepted[vertex] = true;
+            for &t in self.adjacent[vertex].iter() {
+                let tri = self.triangles[t];
+                for &c in tri.iter().filter(|&&c| c != vertex && !accepted[c]) {
+                    let other = tri.iter().copied().find(|&o| o != vertex && o != c).unwrap();
+                    let mut best = distance + self.points[vertex].distance(self.points[c]);
+                    if accepted[other] {
+                        best = best.min(self.unfold(vertex, other, c));
+                    }
+                    if best < self.distances[c] {
+                        self.distances[c] = best;
+                        heap.push(Trial { distance: best, vertex: c });
+                    }
+                }
+            }
+        }
+    }
+
+    /// distance to c through the triangle abc, treating a and b as lit by a single point source laid flat in the
+    /// triangle's plane. Infinite when the straight line from that source to c misses the edge ab
+    fn unfold(&self, a: usize, b: usize, c: usize) -> f64 {
+        let (pa, pb, pc) = (self.points[a], self.points[b], self.points[c]);
+        let (da, db) = (self.distances[a], self.distances[b]);
+        let length = pa.distance(pb);
+        if length == 0. {
+            return f64::INFINITY;
+        }
+        let axis = (pb - pa) / length;
+        let cx = (pc - pa).dot(axis);
+        let cy = ((pc - pa) - axis * cx).magnitude();
+        let sx = (da * da - db * db + length * length) / (2. * length);
+        let sy2 = da * da - sx * sx;
+        if cy == 0. || sy2 < 0. {
+            return f64::INFINITY;
+        }
+        let sy = -sy2.sqrt();
+        let crossing = sx + (cx - sx) * (-sy / (cy - sy));
+        if crossing < 0. || crossing > length {
+            return f64::INFINITY;
+        }
+        ((cx - sx).powi(2) + (cy - sy).powi(2)).sqrt()
+    }
+
+    /// distance along the surface from the vertex to the nearest source, or infinity if none can be reached.
+    /// None when the vertex is not part of the mesh
+    pub fn distance(&self, vertex: PointIndex) -> Option<T> {
+        self.distances.get(vertex.index()).map(|&d| T::from_f64(d).unwrap())
+    }
+
+    /// the distance of every vertex, in vertex order
+    pub fn distances(&self) -> Vec<T> {
+        self.distances.iter().map(|&d| T::from_f64(d).unwrap()).collect()
+    }
+
+    /// the shortest path over the surface from the vertex to its nearest source, found by walking straight downhill
+    /// across each triangle. None when the vertex is not part of the mesh or no source can be reached
+    pub fn path(&self, from: PointIndex) -> Option<Polyline<T>> {
+        if from.index() >= self.points.len() {
+            return None;
+        }
+        self.trace(Location::Vertex(from.index()))
+    }
+
+    /// the shortest path over the surface from a point on it to its nearest source. None when the point's triangle
+    /// is not part of the mesh or no source can be reached
+    pub fn path_from(&self, from: &SurfacePoint<T>) -> Option<Polyline<T>> {
+        self.trace(self.locate(from)?)
+    }
+
+    fn trace(&self, start: Location) -> Option<Polyline<T>> {
+        let reachable = match start {
+            Location::Vertex(v) => self.distances[v].is_finite(),
+            _ => self.triangles_around(start).iter().any(|&t| self.triangles[t].iter().all(|&v| self.distances[v].is_finite())),
+        };
+        if !reachable {
+            return None;
+        }
+        let mut location = start;
+        let mut path = vec![self.position(location)];
+        for _ in 0..4 * (self.points.len() + self.triangles.len()) {
+            if let Location::Vertex(v) = location {
+                if self.distances[v] == 0. {
+                    break;
+                }
+            }
+            // once in a triangle holding a source, the rest of the way is a straight line
+            let around = self.triangles_around(location);
+            if let Some(&(_, point)) = self.inner.iter().find(|(t, _)| around.contains(t)) {
+                if path.last() != Some(&point) {
+                    path.push(point);
+                }
+                break;
+            }
+            location = match self.descend(location) {
+                Some(next) => next,
+                None => match self.slide(location) {
+                    Some(next) => next,
+                    None => break,
+                },
+            };
+            let point = self.position(location);
+            if path.last() != Some(&point) {
+                path.push(point);
+            }
+        }
+        let t = |v: f64| T::from_f64(v).unwrap();
+        Some(Polyline::new(path.iter().map(|p| Vec3::new(t(p.x), t(p.y), t(p.z))).collect(), false))
+    }
+
+    fn position(&self, location: Location) -> Vec3<f64> {
+        match location {
+            Location::Vertex(v) => self.points[v],
+            Location::Edge(a, b, t) => self.points[a] * (1. - t) + self.points[b] * t,
+            Location::Face(t, beta) => (0..3).fold(Vec3::zero(), |p, k| p + self.points[self.triangles[t][k]] * beta[k]),
+        }
+    }
+
+    /// the triangles a location touches
+    fn triangles_around(&self, location: Location) -> Vec<usize> {
+        match location {
+            Location::Vertex(v) => self.adjacent[v].clone(),
+            Location::Edge(a, b, _) => self.adjacent[a].iter().copied().filter(|&t| self.triangles[t].contains(&b)).collect(),
+            Location::Face(t, _) => vec![t],
+        }
+    }
+
+    /// follows the steepest way down through whichever triangle around the location it leads into, returning where
+    /// it leaves that triangle
+    fn descend(&self, location: Location) -> Option<Location> {
+        for t in self.triangles_around(location) {
+            let tri = self.triangles[t];
+            if tri.iter().any(|&v| !self.distances[v].is_finite()) {
+                continue;
+            }
+            let grads = match self.gradients(t) {
+                Some(g) => g,
+                None => continue,
+            };
+            let mut beta = [0.; 3];
+            for k in 0..3 {
+                beta[k] = match location {
+                    Location::Vertex(v) => if tri[k] == v { 1. } else { 0. },
+                    Location::Edge(a, b, s) => if tri[k] == a { 1. - s } else if tri[k] == b { s } else { 0. },
+                    Location::Face(_, weights) => weights[k],
+                };
+            }
+            let direction = -(0..3).fold(Vec3::zero(), |g, k| g + grads[k] * self.distances[tri[k]]);
+            let rates: Vec<f64> = grads.iter().map(|g| g.dot(direction)).collect();
+            let tolerance = 1e-12 * rates.iter().map(|r| r.abs()).sum::<f64>();
+            if tolerance == 0. || (0..3).any(|k| beta[k] <= 1e-12 && rates[k] < -tolerance) {
+                continue;
+            }
+            // walk until the first corner weight reaches zero
+            let step = (0..3)
+                .filter(|&k| rates[k] < -tolerance && beta[k] > 1e-12)
+                .map(|k| beta[k] / -rates[k])
+                .fold(f64::INFINITY, f64::min);
+            if !step.is_finite() || step <= 0. {
+                continue;
+            }
+            let mut next = [0.; 3];
+            for k in 0..3 {
+                next[k] = (beta[k] + rates[k] * step).max(0.);
+            }
+            let zeros: Vec<usize> = (0..3).filter(|&k| next[k] <= 1e-9).collect();
+            return Some(if zeros.len() >= 2 {
+                let k = (0..3).find(|k| !zeros.contains(k)).unwrap_or(0);
+                Location::Vertex(tri[k])
+            } else {
+                let k = zeros.first().copied().unwrap_or_else(|| {
+                    (0..3).min_by(|&a, &b| next[a].total_cmp(&next[b])).unwrap()
+                });
+                let (a, b) = ((k + 1) % 3, (k + 2) % 3);
+                Location::Edge(tri[a], tri[b], next[b] / (next[a] + next[b]))
+            });
+        }
+        None
+    }
+
+    /// where no triangle leads downhill, runs along an edge to a lower vertex instead
+    fn slide(&self, location: Location) -> Option<Location> {
+        match location {
+            Location::Vertex(v) => {
+                let lowest = self.adjacent[v].iter()
+                    .flat_map(|&t| self.triangles[t].iter().copied())
+                    .min_by(|&a, &b| self.distances[a].total_cmp(&self.distances[b]))?;
+                if self.distances[lowest] < self.distances[v] { Some(Location::Vertex(lowest)) } else { None }
+            },
+            Location::Edge(a, b, _) => {
+                Some(Location::Vertex(if self.distances[a] <= self.distances[b] { a } else { b }))
+            },
+            Location::Face(t, _) => {
+                self.triangles[t].iter().copied().min_by(|&a, &b| self.distances[a].total_cmp(&self.distances[b])).map(Location::Vertex)
+            },
+        }
+    }
+
+    /// gradients of the three corner weights of a triangle, or None if it has no area
+    fn gradients(&self, t: usize) -> Option<[Vec3<f64>; 3]> {
+        let tri = self.triangles[t];
+        let p = [self.points[tri[0]], self.points[tri[1]], self.points[tri[2]]];
+        let normal = (p[1] - p[0]).cross(p[2] - p[0]);
+        let double_area = normal.magnitude();
+        if double_area == 0. {
+            return None;
+        }
+        let unit = normal / double_area;
+        let mut grads = [Vec3::zero(); 3];
+        for (k, g) in grads.iter_mut().enumerate() {
+            *g = unit.cross(p[(k + 2) % 3] - p[(k + 1) % 3]) / double_area;
+        }
+        Some(grads)
+    }
+}
+
+/// the shortest path over the surface of the mesh from one vertex to another, or None if they are not connected
+pub fn geodesic_path<T>(mesh: &Mesh<T>, from: PointIndex, to: PointIndex) -> Option<Polyline<T>> where T: GeoNum {
+    reversed(GeodesicField::new(mesh, &[from]).path(to)?)
+}
+
+/// the shortest path over the surface of the mesh between two points on it, such as those found by SurfaceQuery,
+/// or None if they are not connected
+pub fn geodesic_path_between<T>(mesh: &Mesh<T>, from: &SurfacePoint<T>, to: &SurfacePoint<T>) -> Option<Polyline<T>>
+    where T: GeoNum
+{
+    reversed(GeodesicField::from_surface_points(mesh, &[*from]).path_from(to)?)
+}
+
+fn reversed<T: GeoNum>(path: Polyline<T>) -> Option<Polyline<T>> {
+    let mut points = path.points().to_vec();
+    points.reverse();
+    Some(Polyline::new(points, false))
+}
+
+#[test]
+fn geodesic_test() {
+    use super::Face;
+    use crate::core::Vertex;
+
+    let size = 20;
+    let mut mesh = Mesh::<f64>::new();
+    for y in 0..=size {
+        for x in 0..=size {
+            mesh.add_vertex(Vertex::new(x as f64, y as f64, 0.));
+        }
+    }
+    for y in 0..size {
+        for x in 0..size {
+            let i = y * (size + 1) + x;
+            mesh.add_face(Face::new(vec![PointIndex::new(i), PointIndex::new(i + 1), PointIndex::new(i + size + 2), PointIndex::new(i + size + 1)]));
+        }
+    }
+    let index = |x: usize, y: usize| PointIndex::new(y * (size + 1) + x);
+
+    // on a flat sheet surface distance is straight line distance
+    let field = GeodesicField::new(&mesh, &[index(10, 10)]);
+    for (v, d) in field.distances().iter().enumerate() {
+        let p = mesh.verticies()[v];
+        let straight = ((p.x - 10.).powi(2) + (p.y - 10.).powi(2)).sqrt();
+        assert!((d - straight).abs() <= straight * 0.02);
+    }
+
+    // with several sources each vertex measures to the nearest
+    let field = GeodesicField::new(&mesh, &[index(0, 0), index(20, 20)]);
+    assert_eq!(field.distance(index(20, 20)), Some(0.));
+    assert!((field.distance(index(17, 20)).unwrap() - 3.).abs() < 1e-9);
+    assert_eq!(field.distance(PointIndex::new(10_000)), None);
+    assert!(field.path(PointIndex::new(10_000)).is_none());
+
+    let path = geodesic_path(&mesh, index(0, 0), index(20, 13)).unwrap();
+    let ends = (path.points()[0], *path.points().last().unwrap());
+    assert_eq!((ends.0.x, ends.0.y, ends.1.x, ends.1.y), (0., 0., 20., 13.));
+    let straight = (20f64.powi(2) + 13f64.powi(2)).sqrt();
+    assert!((path.length() - straight).abs() < straight * 0.01);
+
+    // paths can start and end part way across faces
+    let query = super::SurfaceQuery::new(&mesh);
+    let (a, b) = (query.closest_point(Vec3::new(2.3, 1.6, 1.)).unwrap(), query.closest_point(Vec3::new(15.5, 17.25, -1.)).unwrap());
+    let path = geodesic_path_between(&mesh, &a, &b).unwrap();
+    assert_eq!(path.points()[0], Vec3::new(2.3, 1.6, 0.));
+    assert_eq!(*path.points().last().unwrap(), Vec3::new(15.5, 17.25, 0.));
+    let straight = Vec3::new(2.3, 1.6, 0.).distance(Vec3::new(15.5, 17.25, 0.));
+    assert!((path.length() - straight).abs() < straight * 0.01);
+
+    // a sheet folded up at a right angle is measured over the fold
+    let mut folded = mesh.clone();
+    folded.map_verts(|v| if v.x > 10. { Vertex::new(10., v.y, v.x - 10.) } else { *v });
+    let field = GeodesicField::new(&folded, &[index(0, 0)]);
+    let far = field.distance(index(20, 20)).unwrap();
+    assert!((far - 800f64.sqrt()).abs() < 800f64.sqrt() * 0.02);
+}
@@ -0,0 +1,344 @@
+use super::{Face, Filter, Mesh, Primitive};
+use super::topology::{edge_faces, edge_key, EdgeKey};
+use crate::core::{GeoNum, Point, PointIndex, Vertex};
+use std::collections::{HashMap, HashSet};
+use vek::Vec3;
+
+/// which holes to fill and how
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FillOptions<T> where T: GeoNum {
+    /// leave holes with more edges than this open
+    pub max_edges: Option<usize>,
+    /// leave holes longer than this all the way round open
+    pub max_perimeter: Option<T>,
+    /// refine the patch to the spacing of the hole's edges and relax it into a membrane, rather than spanning the
+    /// hole with as little area as the boundary allows. The membrane comes from 100 passes of uniform umbrella
+    /// smoothing with the boundary held still, so it meets the surface around it with a crease rather than
+    /// continuing its curvature
+    pub fair: bool,
+}
+
+impl<T> FillOptions<T> where T: GeoNum {
+    /// fills every hole with a fair patch
+    pub fn new() -> Self {
+        Self {
+            max_edges: None,
+            max_perimeter: None,
+            fair: true,
+        }
+    }
+}
+
+impl<T> Default for FillOptions<T> where T: GeoNum {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// every closed loop of open edges in the mesh. Each loop runs the way a face filling it would wind, against the
+/// faces around it. Open edges that do not close up into a loop are left out
+pub fn boundary_loops<T>(mesh: &Mesh<T>) -> Vec<Vec<PointIndex>> where T: GeoNum {
+    let mut next: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (key, uses) in edge_faces(mesh.faces()) {
+        if uses.len() != 1 {
+            continue;
+        }
+        let (a, b) = if uses[0].1 { (key.1, key.0) } else { (key.0, key.1) };
+        next.entry(a).or_default().push(b);
+    }
+
+    let mut starts: Vec<usize> = next.keys().copied().collect();
+    starts.sort_unstable();
+    let mut loops = Vec::new();
+    for start in starts {
+        // a vertex where several holes touch is left once for each
+        while let Some(first) = next.get_mut(&start).and_then(|n| n.pop()) {
+            let mut ring = vec![start];
+            let mut v = first;
+            while v != start {
+                ring.push(v);
+                v = match next.get_mut(&v).and_then(|n| n.pop()) {
+                    Some(n) => n,
+                    None => break,
+                };
+            }
+            if v == start {
+                loops.push(ring.into_iter().map(PointIndex::new).collect());
+            }
+        }
+    }
+    loops
+}
+
+/// closes the holes in the mesh with new faces, skipping any larger than the options allow. Holes with more than
+/// 256 edges are fanned from a new vertex at their centre. New verticies take the average attributes of the
+/// verticies around them. Returns how many holes were filled
+pub fn fill_holes<T>(mesh: &mut Mesh<T>, options: &FillOptions<T>) -> usize where T: GeoNum {
+    let mut filled = 0;
+    for ring in boundary_loops(mesh) {
+        if options.max_edges.map(|m| ring.len() > m).unwrap_or(false) {
+            continue;
+        }
+        let perimeter = (0..ring.len()).fold(T::zero(), |sum, i| {
+            let (a, b) = (mesh.verticies()[ring[i].index()], mesh.verticies()[ring[(i + 1) % ring.len()].index()]);
+            sum + (a.to_vec3() - b.to_vec3()).magnitude()
+        });
+        if options.max_perimeter.map(|m| perimeter > m).unwrap_or(false) {
+            continue;
+        }
+        fill_hole(mesh, &ring, options.fair);
+        filled += 1;
+    }
+    filled
+}
+
+/// holes with more edges than this are fanned from their centre rather than triangulated with the least area,
+/// which takes time cubic in the number of edges
+const MAX_OPTIMAL_EDGES: usize = 256;
+
+fn fill_hole<T: GeoNum>(mesh: &mut Mesh<T>, ring: &[PointIndex], fair: bool) {
+    let n = ring.len();
+    if n < 3 {
+        return;
+    }
+    let mut verts: Vec<usize> = ring.iter().map(|v| v.index()).collect();
+    let mut points: Vec<Vec3<f64>> = verts.iter().map(|&v| {
+        let p = mesh.verticies()[v].to_vec3();
+        Vec3::new(p.x.to_f64().unwrap(), p.y.to_f64().unwrap(), p.z.to_f64().unwrap())
+    }).collect();
+    let area = |p: &[Vec3<f64>], a: usize, b: usize, c: usize| (p[b] - p[a]).cross(p[c] - p[a]).magnitude() / 2.;
+    let t = |x: f64| T::from_f64(x).unwrap();
+
+    let mut triangles = Vec::new();
+    if n > MAX_OPTIMAL_EDGES {
+        let centre = points.iter().fold(Vec3::zero(), |sum, &p| sum + p) / n as f64;
+        let c = mesh.average_vertex(Vertex::new(t(centre.x), t(centre.y), t(centre.z)), ring);
+        verts.push(c.index());
+        points.push(centre);
+        triangles.extend((0..n).map(|i| [i, (i + 1) % n, n]));
+    } else {
+        // the triangulation of the loop with the least area, built up over ever longer runs of it (Barequet and Sharir)
+        let mut weight = vec![vec![0.; n]; n];
+        let mut split = vec![vec![0; n]; n];
+        for gap in 2..n {
+            for i in 0..n - gap {
+                let j = i + gap;
+                let (m, w) = (i + 1..j)
+                    .map(|m| (m, weight[i][m] + weight[m][j] + area(&points, i, m, j)))
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .unwrap();
+                weight[i][j] = w;
+                split[i][j] = m;
+            }
+        }
+        let mut stack = vec![(0, n - 1)];
+        while let Some((i, j)) = stack.pop() {
+            if j - i < 2 {
+                continue;
+            }
+            let m = split[i][j];
+            triangles.push([i, m, j]);
+            stack.push((i, m));
+            stack.push((m, j));
+        }
+    }
+
+    if fair {
+        let existing: HashSet<EdgeKey> = edge_faces(mesh.faces()).keys().copied().collect();
+        let spacing = (0..n).map(|i| points[i].distance(points[(i + 1) % n])).sum::<f64>() / n as f64;
+        let largest = spacing * spacing * 3f64.sqrt() / 4.;
+        for _ in 0..16 {
+            // split every triangle that is large for the spacing of the hole's edges at its centre
+            let mut grew = false;
+            let mut next = Vec::with_capacity(triangles.len());
+            for tri in triangles.iter() {
+                if area(&points, tri[0], tri[1], tri[2]) <= largest {
+                    next.push(*tri);
+                    continue;
+                }
+                let centre = (points[tri[0]] + points[tri[1]] + points[tri[2]]) / 3.;
+                let corners: Vec<PointIndex> = tri.iter().map(|&k| PointIndex::new(verts[k])).collect();
+                let v = mesh.average_vertex(Vertex::new(t(centre.x), t(centre.y), t(centre.z)), &corners);
+                let c = verts.len();
+                verts.push(v.index());
+                points.push(centre);
+                next.extend([[tri[0], tri[1], c], [tri[1], tri[2], c], [tri[2], tri[0], c]].iter());
+                grew = true;
+            }
+            triangles = next;
+            flip_edges(&mut triangles, &points, &verts, &existing);
+            if !grew {
+                break;
+            }
+        }
+
+        // relax the new verticies towards their neighbours while the loop holds still
+        let mut neighbours = vec![Vec::new(); points.len()];
+        for t in triangles.iter() {
+            for k in 0..3 {
+                let (a, b) = (t[k], t[(k + 1) % 3]);
+                if !neighbours[a].contains(&b) {
+                    neighbours[a].push(b);
+                    neighbours[b].push(a);
+                }
+            }
+        }
+        for _ in 0..100 {
+            let moved: Vec<Vec3<f64>> = (0..points.len()).map(|v| {
+                if v < n || neighbours[v].is_empty() {
+                    return points[v];
+                }
+                neighbours[v].iter().fold(Vec3::zero(), |sum, &u| sum + points[u]) / neighbours[v].len() as f64
+            }).collect();
+            points = moved;
+        }
+        let placed: HashMap<usize, Vertex<T>> = (n..points.len())
+            .map(|v| (verts[v], Vertex::new(t(points[v].x), t(points[v].y), t(points[v].z))))
+            .collect();
+        mesh.map_verts_indexed(|i, v| placed.get(&i).copied().unwrap_or(*v));
+    }
+
+    for t in triangles.iter() {
+        mesh.add_face(Face::new(t.iter().map(|&v| PointIndex::new(verts[v])).collect()));
+    }
+}
+
+/// flips edges inside the patch until every pair of triangles meets the Delaunay condition, so the refined patch
+/// keeps well shaped triangles. Edges that would duplicate one already in the mesh are left alone
+fn flip_edges(triangles: &mut [[usize; 3]], points: &[Vec3<f64>], verts: &[usize], existing: &HashSet<EdgeKey>) {
+    let angle = |at: usize, a: usize, b: usize| {
+        let (u, v) = (points[a] - points[at], points[b] - points[at]);
+        u.cross(v).magnitude().atan2(u.dot(v))
+    };
+    let mut owners: HashMap<(usize, usize), usize> = HashMap::new();
+    for (i, t) in triangles.iter().enumerate() {
+        for k in 0..3 {
+            owners.insert((t[k], t[(k + 1) % 3]), i);
+        }
+    }
+
+    // every edge starts out suspect, and each flip makes the edges around it suspect again. A curved patch has no
+    // true Delaunay triangulation to settle on, so the number of flips is capped
+    let mut suspects: Vec<(usize, usize)> = triangles.iter().flat_map(|t| (0..3).map(move |k| (t[k], t[(k + 1) % 3]))).collect();
+    let mut budget = triangles.len() * 16;
+    while let Some((a, b)) = suspects.pop() {
+        let (first, second) = match (owners.get(&(a, b)), owners.get(&(b, a))) {
+            (Some(&first), Some(&second)) => (first, second),
+            _ => continue,
+        };
+        let p = triangles[first].iter().copied().find(|&v| v != a && v != b).unwrap();
+        let q = triangles[second].iter().copied().find(|&v| v != a && v != b).unwrap();
+        if angle(p, a, b) + angle(q, a, b) <= std::f64::consts::PI + 1e-9
+            || owners.contains_key(&(p, q))
+            || owners.contains_key(&(q, p))
+            || existing.contains(&edge_key(verts[p], verts[q])) {
+            continue;
+        }
+        for &i in [first, second].iter() {
+            let t = triangles[i];
+            for k in 0..3 {
+                owners.remove(&(t[k], t[(k + 1) % 3]));
+            }
+        }
+        triangles[first] = [a, q, p];
+        triangles[second] = [q, b, p];
+        for &i in [first, second].iter() {
+            let t = triangles[i];
+            for k in 0..3 {
+                owners.insert((t[k], t[(k + 1) % 3]), i);
+            }
+        }
+        suspects.extend([(a, q), (q, b), (b, p), (p, a)].iter());
+        budget -= 1;
+        if budget == 0 {
+            break;
+        }
+    }
+}
+
+/// filter that fills the holes of the mesh
+#[derive(Debug)]
+pub struct FillHoles<T> where T: GeoNum {
+    options: FillOptions<T>,
+}
+
+impl<T> FillHoles<T> where T: GeoNum {
+    pub fn new(options: FillOptions<T>) -> Self {
+        Self {
+            options,
+        }
+    }
+}
+
+impl<T> Filter<T> for FillHoles<T> where T: GeoNum {
+    fn apply(&self, mesh: &mut Mesh<T>) {
+        fill_holes(mesh, &self.options);
+    }
+}
+
+#[test]
+fn fill_holes_test() {
+    use super::fixture::{closed, cube};
+    use crate::geom::Area;
+
+    // a unit cube without its bottom and top
+    let mut tube = cube(0., 1.);
+    let sides = tube.faces()[2..].to_vec();
+    tube.set_faces(sides);
+
+    assert_eq!(boundary_loops(&tube).len(), 2);
+    let mut small = tube.clone();
+    assert_eq!(fill_holes(&mut small, &FillOptions { max_edges: Some(3), ..FillOptions::new() }), 0);
+    assert_eq!(fill_holes(&mut small, &FillOptions { max_perimeter: Some(3.9), ..FillOptions::new() }), 0);
+
+    let mut box_ = tube.clone();
+    assert_eq!(fill_holes(&mut box_, &FillOptions { fair: false, ..FillOptions::new() }), 2);
+    assert!(closed(&box_));
+    assert_eq!(box_.faces().len(), 8);
+    assert!((box_.volume() - 1.).abs() < 1e-12);
+
+    // a flat sheet with a square missing from its middle is patched flat, back to its full area
+    let size = 6;
+    let mut sheet = Mesh::<f64>::new();
+    for y in 0..=size {
+        for x in 0..=size {
+            sheet.add_vertex(Vertex::new(x as f64, y as f64, 0.));
+        }
+    }
+    for y in 0..size {
+        for x in 0..size {
+            if (2..4).contains(&x) && (2..4).contains(&y) {
+                continue;
+            }
+            let i = y * (size + 1) + x;
+            sheet.add_face(Face::new(vec![PointIndex::new(i), PointIndex::new(i + 1), PointIndex::new(i + size + 2), PointIndex::new(i + size + 1)]));
+        }
+    }
+    sheet.set_uvs(sheet.verticies().iter().map(|v| vek::Vec2::new(v.x / 6., v.y / 6.)).collect());
+    let mut patched = sheet.clone();
+    assert_eq!(fill_holes(&mut patched, &FillOptions { max_edges: Some(8), ..FillOptions::new() }), 1);
+    assert!(patched.uvs().unwrap()[sheet.verticies().len()..].iter().all(|uv| (uv.x - 0.5).abs() < 0.2 && (uv.y - 0.5).abs() < 0.2));
+    assert!(patched.verticies().len() > sheet.verticies().len());
+    assert!(patched.verticies().iter().all(|v| v.z == 0.));
+    assert!(patched.faces().iter().all(|f| patched.newell(f).z > 0.));
+    assert!((patched.area() - 36.).abs() < 1e-9);
+    assert_eq!(boundary_loops(&patched).len(), 1);
+
+    // the ends of a finely divided tube are too long to triangulate optimally, so they are fanned
+    let segments = 260;
+    let mut tube = Mesh::<f64>::new();
+    for i in 0..segments {
+        let a = i as f64 / segments as f64 * 2. * std::f64::consts::PI;
+        tube.add_vertex(Vertex::new(a.cos(), a.sin(), 0.));
+        tube.add_vertex(Vertex::new(a.cos(), a.sin(), 0.05));
+    }
+    for i in 0..segments {
+        let (a, b) = (2 * i, 2 * ((i + 1) % segments));
+        tube.add_face(Face::new(vec![PointIndex::new(a), PointIndex::new(b), PointIndex::new(b + 1), PointIndex::new(a + 1)]));
+    }
+    let mut capped = tube.clone();
+    assert_eq!(fill_holes(&mut capped, &FillOptions { fair: false, ..FillOptions::new() }), 2);
+    assert!(closed(&capped));
+    assert_eq!(capped.verticies().len(), tube.verticies().len() + 2);
+    assert!(capped.volume() > 0.99 * std::f64::consts::PI * 0.05);
+}
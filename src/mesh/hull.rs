@@ -0,0 +1,261 @@
+use super::{Face, Mesh, Primitive};
+use crate::core::{GeoNum, Vertex};
+use std::collections::HashMap;
+use vek::Vec3;
+
+/// builds the convex hull of a point cloud with Quickhull. Faces wind anticlockwise seen from outside.
+///
+/// Degenerate input still gives a mesh: coincident points give a single vertex, colinear points the two end
+/// points with no faces, and coplanar points a flat polygon with one face on each side
+pub fn convex_hull<T>(points: &[Vertex<T>]) -> Mesh<T> where T: GeoNum {
+    let pts: Vec<Vec3<f64>> = points.iter()
+        .map(|p| Vec3::new(p.x.to_f64().unwrap(), p.y.to_f64().unwrap(), p.z.to_f64().unwrap()))
+        .collect();
+    if pts.is_empty() {
+        return Mesh::new();
+    }
+    let extent = pts.iter().fold(0f64, |m, p| m.max(p.x.abs()).max(p.y.abs()).max(p.z.abs()));
+    let eps = (extent * 1e-10).max(1e-12);
+
+    let (verts, faces) = hull(&pts, eps);
+    let mut mesh = Mesh::new();
+    let mut remap = HashMap::new();
+    for &v in verts.iter() {
+        remap.entry(v).or_insert_with(|| mesh.add_vertex(points[v]));
+    }
+    for face in faces {
+        mesh.add_face(Face::new(face.iter().map(|v| remap[v]).collect()));
+    }
+    mesh
+}
+
+/// builds the convex hull of every vertex of a primitive
+pub fn convex_hull_of<T, P>(primitive: &P) -> Mesh<T>
+    where
+        T: GeoNum,
+        P: Primitive<T>
+{
+    convex_hull(primitive.verticies())
+}
+
+/// the hull as the point indices it keeps and the faces between them
+fn hull(pts: &[Vec3<f64>], eps: f64) -> (Vec<usize>, Vec<Vec<usize>>) {
+    // the two extreme points furthest apart
+    let mut extremes = Vec::new();
+    for axis in 0..3 {
+        let min = (0..pts.len()).min_by(|&a, &b| pts[a][axis].total_cmp(&pts[b][axis])).unwrap();
+        let max = (0..pts.len()).max_by(|&a, &b| pts[a][axis].total_cmp(&pts[b][axis])).unwrap();
+        extremes.push((min, max));
+    }
+    let (a, b) = extremes.into_iter()
+        .max_by(|x, y| pts[x.0].distance_squared(pts[x.1]).total_cmp(&pts[y.0].distance_squared(pts[y.1])))
+        .unwrap();
+    if pts[a].distance(pts[b]) <= eps {
+        return (vec![a], Vec::new());
+    }
+
+    // the point furthest from that line
+    let dir = (pts[b] - pts[a]).normalized();
+    let off_line = |p: Vec3<f64>| (p - pts[a]).cross(dir).magnitude();
+    let c = (0..pts.len()).max_by(|&x, &y| off_line(pts[x]).total_cmp(&off_line(pts[y]))).unwrap();
+    if off_line(pts[c]) <= eps {
+        return (vec![a, b], Vec::new());
+    }
+
+    // the point furthest from that plane
+    let normal = (pts[b] - pts[a]).cross(pts[c] - pts[a]).normalized();
+    let off_plane = |p: Vec3<f64>| normal.dot(p - pts[a]);
+    let d = (0..pts.len()).max_by(|&x, &y| off_plane(pts[x]).abs().total_cmp(&off_plane(pts[y]).abs())).unwrap();
+    if off_plane(pts[d]).abs() <= eps {
+        return flat_hull(pts, pts[a], normal);
+    }
+
+    let mut hull = Quickhull::new(pts, eps);
+    if off_plane(pts[d]) < 0. {
+        hull.add_face([a, b, c]);
+        hull.add_face([a, d, b]);
+        hull.add_face([b, d, c]);
+        hull.add_face([c, d, a]);
+    } else {
+        hull.add_face([a, c, b]);
+        hull.add_face([a, b, d]);
+        hull.add_face([b, c, d]);
+        hull.add_face([c, a, d]);
+    }
+    let initial: Vec<usize> = (0..pts.len()).filter(|&p| p != a && p != b && p != c && p != d).collect();
+    hull.assign(initial, &[0, 1, 2, 3]);
+    hull.run();
+
+    let faces: Vec<Vec<usize>> = hull.faces.iter().filter(|f| f.alive).map(|f| f.verts.to_vec()).collect();
+    let mut verts: Vec<usize> = faces.iter().flatten().copied().collect();
+    verts.sort_unstable();
+    verts.dedup();
+    (verts, faces)
+}
+
+/// 2d hull of points lying on one plane, emitted as a polygon facing each way
+fn flat_hull(pts: &[Vec3<f64>], origin: Vec3<f64>, normal: Vec3<f64>) -> (Vec<usize>, Vec<Vec<usize>>) {
+    let u = if normal.x.abs() < 0.9 { Vec3::unit_x() } else { Vec3::unit_y() }.cross(normal).normalized();
+    let v = normal.cross(u);
+    let flat: Vec<(f64, f64)> = pts.iter().map(|p| ((*p - origin).dot(u), (*p - origin).dot(v))).collect();
+    let mut order: Vec<usize> = (0..pts.len()).collect();
+    order.sort_by(|&a, &b| flat[a].0.total_cmp(&flat[b].0).then(flat[a].1.total_cmp(&flat[b].1)));
+
+    // monotone chain, dropping points on the hull's edges
+    let cross = |o: usize, a: usize, b: usize| {
+        (flat[a].0 - flat[o].0) * (flat[b].1 - flat[o].1) - (flat[a].1 - flat[o].1) * (flat[b].0 - flat[o].0)
+    };
+    let mut ring: Vec<usize> = Vec::new();
+    for pass in 0..2 {
+        let start = ring.len();
+        let iter: Box<dyn Iterator<Item = &usize>> = if pass == 0 { Box::new(order.iter()) } else { Box::new(order.iter().rev()) };
+        for &p in iter {
+            while ring.len() >= start + 2 && cross(ring[ring.len() - 2], ring[ring.len() - 1], p) <= 0. {
+                ring.pop();
+            }
+            ring.push(p);
+        }
+        ring.pop();
+    }
+
+    let mut back = ring.clone();
+    back.reverse();
+    let mut verts = ring.clone();
+    verts.sort_unstable();
+    (verts, vec![ring, back])
+}
+
+struct HullFace {
+    verts: [usize; 3],
+    normal: Vec3<f64>,
+    offset: f64,
+    outside: Vec<usize>,
+    alive: bool,
+}
+
+struct Quickhull<'a> {
+    pts: &'a [Vec3<f64>],
+    eps: f64,
+    faces: Vec<HullFace>,
+    /// directed edge to the face it belongs to
+    edges: HashMap<(usize, usize), usize>,
+}
+
+impl<'a> Quickhull<'a> {
+    fn new(pts: &'a [Vec3<f64>], eps: f64) -> Self {
+        Self { pts, eps, faces: Vec::new(), edges: HashMap::new() }
+    }
+
+    fn add_face(&mut self, verts: [usize; 3]) -> usize {
+        let [a, b, c] = verts;
+        let normal = (self.pts[b] - self.pts[a]).cross(self.pts[c] - self.pts[a]).normalized();
+        let index = self.faces.len();
+        self.faces.push(HullFace { verts, normal, offset: normal.dot(self.pts[a]), outside: Vec::new(), alive: true });
+        for i in 0..3 {
+            self.edges.insert((verts[i], verts[(i + 1) % 3]), index);
+        }
+        index
+    }
+
+    fn distance(&self, face: usize, point: usize) -> f64 {
+        let f = &self.faces[face];
+        f.normal.dot(self.pts[point]) - f.offset
+    }
+
+    /// hands each point to the face it is furthest above. Points below all of them are inside the hull
+    fn assign(&mut self, points: Vec<usize>, faces: &[usize]) {
+        for p in points {
+            let best = faces.iter()
+                .map(|&f| (f, self.distance(f, p)))
+                .filter(|&(_, d)| d > self.eps)
+                .max_by(|x, y| x.1.total_cmp(&y.1));
+            if let Some((f, _)) = best {
+                self.faces[f].outside.push(p);
+            }
+        }
+    }
+
+    fn run(&mut self) {
+        while let Some(start) = self.faces.iter().position(|f| f.alive && !f.outside.is_empty()) {
+            let eye = *self.faces[start].outside.iter()
+                .max_by(|&&x, &&y| self.distance(start, x).total_cmp(&self.distance(start, y)))
+                .unwrap();
+
+            // flood out across every face the eye can see
+            let mut visible = vec![start];
+            let mut seen = vec![false; self.faces.len()];
+            seen[start] = true;
+            let mut i = 0;
+            while i < visible.len() {
+                let f = visible[i];
+                for e in 0..3 {
+                    let (a, b) = (self.faces[f].verts[e], self.faces[f].verts[(e + 1) % 3]);
+                    let n = self.edges[&(b, a)];
+                    if !seen[n] && self.distance(n, eye) > self.eps {
+                        seen[n] = true;
+                        visible.push(n);
+                    }
+                }
+                i += 1;
+            }
+
+            let mut horizon = Vec::new();
+            let mut orphans = Vec::new();
+            for &f in visible.iter() {
+                for e in 0..3 {
+                    let (a, b) = (self.faces[f].verts[e], self.faces[f].verts[(e + 1) % 3]);
+                    if !seen[self.edges[&(b, a)]] {
+                        horizon.push((a, b));
+                    }
+                }
+                self.faces[f].alive = false;
+                orphans.append(&mut self.faces[f].outside);
+            }
+            for &f in visible.iter() {
+                for e in 0..3 {
+                    let edge = (self.faces[f].verts[e], self.faces[f].verts[(e + 1) % 3]);
+                    if self.edges.get(&edge) == Some(&f) {
+                        self.edges.remove(&edge);
+                    }
+                }
+            }
+
+            let created: Vec<usize> = horizon.into_iter().map(|(a, b)| self.add_face([a, b, eye])).collect();
+            orphans.retain(|&p| p != eye);
+            self.assign(orphans, &created);
+        }
+    }
+}
+
+#[test]
+fn convex_hull_test() {
+    use super::fixture::{closed, cube};
+
+    let mut points = cube(-1., 1.).verticies().to_vec();
+    points.push(Vertex::new(0., 0., 0.));
+    points.push(Vertex::new(0.5, -0.2, 0.9));
+    points.push(Vertex::new(1., 0., 0.));
+
+    let hull = convex_hull(&points);
+    assert_eq!(hull.verticies().len(), 8);
+    assert_eq!(hull.faces().len(), 12);
+    assert!(closed(&hull));
+    // outward winding: every face normal points away from the centre
+    for face in hull.faces() {
+        let centre = crate::core::Point::to_vec3(hull.verticies()[face.verticies()[0].index()]);
+        assert!(hull.face_normal(face).dot(centre) > 0.);
+    }
+
+    let square = vec![Vertex::new(0., 0., 0.), Vertex::new(1., 0., 0.), Vertex::new(0.5, 0.5, 0.), Vertex::new(1., 1., 0.), Vertex::new(0., 1., 0.)];
+    let flat = convex_hull(&square);
+    assert_eq!(flat.verticies().len(), 4);
+    assert_eq!(flat.faces().len(), 2);
+
+    let line = vec![Vertex::new(0., 0., 0.), Vertex::new(2., 2., 2.), Vertex::new(1., 1., 1.)];
+    let ends = convex_hull(&line);
+    assert_eq!(ends.verticies().len(), 2);
+    assert!(ends.faces().is_empty());
+
+    let same = vec![Vertex::new(1., 1., 1.); 4];
+    assert_eq!(convex_hull(&same).verticies().len(), 1);
+}
@@ -0,0 +1,145 @@
+use super::{Face, Mesh, Primitive};
+use crate::core::GeoNum;
+use crate::geom::Area;
+use vek::{Mat3, Vec3};
+
+/// volume, centre of mass and inertia tensor of a closed mesh filled with uniform density
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MassProperties<T> where T: GeoNum {
+    volume: T,
+    mass: T,
+    centroid: Vec3<T>,
+    inertia: Mat3<T>,
+}
+
+impl<T> MassProperties<T> where T: GeoNum {
+    /// signed enclosed volume. Negative when the faces wind inwards
+    pub fn volume(&self) -> T {
+        self.volume
+    }
+
+    /// volume multiplied by density
+    pub fn mass(&self) -> T {
+        self.mass
+    }
+
+    /// centre of mass
+    pub fn centroid(&self) -> Vec3<T> {
+        self.centroid
+    }
+
+    /// inertia tensor about the centre of mass, in the mesh's axes
+    pub fn inertia(&self) -> Mat3<T> {
+        self.inertia
+    }
+}
+
+impl<T> Mesh<T> where T: GeoNum {
+    /// area of a single face, polygons included
+    pub fn face_area(&self, face: &Face) -> T {
+        self.newell(face).magnitude() / (T::one() + T::one())
+    }
+
+    /// signed volume enclosed by the mesh. Only meaningful when the mesh is closed
+    pub fn volume(&self) -> T {
+        self.mass_properties(T::one()).volume
+    }
+
+    /// volume, mass, centre of mass and inertia tensor for the given density, found by applying the
+    /// divergence theorem to each triangle. Only meaningful when the mesh is closed
+    pub fn mass_properties(&self, density: T) -> MassProperties<T> {
+        let verts: Vec<Vec3<f64>> = self.verticies().iter()
+            .map(|p| Vec3::new(p.x.to_f64().unwrap(), p.y.to_f64().unwrap(), p.z.to_f64().unwrap()))
+            .collect();
+
+        // integrals of 1, x, y, z, x², y², z², xy, yz and zx over the volume
+        let mut integral = [0f64; 10];
+        for tri in self.faces().iter().flat_map(|f| f.triangles()) {
+            let (p0, p1, p2) = (verts[tri[0].index()], verts[tri[1].index()], verts[tri[2].index()]);
+            let d = (p1 - p0).cross(p2 - p0);
+            let (f1x, f2x, f3x, gx) = subexpressions(p0.x, p1.x, p2.x);
+            let (_, f2y, f3y, gy) = subexpressions(p0.y, p1.y, p2.y);
+            let (_, f2z, f3z, gz) = subexpressions(p0.z, p1.z, p2.z);
+            integral[0] += d.x * f1x;
+            integral[1] += d.x * f2x;
+            integral[2] += d.y * f2y;
+            integral[3] += d.z * f2z;
+            integral[4] += d.x * f3x;
+            integral[5] += d.y * f3y;
+            integral[6] += d.z * f3z;
+            integral[7] += d.x * (p0.y * gx[0] + p1.y * gx[1] + p2.y * gx[2]);
+            integral[8] += d.y * (p0.z * gy[0] + p1.z * gy[1] + p2.z * gy[2]);
+            integral[9] += d.z * (p0.x * gz[0] + p1.x * gz[1] + p2.x * gz[2]);
+        }
+        let scale = [6., 24., 24., 24., 60., 60., 60., 120., 120., 120.];
+        for (i, s) in integral.iter_mut().zip(scale.iter()) {
+            *i /= s;
+        }
+
+        let volume = integral[0];
+        let c = if volume != 0. {
+            Vec3::new(integral[1], integral[2], integral[3]) / volume
+        } else {
+            Vec3::zero()
+        };
+        let density = density.to_f64().unwrap();
+        let xx = (integral[5] + integral[6] - volume * (c.y * c.y + c.z * c.z)) * density;
+        let yy = (integral[4] + integral[6] - volume * (c.z * c.z + c.x * c.x)) * density;
+        let zz = (integral[4] + integral[5] - volume * (c.x * c.x + c.y * c.y)) * density;
+        let xy = -(integral[7] - volume * c.x * c.y) * density;
+        let yz = -(integral[8] - volume * c.y * c.z) * density;
+        let zx = -(integral[9] - volume * c.z * c.x) * density;
+
+        let t = |v: f64| T::from_f64(v).unwrap();
+        MassProperties {
+            volume: t(volume),
+            mass: t(volume * density),
+            centroid: c.map(t),
+            inertia: Mat3::new(
+                t(xx), t(xy), t(zx),
+                t(xy), t(yy), t(yz),
+                t(zx), t(yz), t(zz),
+            ),
+        }
+    }
+}
+
+/// total area of every face
+impl<T> Area<T> for Mesh<T> where T: GeoNum {
+    fn area(&self) -> T {
+        self.faces().iter().fold(T::zero(), |sum, f| sum + self.face_area(f))
+    }
+}
+
+/// the polynomial terms of one coordinate of a triangle shared by the volume integrals
+fn subexpressions(w0: f64, w1: f64, w2: f64) -> (f64, f64, f64, [f64; 3]) {
+    let temp0 = w0 + w1;
+    let f1 = temp0 + w2;
+    let temp1 = w0 * w0;
+    let temp2 = temp1 + w1 * temp0;
+    let f2 = temp2 + w2 * f1;
+    let f3 = w0 * temp1 + w1 * temp2 + w2 * f2;
+    let g = [f2 + w0 * (f1 + w0), f2 + w1 * (f1 + w1), f2 + w2 * (f1 + w2)];
+    (f1, f2, f3, g)
+}
+
+#[test]
+fn mass_properties_test() {
+    use super::fixture::cuboid;
+
+    // a 2 x 1 x 1 box with a corner at (1, 1, 1)
+    let mesh = cuboid(Vec3::new(1., 1., 1.), Vec3::new(3., 2., 2.));
+
+    assert!((mesh.area() - 10.).abs() < 1e-12);
+    assert!((mesh.face_area(&mesh.faces()[0]) - 2.).abs() < 1e-12);
+    assert!((mesh.volume() - 2.).abs() < 1e-12);
+
+    let props = mesh.mass_properties(3.);
+    assert!((props.mass() - 6.).abs() < 1e-12);
+    assert!((props.centroid() - Vec3::new(2., 1.5, 1.5)).magnitude() < 1e-12);
+    // solid box: I = m (b² + c²) / 12 about each axis, no products of inertia
+    let expected = Mat3::new(1., 0., 0., 0., 2.5, 0., 0., 0., 2.5);
+    for (a, b) in props.inertia().into_row_array().iter().zip(expected.into_row_array().iter()) {
+        assert!((a - b).abs() < 1e-9);
+    }
+}
@@ -1,6 +1,8 @@
-use vek::Vec3;
+use vek::{Mat4, Rgba, Vec2, Vec3, Vec4};
 use super::{Face, FaceIndex, Primitive, Filter};
-use crate::core::{PointIndex, GeoNum, Vertex};
+use super::attributes::Attributes;
+use super::triangulate::triangulate;
+use crate::core::{PointIndex, GeoNum, Point, Vertex};
 use std::fmt::{Error, Debug};
 
 pub trait Draw {
@@ -8,11 +10,12 @@ pub trait Draw {
 }
 
 #[derive(Debug)]
-#[no_mangle]
 pub struct Mesh<T> where T: GeoNum {
     verticies: Vec<Vertex<T>>,
     faces: Vec<Face>,
+    attributes: Attributes<T>,
     name: Option<String>,
+    material: Option<String>,
     filters: Vec<Box<dyn Filter<T>>>,
 }
 
@@ -22,7 +25,9 @@ impl<T> Clone for Mesh<T> where T: GeoNum {
         Self {
             verticies: self.verticies.clone(),
             faces: self.faces.clone(),
+            attributes: self.attributes.clone(),
             name: self.name.clone(),
+            material: self.material.clone(),
             filters: Vec::new(),
         }
     }
@@ -35,7 +40,9 @@ impl<T> Mesh<T> where T: GeoNum {
         Self {
             verticies: Vec::new(),
             faces: Vec::new(),
+            attributes: Attributes::new(),
             name: None,
+            material: None,
             filters: Vec::new(),
         }
     }
@@ -45,13 +52,104 @@ impl<T> Mesh<T> where T: GeoNum {
         self.name = Some(name);
     }
 
+    /// sets the material this mesh should be drawn with, by name
+    pub fn set_material(&mut self, material: String) {
+        self.material = Some(material);
+    }
+
+    /// sets per-vertex normals. Must hold one normal for every vertex in the mesh
+    pub fn set_normals(&mut self, normals: Vec<Vec3<T>>) {
+        assert_eq!(normals.len(), self.verticies.len(), "expected one normal per vertex");
+        self.attributes.normals = Some(normals);
+    }
+
+    /// sets per-vertex texture coordinates. Must hold one uv for every vertex in the mesh
+    pub fn set_uvs(&mut self, uvs: Vec<Vec2<T>>) {
+        assert_eq!(uvs.len(), self.verticies.len(), "expected one uv per vertex");
+        self.attributes.uvs = Some(uvs);
+    }
+
+    /// sets per-vertex colours. Must hold one colour for every vertex in the mesh
+    pub fn set_colours(&mut self, colours: Vec<Rgba<T>>) {
+        assert_eq!(colours.len(), self.verticies.len(), "expected one colour per vertex");
+        self.attributes.colours = Some(colours);
+    }
+
+    /// sets per-vertex tangents. xyz is the tangent along increasing u and w is the handedness, +1 or -1, so the
+    /// bitangent is w times the cross of the normal and tangent. Must hold one tangent for every vertex in the mesh
+    pub fn set_tangents(&mut self, tangents: Vec<Vec4<T>>) {
+        assert_eq!(tangents.len(), self.verticies.len(), "expected one tangent per vertex");
+        self.attributes.tangents = Some(tangents);
+    }
+
+    /// sets a named per-vertex channel for data corale has no dedicated slot for. Must hold one value for every vertex in the mesh
+    pub fn set_channel(&mut self, name: String, values: Vec<T>) {
+        assert_eq!(values.len(), self.verticies.len(), "expected one value per vertex");
+        self.attributes.custom.insert(name, values);
+    }
+
     /// adds a lone vertex to the mesh. Be sure to give him some friends!
+    /// any per-vertex attributes are padded with zeroed values
     pub fn add_vertex(&mut self, vertex: Vertex<T>) -> PointIndex {
         let i = self.verticies.len();
         self.verticies.push(vertex);
+        self.attributes.push_default();
+        PointIndex::new(i)
+    }
+
+    /// adds a copy of an existing vertex at a new position, carrying its per-vertex attributes along
+    pub(crate) fn duplicate_vertex(&mut self, source: PointIndex, position: Vertex<T>) -> PointIndex {
+        let i = self.verticies.len();
+        self.verticies.push(position);
+        self.attributes.push_copy(source.index());
         PointIndex::new(i)
     }
 
+    /// adds a vertex part way along the edge from a to b, blending the per-vertex attributes of both ends
+    pub(crate) fn split_edge(&mut self, a: PointIndex, b: PointIndex, t: T) -> PointIndex {
+        let (pa, pb) = (self.verticies[a.index()].to_vec3(), self.verticies[b.index()].to_vec3());
+        let i = self.verticies.len();
+        self.verticies.push(Vertex::from_vec3(pa + (pb - pa) * t));
+        self.attributes.push_lerp(a.index(), b.index(), t);
+        PointIndex::new(i)
+    }
+
+    /// adds a vertex among several existing ones, averaging their per-vertex attributes
+    pub(crate) fn average_vertex(&mut self, position: Vertex<T>, sources: &[PointIndex]) -> PointIndex {
+        let i = self.verticies.len();
+        self.verticies.push(position);
+        self.attributes.push_average(&sources.iter().map(|v| v.index()).collect::<Vec<_>>());
+        PointIndex::new(i)
+    }
+
+    /// sets uvs given per face corner. A vertex whose corners disagree is split into one copy per distinct uv,
+    /// opening a seam there. corners must hold one uv for every corner of every face
+    pub(crate) fn set_corner_uvs(&mut self, corners: &[Vec<Vec2<T>>]) {
+        let mut uvs = vec![Vec2::zero(); self.verticies.len()];
+        let mut copies: Vec<Vec<(Vec2<T>, PointIndex)>> = vec![Vec::new(); self.verticies.len()];
+        let mut faces = self.faces.clone();
+        for (face, corner) in faces.iter_mut().zip(corners.iter()) {
+            let verts = face.verticies().iter().zip(corner.iter()).map(|(&v, &uv)| {
+                if let Some(&(_, copy)) = copies[v.index()].iter().find(|(existing, _)| *existing == uv) {
+                    return copy;
+                }
+                let copy = if copies[v.index()].is_empty() {
+                    v
+                } else {
+                    let position = self.verticies[v.index()];
+                    uvs.push(uv);
+                    self.duplicate_vertex(v, position)
+                };
+                uvs[copy.index()] = uv;
+                copies[v.index()].push((uv, copy));
+                copy
+            }).collect();
+            *face = Face::new(verts);
+        }
+        self.faces = faces;
+        self.set_uvs(uvs);
+    }
+
     /// adds a face to the mesh. Assumes the vertecies are already in the mesh
     pub fn add_face(&mut self, face: Face) -> FaceIndex {
         let i = self.faces.len();
@@ -62,13 +160,123 @@ impl<T> Mesh<T> where T: GeoNum {
     /// generates a face from given points and adds the vertecies to the mesh. Not to be used in conjunction with add_vertex or add_face
     pub fn make_face(&mut self, verticies: Vec<Vertex<T>>) -> FaceIndex {
         let mut face = Face::capacity(verticies.len());
-        for i in 0..verticies.len() {
-            let vi = self.add_vertex(verticies[i]);
+        for vertex in verticies {
+            let vi = self.add_vertex(vertex);
             face.add_vert(vi);
         }
         self.add_face(face)
     }
 
+    /// drops every vertex that no face refers to, returning how many were removed
+    pub fn remove_unused_verticies(&mut self) -> usize {
+        let mut used = vec![false; self.verticies.len()];
+        for face in self.faces.iter() {
+            for v in face.verticies() {
+                used[v.index()] = true;
+            }
+        }
+        let sources: Vec<usize> = (0..used.len()).filter(|&i| used[i]).collect();
+        let removed = self.verticies.len() - sources.len();
+        if removed > 0 {
+            let mut remap = vec![0; used.len()];
+            for (new, &old) in sources.iter().enumerate() {
+                remap[old] = new;
+            }
+            let faces = self.faces.iter()
+                .map(|f| Face::new(f.verticies().iter().map(|v| PointIndex::new(remap[v.index()])).collect()))
+                .collect();
+            self.rebuild(&sources, faces);
+        }
+        removed
+    }
+
+    /// copies every vertex and face of other onto the end of this mesh. Faces are re-indexed to the copied
+    /// verticies, and per-vertex attributes either mesh lacks are padded with blank values
+    pub fn append(&mut self, other: &Mesh<T>) {
+        let offset = self.verticies.len();
+        self.attributes.append(&other.attributes, offset, other.verticies.len());
+        self.verticies.extend_from_slice(&other.verticies);
+        self.faces.extend(other.faces.iter()
+            .map(|f| Face::new(f.verticies().iter().map(|v| PointIndex::new(v.index() + offset)).collect())));
+    }
+
+    /// splits the mesh into one mesh per group of faces joined by shared verticies. Each piece keeps the name,
+    /// material and attributes of its verticies. Verticies no face uses are left out
+    pub fn components(&self) -> Vec<Mesh<T>> {
+        let mut parent: Vec<usize> = (0..self.verticies.len()).collect();
+        fn root(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+        for face in self.faces.iter() {
+            let first = match face.verticies().first() {
+                Some(v) => root(&mut parent, v.index()),
+                None => continue,
+            };
+            for v in face.verticies().iter().skip(1) {
+                let r = root(&mut parent, v.index());
+                parent[r] = first;
+            }
+        }
+
+        // components are numbered in the order their first face appears
+        let mut component = vec![usize::MAX; self.verticies.len()];
+        let mut pieces: Vec<(Vec<usize>, Vec<Face>)> = Vec::new();
+        let mut remap = vec![0; self.verticies.len()];
+        let mut piece_of_root = std::collections::HashMap::new();
+        for face in self.faces.iter().filter(|f| !f.is_empty()) {
+            let r = root(&mut parent, face.verticies()[0].index());
+            let piece = *piece_of_root.entry(r).or_insert_with(|| {
+                pieces.push((Vec::new(), Vec::new()));
+                pieces.len() - 1
+            });
+            let (sources, faces) = &mut pieces[piece];
+            let verts = face.verticies().iter().map(|v| {
+                let i = v.index();
+                if component[i] != piece {
+                    component[i] = piece;
+                    remap[i] = sources.len();
+                    sources.push(i);
+                }
+                PointIndex::new(remap[i])
+            }).collect();
+            faces.push(Face::new(verts));
+        }
+
+        pieces.into_iter().map(|(sources, faces)| {
+            let mut mesh = self.clone();
+            mesh.rebuild(&sources, faces);
+            mesh
+        }).collect()
+    }
+
+    /// swaps in a new set of faces over the existing verticies
+    pub(crate) fn set_faces(&mut self, faces: Vec<Face>) {
+        self.faces = faces;
+    }
+
+    /// swaps in new geometry that keeps the existing verticies at the front. Those keep their attributes, and each
+    /// vertex after them takes the average of the attributes of the old verticies averages lists for it
+    pub(crate) fn replace_geometry(&mut self, verticies: Vec<Vertex<T>>, faces: Vec<Face>, averages: &[Vec<usize>]) {
+        assert_eq!(self.verticies.len() + averages.len(), verticies.len(), "expected one average per added vertex");
+        for sources in averages.iter() {
+            self.attributes.push_average(sources);
+        }
+        self.verticies = verticies;
+        self.faces = faces;
+    }
+
+    /// replaces the vertex list with verticies[sources[i]] for each new index i, and swaps in a new set of faces
+    /// that refer to the new indices. Per-vertex attributes follow their verticies
+    pub(crate) fn rebuild(&mut self, sources: &[usize], faces: Vec<Face>) {
+        self.verticies = sources.iter().map(|&i| self.verticies[i]).collect();
+        self.attributes = self.attributes.gather(sources);
+        self.faces = faces;
+    }
+
     /// adds a filter to our lovely mesh
     pub fn add_filter(&mut self, filter: Box<dyn Filter<T>>) {
         self.filters.push(filter);
@@ -79,6 +287,30 @@ impl<T> Mesh<T> where T: GeoNum {
         self.map_verts(|v| Vertex::new(v.x + offset.x, v.y + offset.y, v.z + offset.z));
     }
 
+    /// applies an affine transform to the mesh. Normals are carried along by the inverse transpose and tangents by
+    /// the transform itself
+    pub fn transform(&mut self, matrix: Mat4<T>) {
+        self.map_verts(|v| Vertex::from_vec3(matrix.mul_point(v.to_vec3())));
+        if let Some(normals) = self.attributes.normals.as_mut() {
+            let normal_matrix = matrix.inverted().transposed();
+            for n in normals.iter_mut() {
+                let m = normal_matrix.mul_direction(*n);
+                let length = m.magnitude();
+                *n = if length > T::zero() { m / length } else { m };
+            }
+        }
+        if let Some(tangents) = self.attributes.tangents.as_mut() {
+            // a mirroring transform turns the bitangent the other way
+            let flip = if matrix.determinant() < T::zero() { -T::one() } else { T::one() };
+            for t in tangents.iter_mut() {
+                let m = matrix.mul_direction(Vec3::from(*t));
+                let length = m.magnitude();
+                let m = if length > T::zero() { m / length } else { m };
+                *t = Vec4::new(m.x, m.y, m.z, t.w * flip);
+            }
+        }
+    }
+
     /// inverts the sign of all x coordinates
     pub fn invert_x(&mut self) {
         self.map_verts(|v| 
@@ -105,11 +337,100 @@ impl<T> Mesh<T> where T: GeoNum {
         self.name.clone()
     }
 
+    /// returns the name of the material assigned to this mesh
+    pub fn material(&self) -> Option<String> {
+        self.material.clone()
+    }
+
+    /// per-vertex normals, if the mesh has any
+    pub fn normals(&self) -> Option<&Vec<Vec3<T>>> {
+        self.attributes.normals.as_ref()
+    }
+
+    /// per-vertex texture coordinates, if the mesh has any
+    pub fn uvs(&self) -> Option<&Vec<Vec2<T>>> {
+        self.attributes.uvs.as_ref()
+    }
+
+    /// per-vertex colours, if the mesh has any
+    pub fn colours(&self) -> Option<&Vec<Rgba<T>>> {
+        self.attributes.colours.as_ref()
+    }
+
+    /// per-vertex tangents with handedness in w, if the mesh has any
+    pub fn tangents(&self) -> Option<&Vec<Vec4<T>>> {
+        self.attributes.tangents.as_ref()
+    }
+
+    /// a named custom per-vertex channel
+    pub fn channel(&self, name: &str) -> Option<&Vec<T>> {
+        self.attributes.custom.get(name)
+    }
+
+    /// names of all custom per-vertex channels, in sorted order
+    pub fn channels(&self) -> Vec<String> {
+        self.attributes.custom.keys().cloned().collect()
+    }
+
+    /// the unit normal of a face, following its winding. Uses Newell's method so n-gons are handled too
+    pub fn face_normal(&self, face: &Face) -> Vec3<T> {
+        let normal = self.newell(face);
+        let length = normal.magnitude();
+        if length > T::zero() {
+            normal / length
+        } else {
+            normal
+        }
+    }
+
+    /// Newell's vector for a face: its normal scaled by twice its area
+    pub(crate) fn newell(&self, face: &Face) -> Vec3<T> {
+        let verts = face.verticies();
+        let mut normal = Vec3::<T>::zero();
+        for i in 0..verts.len() {
+            let a = self.verticies[verts[i].index()];
+            let b = self.verticies[verts[(i + 1) % verts.len()].index()];
+            normal.x = normal.x + (a.y - b.y) * (a.z + b.z);
+            normal.y = normal.y + (a.z - b.z) * (a.x + b.x);
+            normal.z = normal.z + (a.x - b.x) * (a.y + b.y);
+        }
+        normal
+    }
+
+    /// splits a face into triangles by ear clipping in its own plane, so a concave polygon is covered exactly where
+    /// a fan from its first corner would spill outside it. Triangles follow the face's winding
+    pub(crate) fn face_triangles(&self, face: &Face) -> Vec<[PointIndex; 3]> {
+        let verts = face.verticies();
+        let normal = self.newell(face);
+        let normal = Vec3::new(normal.x.to_f64().unwrap(), normal.y.to_f64().unwrap(), normal.z.to_f64().unwrap());
+        let length = normal.magnitude();
+        if verts.len() <= 3 || length == 0. || !length.is_finite() {
+            return face.triangles();
+        }
+        let normal = normal / length;
+        let u = if normal.x.abs() < 0.9 { Vec3::unit_x() } else { Vec3::unit_y() }.cross(normal).normalized();
+        let w = normal.cross(u);
+        let points: Vec<Vec2<f64>> = verts.iter().map(|v| {
+            let p = self.verticies[v.index()].to_vec3();
+            let p = Vec3::new(p.x.to_f64().unwrap(), p.y.to_f64().unwrap(), p.z.to_f64().unwrap());
+            Vec2::new(p.dot(u), p.dot(w))
+        }).collect();
+        let ring: Vec<usize> = (0..verts.len()).collect();
+        triangulate(&points, &ring, &[]).iter().map(|t| [verts[t[0]], verts[t[1]], verts[t[2]]]).collect()
+    }
+
     /// applies a function to the verticies of this mesh
     pub fn map_verts<F>(&mut self, f: F)  
         where F: Fn(&Vertex<T>) -> Vertex<T> 
     {
-        self.verticies = self.verticies.iter().map(|x| f(&x)).collect();
+        self.verticies = self.verticies.iter().map(f).collect();
+    }
+
+    /// applies a function to the verticies of this mesh, along with the index of each
+    pub fn map_verts_indexed<F>(&mut self, f: F)
+        where F: Fn(usize, &Vertex<T>) -> Vertex<T>
+    {
+        self.verticies = self.verticies.iter().enumerate().map(|(i, v)| f(i, v)).collect();
     }
 
     /// makes a copy of the mesh and applies all filters
@@ -123,6 +444,12 @@ impl<T> Mesh<T> where T: GeoNum {
     }
 }
 
+impl<T> Default for Mesh<T> where T: GeoNum {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> Primitive<T> for Mesh<T> where T: GeoNum {
     /// Getter for verts
     fn verticies(&self) -> &Vec<Vertex<T>> {
@@ -147,4 +474,32 @@ impl<T> Primitive<T> for Mesh<T> where T: GeoNum {
         }
         Some(&mut self.verticies[index.index()])
     }
-}
\ No newline at end of file
+}
+#[test]
+fn append_components_test() {
+    let mut a = Mesh::<f64>::new();
+    a.make_face(vec![Vertex::new(0., 0., 0.), Vertex::new(1., 0., 0.), Vertex::new(0., 1., 0.)]);
+    a.set_channel("weight".into(), vec![1., 2., 3.]);
+    let mut b = Mesh::<f64>::new();
+    b.make_face(vec![Vertex::new(5., 0., 0.), Vertex::new(6., 0., 0.), Vertex::new(5., 1., 0.)]);
+    b.make_face(vec![Vertex::new(5., 0., 1.), Vertex::new(6., 0., 1.), Vertex::new(5., 1., 1.)]);
+    b.set_uvs(vec![vek::Vec2::new(0.5, 0.5); 6]);
+
+    a.append(&b);
+    assert_eq!(a.verticies().len(), 9);
+    assert_eq!(a.faces()[2].verticies()[0].index(), 6);
+    assert_eq!(a.channel("weight").unwrap()[3..], [0.; 6]);
+    assert_eq!(a.uvs().unwrap()[0], vek::Vec2::zero());
+    assert_eq!(a.uvs().unwrap()[8], vek::Vec2::new(0.5, 0.5));
+
+    let pieces = a.components();
+    assert_eq!(pieces.len(), 3);
+    assert_eq!(pieces[0].channel("weight").unwrap(), &vec![1., 2., 3.]);
+    assert_eq!(pieces[2].verticies()[0], Vertex::new(5., 0., 1.));
+    assert_eq!(pieces[2].faces()[0].verticies()[2].index(), 2);
+
+    // a channel both sides carry is joined once, end to end
+    let mut c = pieces[0].clone();
+    c.append(&pieces[0]);
+    assert_eq!(c.channel("weight").unwrap(), &vec![1., 2., 3., 1., 2., 3.]);
+}
@@ -1,9 +1,55 @@
 mod face;
+#[allow(clippy::module_inception)]
 mod mesh;
 mod filter;
+mod attributes;
 mod primitive;
+mod weld;
+mod topology;
+#[cfg(test)]
+mod fixture;
+mod validate;
+mod decimate;
+mod subdivide;
+mod smooth;
+mod csg;
+mod hull;
+mod mass;
+mod query;
+mod extrude;
+mod bevel;
+mod slice;
+mod triangulate;
+mod clip;
+mod uv;
+mod unwrap;
+mod tangents;
+mod optimize;
+mod geodesic;
+mod curvature;
+mod holes;
 
 pub use filter::{Filter, Scale};
-pub use mesh::Mesh;
+pub use mesh::{Mesh, Draw};
 pub use face::{Face, FaceIndex};
-pub use primitive::Primitive;
\ No newline at end of file
+pub use primitive::Primitive;
+pub use weld::{weld, Weld};
+pub use validate::{validate, repair, MeshIssue, ValidationReport, RepairReport};
+pub use decimate::{decimate, Decimate, DecimateOptions};
+pub use subdivide::{catmull_clark, loop_subdivision, CatmullClark, LoopSubdivision, SubdivideOptions};
+pub use smooth::{Laplacian, Taubin, Cotangent};
+pub use csg::{union, intersection, difference, CsgError};
+pub use hull::{convex_hull, convex_hull_of};
+pub use mass::MassProperties;
+pub use query::{SurfaceQuery, SurfacePoint};
+pub use extrude::{extrude, inset, ExtrudeDirection};
+pub use bevel::bevel;
+pub use slice::{slice, slice_layers};
+pub use clip::{clip, split, crop};
+pub use uv::{project_uvs, Projection, UvOptions, UvProjection};
+pub use unwrap::{unwrap, Unwrap, UnwrapOptions};
+pub use tangents::{generate_tangents, TangentError};
+pub use optimize::{optimize, optimize_vertex_cache, optimize_overdraw, optimize_vertex_fetch, CacheStats, Optimize, OptimizeOptions, OptimizeReport};
+pub use geodesic::{geodesic_path, geodesic_path_between, GeodesicField};
+pub use curvature::{Curvature, CurvatureChannels};
+pub use holes::{boundary_loops, fill_holes, FillHoles, FillOptions};
@@ -0,0 +1,404 @@
+use super::{Face, Filter, Mesh, Primitive};
+use crate::core::{GeoNum, Point, PointIndex};
+use std::collections::VecDeque;
+use vek::Vec3;
+
+/// how many recently used verticies the cache scoring of Forsyth's algorithm keeps track of
+const SCORE_CACHE: usize = 32;
+const CACHE_DECAY_POWER: f64 = 1.5;
+const LAST_TRIANGLE_SCORE: f64 = 0.75;
+const VALENCE_BOOST_SCALE: f64 = 2.;
+const VALENCE_BOOST_POWER: f64 = 0.5;
+
+/// how well the index order of a mesh uses a post-transform vertex cache
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheStats {
+    /// average cache misses per triangle. 3 is the worst, and about 0.5 the best a regular grid can do
+    pub acmr: f64,
+    /// average times each vertex is transformed. 1 is the best possible
+    pub atvr: f64,
+}
+
+impl CacheStats {
+    /// simulates drawing the mesh through a first in, first out cache of the given size
+    pub fn measure<T>(mesh: &Mesh<T>, cache_size: usize) -> Self where T: GeoNum {
+        let triangles = triangles(mesh);
+        let mut used = vec![false; mesh.verticies().len()];
+        for v in triangles.iter().flatten() {
+            used[*v] = true;
+        }
+        let misses: usize = fifo_misses(&triangles, cache_size).iter().sum();
+        let count = used.iter().filter(|&&u| u).count();
+        Self {
+            acmr: if triangles.is_empty() { 0. } else { misses as f64 / triangles.len() as f64 },
+            atvr: if count == 0 { 0. } else { misses as f64 / count as f64 },
+        }
+    }
+}
+
+/// cache statistics from either side of an optimize
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OptimizeReport {
+    pub before: CacheStats,
+    pub after: CacheStats,
+}
+
+/// settings for reordering a mesh for the gpu
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OptimizeOptions {
+    /// size of the vertex cache to optimize for and measure with
+    pub cache_size: usize,
+    /// reorder clusters of triangles so outward facing ones are drawn first
+    pub overdraw: bool,
+    /// how much worse than the vertex cache order a cluster may get when split up for overdraw. 1.05 allows 5%
+    pub overdraw_threshold: f64,
+    /// renumber verticies in the order they are first drawn
+    pub vertex_fetch: bool,
+}
+
+impl OptimizeOptions {
+    /// every pass, for a 16 entry cache
+    pub fn new() -> Self {
+        Self {
+            cache_size: 16,
+            overdraw: true,
+            overdraw_threshold: 1.05,
+            vertex_fetch: true,
+        }
+    }
+}
+
+impl Default for OptimizeOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// reorders the mesh for drawing: triangles for the vertex cache, then clusters of them for overdraw, then
+/// verticies for fetching. Polygons are split into triangles. Reports the cache statistics before and after
+pub fn optimize<T>(mesh: &mut Mesh<T>, options: &OptimizeOptions) -> OptimizeReport where T: GeoNum {
+    let before = CacheStats::measure(mesh, options.cache_size);
+    optimize_vertex_cache(mesh);
+    if options.overdraw {
+        optimize_overdraw(mesh, options.cache_size, options.overdraw_threshold);
+    }
+    if options.vertex_fetch {
+        optimize_vertex_fetch(mesh);
+    }
+    OptimizeReport {
+        before,
+        after: CacheStats::measure(mesh, options.cache_size),
+    }
+}
+
+/// reorders the triangles so verticies are reused while still in the post-transform cache, using Tom Forsyth's
+/// linear speed algorithm. It scores verticies by how recently they were used and how few triangles they have
+/// left, so it suits any cache size. Polygons are split into triangles
+pub fn optimize_vertex_cache<T>(mesh: &mut Mesh<T>) where T: GeoNum {
+    let triangles = triangles(mesh);
+    let count = mesh.verticies().len();
+    let mut adjacent: Vec<Vec<usize>> = vec![Vec::new(); count];
+    for (t, tri) in triangles.iter().enumerate() {
+        for &v in tri.iter() {
+            adjacent[v].push(t);
+        }
+    }
+
+    let score = |position: Option<usize>, remaining: usize| {
+        if remaining == 0 {
+            return -1.;
+        }
+        let cache = match position {
+            None => 0.,
+            Some(p) if p < 3 => LAST_TRIANGLE_SCORE,
+            Some(p) => (1. - (p - 3) as f64 / (SCORE_CACHE - 3) as f64).powf(CACHE_DECAY_POWER),
+        };
+        cache + VALENCE_BOOST_SCALE * (remaining as f64).powf(-VALENCE_BOOST_POWER)
+    };
+    let mut position: Vec<Option<usize>> = vec![None; count];
+    let mut vertex_score: Vec<f64> = adjacent.iter().map(|a| score(None, a.len())).collect();
+    let mut triangle_score: Vec<f64> = triangles.iter().map(|t| t.iter().map(|&v| vertex_score[v]).sum()).collect();
+    let mut added = vec![false; triangles.len()];
+    let mut cache: Vec<usize> = Vec::new();
+    let mut order = Vec::with_capacity(triangles.len());
+
+    let mut best = (0..triangles.len()).max_by(|&a, &b| triangle_score[a].total_cmp(&triangle_score[b]));
+    let mut cursor = 0;
+    while order.len() < triangles.len() {
+        // once nothing in the cache has triangles left, start afresh from the next one not drawn
+        let t = match best {
+            Some(t) => t,
+            None => {
+                while added[cursor] {
+                    cursor += 1;
+                }
+                cursor
+            },
+        };
+        added[t] = true;
+        order.push(t);
+        for &v in triangles[t].iter() {
+            adjacent[v].retain(|&other| other != t);
+        }
+
+        let mut next: Vec<usize> = Vec::with_capacity(cache.len() + 3);
+        for &v in triangles[t].iter().chain(cache.iter()) {
+            if !next.contains(&v) {
+                next.push(v);
+            }
+        }
+        let evicted: Vec<usize> = if next.len() > SCORE_CACHE { next.split_off(SCORE_CACHE) } else { Vec::new() };
+        for &v in evicted.iter() {
+            position[v] = None;
+            vertex_score[v] = score(None, adjacent[v].len());
+        }
+        for (p, &v) in next.iter().enumerate() {
+            position[v] = Some(p);
+            vertex_score[v] = score(Some(p), adjacent[v].len());
+        }
+        cache = next;
+
+        for &v in evicted.iter().chain(cache.iter()) {
+            for &other in adjacent[v].iter() {
+                triangle_score[other] = triangles[other].iter().map(|&u| vertex_score[u]).sum();
+            }
+        }
+        best = cache.iter()
+            .flat_map(|&v| adjacent[v].iter().copied())
+            .max_by(|&a, &b| triangle_score[a].total_cmp(&triangle_score[b]));
+    }
+
+    set_triangles(mesh, order.iter().map(|&t| triangles[t]).collect());
+}
+
+/// reorders the triangles so the outward facing parts of the mesh are drawn first and hide what is behind them,
+/// after Sander, Nehab and Barczak. The current order is cut into clusters wherever it jumps to a new patch or
+/// wherever the cache efficiency of the piece so far is within threshold of the whole, and the clusters are sorted
+/// by how far out they face. Run it after optimize_vertex_cache so the order within clusters stays cache friendly
+pub fn optimize_overdraw<T>(mesh: &mut Mesh<T>, cache_size: usize, threshold: f64) where T: GeoNum {
+    let triangles = triangles(mesh);
+    if triangles.is_empty() {
+        return;
+    }
+    let points: Vec<Vec3<f64>> = mesh.verticies().iter().map(|v| {
+        let p = v.to_vec3();
+        Vec3::new(p.x.to_f64().unwrap(), p.y.to_f64().unwrap(), p.z.to_f64().unwrap())
+    }).collect();
+
+    // a triangle missing on every corner starts a patch the cache order had to jump to
+    let misses = fifo_misses(&triangles, cache_size);
+    let mut hard: Vec<usize> = (0..triangles.len()).filter(|&t| t == 0 || misses[t] == 3).collect();
+    hard.push(triangles.len());
+
+    let mut starts = Vec::new();
+    for bounds in hard.windows(2) {
+        let (start, end) = (bounds[0], bounds[1]);
+        let total: usize = misses[start..end].iter().sum();
+        let target = threshold * total as f64 / (end - start) as f64;
+        let mut cache = Fifo::new(cache_size);
+        let (mut running, mut from) = (0, start);
+        starts.push(from);
+        for (t, triangle) in triangles.iter().enumerate().take(end).skip(start) {
+            running += cache.draw(triangle);
+            if t + 1 < end && running as f64 / (t + 1 - from) as f64 <= target {
+                cache = Fifo::new(cache_size);
+                running = 0;
+                from = t + 1;
+                starts.push(from);
+            }
+        }
+    }
+    starts.push(triangles.len());
+
+    let centre = {
+        let mut used = vec![false; points.len()];
+        for v in triangles.iter().flatten() {
+            used[*v] = true;
+        }
+        let (sum, n) = points.iter().zip(used.iter()).filter(|(_, &u)| u)
+            .fold((Vec3::zero(), 0.), |(s, n), (p, _)| (s + *p, n + 1.));
+        sum / n
+    };
+    let mut clusters: Vec<(f64, &[[usize; 3]])> = starts.windows(2).map(|s| {
+        let cluster = &triangles[s[0]..s[1]];
+        let (mut middle, mut normal, mut area) = (Vec3::zero(), Vec3::<f64>::zero(), 0.);
+        for t in cluster.iter() {
+            let (a, b, c) = (points[t[0]], points[t[1]], points[t[2]]);
+            let n = (b - a).cross(c - a);
+            let weight = n.magnitude();
+            middle += (a + b + c) / 3. * weight;
+            normal += n;
+            area += weight;
+        }
+        let middle = if area > 0. { middle / area } else { centre };
+        let normal = if normal.magnitude_squared() > 0. { normal.normalized() } else { normal };
+        ((middle - centre).dot(normal), cluster)
+    }).collect();
+    clusters.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    set_triangles(mesh, clusters.iter().flat_map(|c| c.1.iter().copied()).collect());
+}
+
+/// renumbers the verticies in the order the faces first use them, so drawing reads the vertex buffer front to
+/// back. Verticies no face uses are moved to the end
+pub fn optimize_vertex_fetch<T>(mesh: &mut Mesh<T>) where T: GeoNum {
+    let count = mesh.verticies().len();
+    let mut remap = vec![usize::MAX; count];
+    let mut sources = Vec::with_capacity(count);
+    for face in mesh.faces().iter() {
+        for v in face.verticies() {
+            if remap[v.index()] == usize::MAX {
+                remap[v.index()] = sources.len();
+                sources.push(v.index());
+            }
+        }
+    }
+    for (v, r) in remap.iter_mut().enumerate() {
+        if *r == usize::MAX {
+            *r = sources.len();
+            sources.push(v);
+        }
+    }
+    let faces = mesh.faces().iter()
+        .map(|f| Face::new(f.verticies().iter().map(|v| PointIndex::new(remap[v.index()])).collect()))
+        .collect();
+    mesh.rebuild(&sources, faces);
+}
+
+fn triangles<T: GeoNum>(mesh: &Mesh<T>) -> Vec<[usize; 3]> {
+    mesh.faces().iter()
+        .flat_map(|f| f.triangles())
+        .map(|t| [t[0].index(), t[1].index(), t[2].index()])
+        .collect()
+}
+
+fn set_triangles<T: GeoNum>(mesh: &mut Mesh<T>, triangles: Vec<[usize; 3]>) {
+    mesh.set_faces(triangles.iter().map(|t| Face::new(t.iter().map(|&v| PointIndex::new(v)).collect())).collect());
+}
+
+/// how many corners of each triangle miss a first in, first out cache, as most hardware has
+fn fifo_misses(triangles: &[[usize; 3]], cache_size: usize) -> Vec<usize> {
+    let mut cache = Fifo::new(cache_size);
+    triangles.iter().map(|t| cache.draw(t)).collect()
+}
+
+/// a simulated first in, first out vertex cache
+struct Fifo {
+    entries: VecDeque<usize>,
+    size: usize,
+}
+
+impl Fifo {
+    fn new(size: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(size + 1),
+            size,
+        }
+    }
+
+    /// feeds a triangle through the cache, returning how many of its corners missed
+    fn draw(&mut self, triangle: &[usize; 3]) -> usize {
+        let mut misses = 0;
+        for &v in triangle.iter() {
+            if !self.entries.contains(&v) {
+                misses += 1;
+                self.entries.push_back(v);
+                if self.entries.len() > self.size {
+                    self.entries.pop_front();
+                }
+            }
+        }
+        misses
+    }
+}
+
+/// filter that reorders the mesh for drawing
+#[derive(Debug)]
+pub struct Optimize {
+    options: OptimizeOptions,
+}
+
+impl Optimize {
+    pub fn new(options: OptimizeOptions) -> Self {
+        Self {
+            options,
+        }
+    }
+}
+
+impl<T> Filter<T> for Optimize where T: GeoNum {
+    fn apply(&self, mesh: &mut Mesh<T>) {
+        optimize(mesh, &self.options);
+    }
+}
+
+#[test]
+fn optimize_test() {
+    use crate::core::Vertex;
+
+    // a grid of quads handed over in a scrambled order
+    let size = 30;
+    let mut mesh = Mesh::<f64>::new();
+    for y in 0..=size {
+        for x in 0..=size {
+            mesh.add_vertex(Vertex::new(x as f64, y as f64, 0.));
+        }
+    }
+    let mut quads = Vec::new();
+    for y in 0..size {
+        for x in 0..size {
+            let i = y * (size + 1) + x;
+            quads.push(vec![i, i + 1, i + size + 2, i + size + 1]);
+        }
+    }
+    let mut seed = 7u64;
+    for i in (1..quads.len()).rev() {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        quads.swap(i, (seed >> 33) as usize % (i + 1));
+    }
+    for q in quads.iter() {
+        mesh.add_face(Face::new(q.iter().map(|&i| PointIndex::new(i)).collect()));
+    }
+    let area = |mesh: &Mesh<f64>| mesh.faces().iter().map(|f| mesh.face_area(f)).sum::<f64>();
+    let before_area = area(&mesh);
+
+    let report = optimize(&mut mesh, &OptimizeOptions::new());
+    assert!(report.before.acmr > 1.5);
+    assert!(report.after.acmr < 0.8);
+    assert!(report.after.atvr < report.before.atvr);
+    assert_eq!(report.after, CacheStats::measure(&mesh, 16));
+
+    // every triangle is still there, and verticies come in the order they are drawn
+    assert_eq!(mesh.faces().len(), size * size * 2);
+    assert!((area(&mesh) - before_area).abs() < 1e-9);
+    assert_eq!(mesh.faces()[0].verticies(), vec![PointIndex::new(0), PointIndex::new(1), PointIndex::new(2)]);
+
+    // a hollow box: a rounded shell facing out around a smaller one facing in, the inner one handed over first
+    let rounded = |size: f64| {
+        let mut shell = super::fixture::cube(-size, size);
+        super::catmull_clark(&mut shell, &super::SubdivideOptions::new(2));
+        shell
+    };
+    let mut hollow = rounded(1.);
+    let inward = hollow.faces().iter().map(|f| {
+        let mut verts = f.verticies();
+        verts.reverse();
+        Face::new(verts)
+    }).collect();
+    hollow.set_faces(inward);
+    hollow.append(&rounded(2.));
+
+    let mut plain = hollow.clone();
+    let without = optimize(&mut plain, &OptimizeOptions { overdraw: false, ..OptimizeOptions::new() });
+    let options = OptimizeOptions::new();
+    let with = optimize(&mut hollow, &options);
+    assert!(with.after.acmr <= without.after.acmr * options.overdraw_threshold);
+
+    // every triangle facing out from the centre is drawn before any facing in
+    let outward: Vec<bool> = hollow.faces().iter().map(|f| {
+        let middle = f.verticies().iter().fold(Vec3::zero(), |s, v| s + hollow.verticies()[v.index()].to_vec3());
+        hollow.newell(f).dot(middle) > 0.
+    }).collect();
+    assert_eq!(outward.iter().filter(|&&o| o).count(), hollow.faces().len() / 2);
+    assert!(outward.windows(2).all(|w| w[0] || !w[1]));
+}
@@ -0,0 +1,294 @@
+use super::{FaceIndex, Mesh, Primitive};
+use super::topology::{edge_key, EdgeKey};
+use crate::core::{GeoNum, PointIndex};
+use crate::geom::{BoundingBox, Cube};
+use std::collections::HashMap;
+use vek::Vec3;
+
+/// the point on a mesh's surface nearest to a query point
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SurfacePoint<T> where T: GeoNum {
+    point: Vec3<T>,
+    face: FaceIndex,
+    triangle: [PointIndex; 3],
+    barycentric: Vec3<T>,
+    distance: T,
+}
+
+impl<T> SurfacePoint<T> where T: GeoNum {
+    /// position on the surface
+    pub fn point(&self) -> Vec3<T> {
+        self.point
+    }
+
+    /// the face the point lies on
+    pub fn face(&self) -> FaceIndex {
+        self.face
+    }
+
+    /// the triangle of the face the point lies on. Triangles share the face's fan triangulation
+    pub fn triangle(&self) -> [PointIndex; 3] {
+        self.triangle
+    }
+
+    /// weights of the triangle's three corners that add up to the point
+    pub fn barycentric(&self) -> Vec3<T> {
+        self.barycentric
+    }
+
+    /// unsigned distance from the query point
+    pub fn distance(&self) -> T {
+        self.distance
+    }
+}
+
+/// answers closest point and signed distance queries against a snapshot of a mesh. Triangles are held in a
+/// bounding volume hierarchy so each query only visits the few near the query point
+#[derive(Clone, Debug)]
+pub struct SurfaceQuery<T> where T: GeoNum {
+    points: Vec<Vec3<f64>>,
+    triangles: Vec<Triangle>,
+    nodes: Vec<BvhNode>,
+    vertex_normals: Vec<Vec3<f64>>,
+    edge_normals: HashMap<EdgeKey, Vec3<f64>>,
+    marker: std::marker::PhantomData<T>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Triangle {
+    verts: [usize; 3],
+    face: usize,
+    normal: Vec3<f64>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct BvhNode {
+    bounds: BoundingBox<f64>,
+    /// children for a branch, or the start of the node's run of triangles for a leaf
+    first: usize,
+    /// zero for a branch
+    count: usize,
+}
+
+const LEAF_SIZE: usize = 4;
+
+impl<T> SurfaceQuery<T> where T: GeoNum {
+    pub fn new(mesh: &Mesh<T>) -> Self {
+        let points: Vec<Vec3<f64>> = mesh.verticies().iter()
+            .map(|p| Vec3::new(p.x.to_f64().unwrap(), p.y.to_f64().unwrap(), p.z.to_f64().unwrap()))
+            .collect();
+
+        let mut triangles = Vec::new();
+        for (face, f) in mesh.faces().iter().enumerate() {
+            for t in f.triangles() {
+                let verts = [t[0].index(), t[1].index(), t[2].index()];
+                let normal = (points[verts[1]] - points[verts[0]]).cross(points[verts[2]] - points[verts[0]]);
+                // slivers have no closest point of their own; their neighbours cover the same ground
+                if normal.magnitude_squared() > 0. {
+                    triangles.push(Triangle { verts, face, normal: normal.normalized() });
+                }
+            }
+        }
+
+        // angle weighted pseudo-normals give the correct sign wherever the closest point lands
+        let mut vertex_normals = vec![Vec3::zero(); points.len()];
+        let mut edge_normals: HashMap<EdgeKey, Vec3<f64>> = HashMap::new();
+        for t in triangles.iter() {
+            for i in 0..3 {
+                let (a, b, c) = (t.verts[i], t.verts[(i + 1) % 3], t.verts[(i + 2) % 3]);
+                let angle = (points[b] - points[a]).angle_between(points[c] - points[a]);
+                vertex_normals[a] += t.normal * angle;
+                *edge_normals.entry(edge_key(a, b)).or_insert_with(Vec3::zero) += t.normal;
+            }
+        }
+
+        let mut query = Self {
+            points,
+            triangles,
+            nodes: Vec::new(),
+            vertex_normals,
+            edge_normals,
+            marker: std::marker::PhantomData,
+        };
+        if !query.triangles.is_empty() {
+            query.nodes.push(query.leaf(0, query.triangles.len()));
+            query.split(0);
+        }
+        query
+    }
+
+    /// the closest point on the surface, or None for a mesh without faces
+    pub fn closest_point(&self, point: Vec3<T>) -> Option<SurfacePoint<T>> {
+        let p = Vec3::new(point.x.to_f64().unwrap(), point.y.to_f64().unwrap(), point.z.to_f64().unwrap());
+        let (tri, closest, weights) = self.nearest(p)?;
+        let t = |v: f64| T::from_f64(v).unwrap();
+        let triangle = &self.triangles[tri];
+        Some(SurfacePoint {
+            point: closest.map(t),
+            face: FaceIndex::new(triangle.face),
+            triangle: [PointIndex::new(triangle.verts[0]), PointIndex::new(triangle.verts[1]), PointIndex::new(triangle.verts[2])],
+            barycentric: Vec3::new(t(weights[0]), t(weights[1]), t(weights[2])),
+            distance: t(p.distance(closest)),
+        })
+    }
+
+    /// distance to the surface, negative inside. The sign is only meaningful for closed meshes
+    pub fn signed_distance(&self, point: Vec3<T>) -> Option<T> {
+        let p = Vec3::new(point.x.to_f64().unwrap(), point.y.to_f64().unwrap(), point.z.to_f64().unwrap());
+        let (tri, closest, weights) = self.nearest(p)?;
+        let verts = self.triangles[tri].verts;
+
+        let zeros: Vec<usize> = (0..3).filter(|&i| weights[i] == 0.).collect();
+        let normal = match zeros.len() {
+            2 => self.vertex_normals[verts[(0..3).find(|i| !zeros.contains(i)).unwrap()]],
+            1 => self.edge_normals[&edge_key(verts[(zeros[0] + 1) % 3], verts[(zeros[0] + 2) % 3])],
+            _ => self.triangles[tri].normal,
+        };
+        let distance = p.distance(closest);
+        let signed = if (p - closest).dot(normal) < 0. { -distance } else { distance };
+        T::from_f64(signed)
+    }
+
+    fn leaf(&self, first: usize, count: usize) -> BvhNode {
+        let mut min = Vec3::broadcast(f64::INFINITY);
+        let mut max = Vec3::broadcast(f64::NEG_INFINITY);
+        for t in self.triangles[first..first + count].iter() {
+            for &v in t.verts.iter() {
+                min = Vec3::partial_min(min, self.points[v]);
+                max = Vec3::partial_max(max, self.points[v]);
+            }
+        }
+        BvhNode { bounds: BoundingBox::new(min, max), first, count }
+    }
+
+    /// splits a leaf at the median of its triangle centres along the widest axis, recursing until leaves are small
+    fn split(&mut self, node: usize) {
+        let BvhNode { bounds, first, count } = self.nodes[node];
+        if count <= LEAF_SIZE {
+            return;
+        }
+        let extent = bounds.max() - bounds.min();
+        let axis = if extent.x >= extent.y && extent.x >= extent.z { 0 } else if extent.y >= extent.z { 1 } else { 2 };
+        let points = &self.points;
+        let centre = |t: &Triangle| points[t.verts[0]][axis] + points[t.verts[1]][axis] + points[t.verts[2]][axis];
+        let half = count / 2;
+        self.triangles[first..first + count].select_nth_unstable_by(half, |a, b| centre(a).total_cmp(&centre(b)));
+
+        let left = self.nodes.len();
+        let (l, r) = (self.leaf(first, half), self.leaf(first + half, count - half));
+        self.nodes.push(l);
+        self.nodes.push(r);
+        self.nodes[node].first = left;
+        self.nodes[node].count = 0;
+        self.split(left);
+        self.split(left + 1);
+    }
+
+    /// the nearest triangle, the closest point on it and that point's barycentric weights
+    fn nearest(&self, p: Vec3<f64>) -> Option<(usize, Vec3<f64>, [f64; 3])> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let mut best: Option<(usize, Vec3<f64>, [f64; 3])> = None;
+        let mut best_distance = f64::INFINITY;
+        let mut stack = vec![0];
+        while let Some(n) = stack.pop() {
+            let node = &self.nodes[n];
+            if box_distance_squared(&node.bounds, p) >= best_distance {
+                continue;
+            }
+            if node.count == 0 {
+                // visit the nearer child first so the far one is more often pruned
+                let (a, b) = (node.first, node.first + 1);
+                let (da, db) = (box_distance_squared(&self.nodes[a].bounds, p), box_distance_squared(&self.nodes[b].bounds, p));
+                if da < db {
+                    stack.push(b);
+                    stack.push(a);
+                } else {
+                    stack.push(a);
+                    stack.push(b);
+                }
+                continue;
+            }
+            for i in node.first..node.first + node.count {
+                let v = self.triangles[i].verts;
+                let (closest, weights) = closest_on_triangle(p, self.points[v[0]], self.points[v[1]], self.points[v[2]]);
+                let d = p.distance_squared(closest);
+                if d < best_distance {
+                    best_distance = d;
+                    best = Some((i, closest, weights));
+                }
+            }
+        }
+        best
+    }
+}
+
+fn box_distance_squared(bounds: &BoundingBox<f64>, p: Vec3<f64>) -> f64 {
+    let outside = Vec3::partial_max(Vec3::partial_max(bounds.min() - p, p - bounds.max()), Vec3::zero());
+    outside.magnitude_squared()
+}
+
+/// closest point on triangle abc to p, from Ericson's Real-Time Collision Detection. Weights on the triangle's
+/// edges and corners are exactly zero, which signed_distance relies on to pick a pseudo-normal
+fn closest_on_triangle(p: Vec3<f64>, a: Vec3<f64>, b: Vec3<f64>, c: Vec3<f64>) -> (Vec3<f64>, [f64; 3]) {
+    let (ab, ac, ap) = (b - a, c - a, p - a);
+    let (d1, d2) = (ab.dot(ap), ac.dot(ap));
+    if d1 <= 0. && d2 <= 0. {
+        return (a, [1., 0., 0.]);
+    }
+    let bp = p - b;
+    let (d3, d4) = (ab.dot(bp), ac.dot(bp));
+    if d3 >= 0. && d4 <= d3 {
+        return (b, [0., 1., 0.]);
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0. && d1 >= 0. && d3 <= 0. {
+        let v = d1 / (d1 - d3);
+        return (a + ab * v, [1. - v, v, 0.]);
+    }
+    let cp = p - c;
+    let (d5, d6) = (ab.dot(cp), ac.dot(cp));
+    if d6 >= 0. && d5 <= d6 {
+        return (c, [0., 0., 1.]);
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0. && d2 >= 0. && d6 <= 0. {
+        let w = d2 / (d2 - d6);
+        return (a + ac * w, [1. - w, 0., w]);
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0. && d4 - d3 >= 0. && d5 - d6 >= 0. {
+        let w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return (b + (c - b) * w, [0., 1. - w, w]);
+    }
+    let denom = 1. / (va + vb + vc);
+    let (v, w) = (vb * denom, vc * denom);
+    (a + ab * v + ac * w, [1. - v - w, v, w])
+}
+
+#[test]
+fn surface_query_test() {
+    use super::fixture::cube;
+    use crate::core::Point;
+
+    let mesh = cube(0., 1.);
+    let query = SurfaceQuery::new(&mesh);
+
+    let hit = query.closest_point(Vec3::new(0.25, 0.5, 3.)).unwrap();
+    assert_eq!(hit.face().index(), 1);
+    assert!((hit.point() - Vec3::new(0.25, 0.5, 1.)).magnitude() < 1e-12);
+    assert!((hit.distance() - 2.).abs() < 1e-12);
+    let b = hit.barycentric();
+    assert!((b.sum() - 1.).abs() < 1e-12);
+    let t = hit.triangle();
+    let rebuilt = mesh.verticies()[t[0].index()].to_vec3() * b.x + mesh.verticies()[t[1].index()].to_vec3() * b.y + mesh.verticies()[t[2].index()].to_vec3() * b.z;
+    assert!((rebuilt - hit.point()).magnitude() < 1e-12);
+
+    assert!((query.signed_distance(Vec3::new(0.5, 0.5, 0.4)).unwrap() + 0.4).abs() < 1e-12);
+    // nearest to a corner and to an edge, where the face normals alone would disagree on the sign
+    assert!((query.signed_distance(Vec3::new(2., 2., 2.)).unwrap() - 3f64.sqrt()).abs() < 1e-12);
+    assert!((query.signed_distance(Vec3::new(2., 2., 0.5)).unwrap() - 2f64.sqrt()).abs() < 1e-12);
+
+    assert!(SurfaceQuery::new(&Mesh::<f64>::new()).closest_point(Vec3::zero()).is_none());
+}
@@ -0,0 +1,183 @@
+use super::{Mesh, Primitive};
+use super::topology::{edge_key, EdgeKey};
+use crate::core::{GeoNum, Point};
+use crate::geom::{Plane, Polyline};
+use std::collections::{HashMap, HashSet};
+use vek::Vec3;
+
+/// the most layers slice_layers will cut in one call
+const MAX_LAYERS: i64 = 1 << 20;
+
+/// cuts the mesh with a plane, returning the cross section as polylines. Loops wind anticlockwise seen from the
+/// side the plane faces, around solid material, so holes wind clockwise. Where the mesh is open the section can
+/// stop short, and those contours come back open
+pub fn slice<T>(mesh: &Mesh<T>, plane: &Plane<T>) -> Vec<Polyline<T>> where T: GeoNum {
+    let points: Vec<Vec3<T>> = mesh.verticies().iter().map(|v| v.to_vec3()).collect();
+    let distances: Vec<T> = points.iter().map(|&p| plane.distance(p)).collect();
+    let triangles = triangles(mesh);
+    let crossing: Vec<usize> = (0..triangles.len()).collect();
+    section(&points, &triangles, &crossing, &distances)
+}
+
+/// slices the mesh at every multiple of spacing along normal that passes through it, such as the layers of a print
+/// or the floors of a building. Each layer comes with its height along the normal.
+///
+/// A zero or non-finite normal, or a spacing that is not positive and finite, gives no layers. So does a mesh that
+/// would need more than about a million layers. Triangles with corners too far out to number their layers are
+/// left out
+pub fn slice_layers<T>(mesh: &Mesh<T>, normal: Vec3<T>, spacing: T) -> Vec<(T, Vec<Polyline<T>>)> where T: GeoNum {
+    let length = normal.magnitude();
+    let triangles = triangles(mesh);
+    if triangles.is_empty() || !(length.is_finite() && length > T::zero()) || !(spacing.is_finite() && spacing > T::zero()) {
+        return Vec::new();
+    }
+    let normal = normal / length;
+    let points: Vec<Vec3<T>> = mesh.verticies().iter().map(|v| v.to_vec3()).collect();
+    let heights: Vec<T> = points.iter().map(|p| normal.dot(*p)).collect();
+
+    // a triangle only needs visiting for the layers between its lowest and highest corner
+    let layer = |h: T| (h / spacing).floor().to_i64();
+    let spans: Vec<(usize, i64, i64)> = triangles.iter().enumerate().filter_map(|(t, tri)| {
+        if tri.iter().any(|&v| !heights[v].is_finite()) {
+            return None;
+        }
+        let low = tri.iter().map(|&v| heights[v]).fold(T::infinity(), T::min);
+        let high = tri.iter().map(|&v| heights[v]).fold(T::neg_infinity(), T::max);
+        let (start, mut end) = (layer(low)?.checked_add(1)?, layer(high)?);
+        if T::from_i64(end)? * spacing > high {
+            end -= 1;
+        }
+        Some((t, start, end))
+    }).collect();
+    let first = spans.iter().map(|s| s.1).min().unwrap_or(0);
+    let last = spans.iter().map(|s| s.2).max().unwrap_or(-1);
+    let count = match last.checked_sub(first) {
+        Some(c) if (0..MAX_LAYERS).contains(&c) => c as usize + 1,
+        _ => return Vec::new(),
+    };
+
+    let mut buckets: Vec<Vec<usize>> = vec![Vec::new(); count];
+    for &(t, start, end) in spans.iter() {
+        for k in start..=end {
+            buckets[(k - first) as usize].push(t);
+        }
+    }
+
+    buckets.iter().enumerate().map(|(k, crossing)| {
+        let h = T::from_i64(first + k as i64).unwrap() * spacing;
+        let distances: Vec<T> = heights.iter().map(|&d| d - h).collect();
+        (h, section(&points, &triangles, crossing, &distances))
+    }).collect()
+}
+
+fn triangles<T: GeoNum>(mesh: &Mesh<T>) -> Vec<[usize; 3]> {
+    mesh.faces().iter()
+        .flat_map(|f| f.triangles())
+        .map(|t| [t[0].index(), t[1].index(), t[2].index()])
+        .collect()
+}
+
+/// joins the segments where the given triangles cross zero distance into polylines. Verticies exactly on the plane
+/// count as being in front of it, so every crossing falls strictly inside an edge and each edge gives one point
+fn section<T: GeoNum>(points: &[Vec3<T>], triangles: &[[usize; 3]], crossing: &[usize], distances: &[T]) -> Vec<Polyline<T>> {
+    let front = |v: usize| distances[v] >= T::zero();
+    let mut cuts: HashMap<EdgeKey, Vec3<T>> = HashMap::new();
+    let mut segments: Vec<(EdgeKey, EdgeKey)> = Vec::new();
+    for &t in crossing {
+        let tri = triangles[t];
+        let (mut exit, mut entry) = (None, None);
+        for i in 0..3 {
+            let (a, b) = (tri[i], tri[(i + 1) % 3]);
+            if front(a) == front(b) {
+                continue;
+            }
+            let key = edge_key(a, b);
+            cuts.entry(key).or_insert_with(|| {
+                let (p, q) = (key.0, key.1);
+                let s = distances[p] / (distances[p] - distances[q]);
+                points[p] + (points[q] - points[p]) * s
+            });
+            if front(a) { exit = Some(key) } else { entry = Some(key) }
+        }
+        if let (Some(exit), Some(entry)) = (exit, entry) {
+            segments.push((exit, entry));
+        }
+    }
+
+    let mut leaving: HashMap<EdgeKey, Vec<usize>> = HashMap::new();
+    for (i, s) in segments.iter().enumerate() {
+        leaving.entry(s.0).or_default().push(i);
+    }
+    let arriving: HashSet<EdgeKey> = segments.iter().map(|s| s.1).collect();
+
+    // chains with a loose start are walked first so they are not mistaken for part of a loop
+    let mut order: Vec<usize> = (0..segments.len()).filter(|&i| !arriving.contains(&segments[i].0)).collect();
+    order.extend(0..segments.len());
+    let mut used = vec![false; segments.len()];
+    let mut polylines = Vec::new();
+    for start in order {
+        if used[start] {
+            continue;
+        }
+        let mut keys = vec![segments[start].0];
+        let mut current = start;
+        let closed = loop {
+            used[current] = true;
+            let end = segments[current].1;
+            if end == segments[start].0 {
+                break true;
+            }
+            keys.push(end);
+            match leaving.get(&end).and_then(|next| next.iter().copied().find(|&n| !used[n])) {
+                Some(next) => current = next,
+                None => break false,
+            }
+        };
+
+        let mut line: Vec<Vec3<T>> = keys.iter().map(|k| cuts[k]).collect();
+        // a vertex lying on the plane cuts every edge leaving it at the same spot
+        line.dedup();
+        while closed && line.len() > 1 && line.first() == line.last() {
+            line.pop();
+        }
+        if line.len() >= 2 {
+            polylines.push(Polyline::new(line, closed));
+        }
+    }
+    polylines
+}
+
+#[test]
+fn slice_test() {
+    use super::fixture::cuboid;
+
+    let mesh = cuboid(Vec3::zero(), Vec3::new(1., 1., 3.));
+
+    let section = slice(&mesh, &Plane::new(Vec3::new(0., 0., 0.5), Vec3::unit_z()));
+    assert_eq!(section.len(), 1);
+    assert!(section[0].is_closed());
+    assert!((section[0].length() - 4.).abs() < 1e-12);
+    let p = section[0].points();
+    let area: f64 = (0..p.len()).map(|i| p[i].x * p[(i + 1) % p.len()].y - p[(i + 1) % p.len()].x * p[i].y).sum();
+    assert!(area > 0.);
+
+    let layers = slice_layers(&mesh, Vec3::unit_z(), 1.);
+    assert_eq!(layers.iter().map(|l| l.0).collect::<Vec<_>>(), vec![1., 2., 3.]);
+    assert_eq!(layers[0].1.len(), 1);
+    assert!(layers[2].1.is_empty() || layers[2].1.iter().all(|l| l.points().iter().all(|p| p.z == 3.)));
+
+    // layers that cannot be numbered or would not fit in memory give nothing rather than panic
+    assert!(slice_layers(&mesh, Vec3::zero(), 1.).is_empty());
+    assert!(slice_layers(&mesh, Vec3::unit_z(), f64::NAN).is_empty());
+    assert!(slice_layers(&mesh, Vec3::unit_z(), 1e-300).is_empty());
+    assert!(slice_layers(&mesh, Vec3::unit_z(), 1e-12).is_empty());
+    assert_eq!(slice_layers(&mesh, Vec3::unit_z() * 2., 1.).len(), 3);
+
+    // without its ends the tube still gives a closed loop across it, but a cut along it runs out at the open ends
+    let mut open = mesh.clone();
+    open.set_faces(open.faces()[2..].to_vec());
+    assert!(slice(&open, &Plane::new(Vec3::new(0., 0., 0.5), Vec3::unit_z()))[0].is_closed());
+    let side = slice(&open, &Plane::new(Vec3::new(0.5, 0., 0.), Vec3::unit_x()));
+    assert_eq!(side.len(), 2);
+    assert!(side.iter().all(|l| !l.is_closed() && (l.length() - 3.).abs() < 1e-12));
+}
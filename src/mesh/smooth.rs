@@ -0,0 +1,204 @@
+use super::{Filter, Mesh, Primitive};
+use super::topology::{boundary_verticies, vertex_neighbours};
+use crate::core::{GeoNum, Point, Vertex};
+use std::collections::BTreeMap;
+use vek::Vec3;
+
+/// uniform Laplacian smoothing. Each pass moves every vertex towards the average of its neighbours by strength (0 - 1).
+/// shrinks the mesh a little with every pass
+#[derive(Debug)]
+pub struct Laplacian<T> where T: GeoNum {
+    iterations: usize,
+    strength: T,
+}
+
+impl<T> Laplacian<T> where T: GeoNum {
+    pub fn new(iterations: usize, strength: T) -> Self {
+        Self {
+            iterations,
+            strength,
+        }
+    }
+}
+
+impl<T> Filter<T> for Laplacian<T> where T: GeoNum {
+    fn apply(&self, mesh: &mut Mesh<T>) {
+        let neighbours = vertex_neighbours(mesh.verticies().len(), mesh.faces());
+        for _ in 0..self.iterations {
+            umbrella_step(mesh, &neighbours, self.strength);
+        }
+    }
+}
+
+/// Taubin lambda|mu smoothing. Every pass is a shrinking step of lambda followed by an inflating step of mu,
+/// where mu is negative and slightly larger in size than lambda, so the mesh keeps its volume
+#[derive(Debug)]
+pub struct Taubin<T> where T: GeoNum {
+    iterations: usize,
+    lambda: T,
+    mu: T,
+}
+
+impl<T> Taubin<T> where T: GeoNum {
+    pub fn new(iterations: usize, lambda: T, mu: T) -> Self {
+        Self {
+            iterations,
+            lambda,
+            mu,
+        }
+    }
+}
+
+impl<T> Filter<T> for Taubin<T> where T: GeoNum {
+    fn apply(&self, mesh: &mut Mesh<T>) {
+        let neighbours = vertex_neighbours(mesh.verticies().len(), mesh.faces());
+        for _ in 0..self.iterations {
+            umbrella_step(mesh, &neighbours, self.lambda);
+            umbrella_step(mesh, &neighbours, self.mu);
+        }
+    }
+}
+
+/// cotangent weighted smoothing, which follows the shape of the surface rather than the spacing of its verticies.
+/// open boundary verticies can be pinned so the outline does not creep inwards
+#[derive(Debug)]
+pub struct Cotangent<T> where T: GeoNum {
+    iterations: usize,
+    strength: T,
+    pin_boundary: bool,
+}
+
+impl<T> Cotangent<T> where T: GeoNum {
+    pub fn new(iterations: usize, strength: T, pin_boundary: bool) -> Self {
+        Self {
+            iterations,
+            strength,
+            pin_boundary,
+        }
+    }
+}
+
+impl<T> Filter<T> for Cotangent<T> where T: GeoNum {
+    fn apply(&self, mesh: &mut Mesh<T>) {
+        let count = mesh.verticies().len();
+        let pinned = if self.pin_boundary {
+            boundary_verticies(count, mesh.faces())
+        } else {
+            vec![false; count]
+        };
+
+        for _ in 0..self.iterations {
+            let points: Vec<Vec3<T>> = mesh.verticies().iter().map(|v| v.to_vec3()).collect();
+            let weights = cotangent_weights(mesh, &points);
+            let mut sum = vec![Vec3::<T>::zero(); count];
+            let mut total = vec![T::zero(); count];
+            for (&(i, j), &w) in weights.iter() {
+                sum[i] = sum[i] + (points[j] - points[i]) * w;
+                sum[j] = sum[j] + (points[i] - points[j]) * w;
+                total[i] = total[i] + w;
+                total[j] = total[j] + w;
+            }
+
+            let moved: Vec<Vertex<T>> = (0..count).map(|v| {
+                if pinned[v] || total[v] <= T::zero() {
+                    Vertex::from_vec3(points[v])
+                } else {
+                    Vertex::from_vec3(points[v] + sum[v] / total[v] * self.strength)
+                }
+            }).collect();
+            mesh.map_verts_indexed(|i, _| moved[i]);
+        }
+    }
+}
+
+fn umbrella_step<T: GeoNum>(mesh: &mut Mesh<T>, neighbours: &[Vec<usize>], factor: T) {
+    let points: Vec<Vec3<T>> = mesh.verticies().iter().map(|v| v.to_vec3()).collect();
+    mesh.map_verts_indexed(|i, v| {
+        let around = &neighbours[i];
+        if around.is_empty() {
+            return *v;
+        }
+        let sum = around.iter().fold(Vec3::zero(), |acc, &n| acc + points[n]);
+        let average = sum / T::from_usize(around.len()).unwrap();
+        Vertex::from_vec3(points[i] + (average - points[i]) * factor)
+    });
+}
+
+/// half the sum of the cotangents of the angles opposite each edge. Negative cotangents from obtuse
+/// angles are clamped to zero to keep the smoothing stable. Only the edges of each polygon are weighted: an edge
+/// of an n-gon takes the average cotangent over the n - 2 corners off it, which for a triangle is the usual one
+fn cotangent_weights<T: GeoNum>(mesh: &Mesh<T>, points: &[Vec3<T>]) -> BTreeMap<(usize, usize), T> {
+    let half = T::from_f64(0.5).unwrap();
+    let mut weights = BTreeMap::new();
+    for face in mesh.faces().iter() {
+        let verts = face.verticies();
+        let n = verts.len();
+        if n < 3 {
+            continue;
+        }
+        let share = half / T::from_usize(n - 2).unwrap();
+        for k in 0..n {
+            let (i, j) = (verts[k].index(), verts[(k + 1) % n].index());
+            for m in 2..n {
+                let o = verts[(k + m) % n].index();
+                let (a, b) = (points[i] - points[o], points[j] - points[o]);
+                let sin = a.cross(b).magnitude();
+                if sin <= T::zero() {
+                    continue;
+                }
+                let cot = (a.dot(b) / sin).max(T::zero());
+                let key = if i < j { (i, j) } else { (j, i) };
+                let w = weights.entry(key).or_insert_with(T::zero);
+                *w = *w + cot * share;
+            }
+        }
+    }
+    weights
+}
+
+#[test]
+fn smooth_test() {
+    use super::Face;
+    use crate::core::PointIndex;
+
+    // a flat 3x3 grid of verticies with the centre one pushed up
+    let mut mesh = Mesh::<f64>::new();
+    for y in 0..3 {
+        for x in 0..3 {
+            let z = if x == 1 && y == 1 { 1. } else { 0. };
+            mesh.add_vertex(Vertex::new(x as f64, y as f64, z));
+        }
+    }
+    for y in 0..2 {
+        for x in 0..2 {
+            let i = y * 3 + x;
+            mesh.add_face(Face::new(vec![PointIndex::new(i), PointIndex::new(i + 1), PointIndex::new(i + 4), PointIndex::new(i + 3)]));
+        }
+    }
+
+    let mut cot = mesh.clone();
+    Cotangent::new(10, 0.5, true).apply(&mut cot);
+    assert!(cot.verticies()[4].z < 0.01);
+    assert_eq!(cot.verticies()[0], Vertex::new(0., 0., 0.));
+
+    // the result does not depend on where each quad starts, so no diagonal is weighted
+    let mut turned = mesh.clone();
+    let faces = turned.faces().iter().map(|f| {
+        let mut verts = f.verticies();
+        verts.rotate_left(1);
+        Face::new(verts)
+    }).collect();
+    turned.set_faces(faces);
+    Cotangent::new(10, 0.5, false).apply(&mut turned);
+    let mut again = mesh.clone();
+    Cotangent::new(10, 0.5, false).apply(&mut again);
+    assert!(turned.verticies().iter().zip(again.verticies().iter()).all(|(a, b)| (a.to_vec3() - b.to_vec3()).magnitude() < 1e-12));
+
+    let mut lap = mesh.clone();
+    Laplacian::new(1, 1.).apply(&mut lap);
+    assert_eq!(lap.verticies()[4], Vertex::new(1., 1., 0.));
+
+    let mut taubin = mesh;
+    Taubin::new(5, 0.5, -0.53).apply(&mut taubin);
+    assert!(taubin.verticies()[4].z < 0.5);
+}
@@ -0,0 +1,343 @@
+use super::{Face, Filter, Mesh, Primitive};
+use super::topology::{edge_key, face_edges, EdgeKey};
+use crate::core::{GeoNum, Point, PointIndex, Vertex};
+use std::collections::{HashMap, HashSet};
+use vek::Vec3;
+
+/// settings shared by both subdivision schemes
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SubdivideOptions {
+    /// how many times to subdivide
+    pub levels: usize,
+    /// edges that stay sharp. Open edges are always treated as creases
+    pub creases: Vec<(PointIndex, PointIndex)>,
+    /// keep verticies on open edges where they are rather than smoothing the outline
+    pub fix_boundary: bool,
+}
+
+impl SubdivideOptions {
+    pub fn new(levels: usize) -> Self {
+        Self {
+            levels,
+            creases: Vec::new(),
+            fix_boundary: false,
+        }
+    }
+}
+
+/// Catmull-Clark subdivision. Works on any polygon mesh and always produces quads.
+/// per-vertex attributes are carried over linearly rather than smoothed with the surface: old verticies keep theirs,
+/// edge points take the average of the edge's ends and face points the average of the face's corners, so uv
+/// layouts keep their shape
+pub fn catmull_clark<T>(mesh: &mut Mesh<T>, options: &SubdivideOptions) where T: GeoNum {
+    let mut creases = crease_set(options);
+    for _ in 0..options.levels {
+        let topology = Topology::new(mesh, &creases);
+        let points = positions(mesh);
+        let half = T::from_f64(0.5).unwrap();
+
+        let face_points: Vec<Vec3<T>> = mesh.faces().iter()
+            .map(|f| average(f.verticies().iter().map(|v| points[v.index()])))
+            .collect();
+
+        // new verticies are laid out as: old verticies, then edge points, then face points
+        let edge_base = points.len();
+        let face_base = edge_base + topology.edges.len();
+        let edge_points: Vec<Vec3<T>> = topology.edges.iter().map(|(e, faces)| {
+            let (a, b) = (points[e.0], points[e.1]);
+            if topology.sharp(e) {
+                (a + b) * half
+            } else {
+                average(vec![a, b, face_points[faces[0]], face_points[faces[1]]].into_iter())
+            }
+        }).collect();
+
+        let mut verticies: Vec<Vec3<T>> = Vec::with_capacity(face_base + face_points.len());
+        for (v, p) in points.iter().enumerate() {
+            let sharp = topology.sharp_neighbours(v);
+            let moved = if options.fix_boundary && topology.on_boundary(v) {
+                *p
+            } else if sharp.len() == 2 {
+                crease_rule(*p, points[sharp[0]], points[sharp[1]])
+            } else if sharp.len() > 2 || topology.vertex_edges[v].is_empty() {
+                *p
+            } else {
+                let n = T::from_usize(topology.vertex_edges[v].len()).unwrap();
+                let f = average(topology.vertex_faces[v].iter().map(|&f| face_points[f]));
+                let r = average(topology.vertex_edges[v].iter().map(|e| (points[e.0] + points[e.1]) * half));
+                (f + r * T::from_f64(2.).unwrap() + *p * (n - T::from_f64(3.).unwrap())) / n
+            };
+            verticies.push(moved);
+        }
+        verticies.extend(edge_points);
+        verticies.extend(face_points);
+
+        let mut faces = Vec::new();
+        for (f, face) in mesh.faces().iter().enumerate() {
+            let edges = face_edges(face);
+            for i in 0..edges.len() {
+                let previous = edges[(i + edges.len() - 1) % edges.len()];
+                let (a, b) = edges[i];
+                faces.push(Face::new(vec![
+                    PointIndex::new(a),
+                    PointIndex::new(edge_base + topology.index[&edge_key(a, b)]),
+                    PointIndex::new(face_base + f),
+                    PointIndex::new(edge_base + topology.index[&edge_key(previous.0, previous.1)]),
+                ]));
+            }
+        }
+
+        let averages: Vec<Vec<usize>> = topology.edges.iter()
+            .map(|(e, _)| vec![e.0, e.1])
+            .chain(mesh.faces().iter().map(|f| f.verticies().iter().map(|v| v.index()).collect()))
+            .collect();
+        creases = split_creases(&creases, &topology, edge_base);
+        mesh.replace_geometry(verticies.into_iter().map(Vertex::from_vec3).collect(), faces, &averages);
+    }
+}
+
+/// Loop subdivision for triangle meshes. Other polygons are fanned into triangles first.
+/// per-vertex attributes are carried over linearly: old verticies keep theirs and edge points take the average
+/// of the edge's ends
+pub fn loop_subdivision<T>(mesh: &mut Mesh<T>, options: &SubdivideOptions) where T: GeoNum {
+    let mut creases = crease_set(options);
+    let triangles: Vec<Face> = mesh.faces().iter()
+        .flat_map(|f| f.triangles())
+        .map(|t| Face::new(t.to_vec()))
+        .collect();
+    mesh.set_faces(triangles);
+
+    for _ in 0..options.levels {
+        let topology = Topology::new(mesh, &creases);
+        let points = positions(mesh);
+        let f = |x: f64| T::from_f64(x).unwrap();
+
+        let edge_base = points.len();
+        let edge_points: Vec<Vec3<T>> = topology.edges.iter().map(|(e, faces)| {
+            let (a, b) = (points[e.0], points[e.1]);
+            if topology.sharp(e) {
+                (a + b) * f(0.5)
+            } else {
+                let opposite = |face: usize| mesh.faces()[face].verticies().iter()
+                    .map(|v| v.index())
+                    .find(|&v| v != e.0 && v != e.1)
+                    .unwrap();
+                (a + b) * f(0.375) + (points[opposite(faces[0])] + points[opposite(faces[1])]) * f(0.125)
+            }
+        }).collect();
+
+        let mut verticies: Vec<Vec3<T>> = Vec::with_capacity(edge_base + edge_points.len());
+        for (v, p) in points.iter().enumerate() {
+            let sharp = topology.sharp_neighbours(v);
+            let moved = if options.fix_boundary && topology.on_boundary(v) {
+                *p
+            } else if sharp.len() == 2 {
+                crease_rule(*p, points[sharp[0]], points[sharp[1]])
+            } else if sharp.len() > 2 || topology.vertex_edges[v].is_empty() {
+                *p
+            } else {
+                let n = topology.vertex_edges[v].len() as f64;
+                let c = 0.375 + 0.25 * (2. * std::f64::consts::PI / n).cos();
+                let beta = (0.625 - c * c) / n;
+                let sum = topology.vertex_edges[v].iter()
+                    .map(|e| points[if e.0 == v { e.1 } else { e.0 }])
+                    .fold(Vec3::zero(), |acc, x| acc + x);
+                *p * f(1. - n * beta) + sum * f(beta)
+            };
+            verticies.push(moved);
+        }
+        verticies.extend(edge_points);
+
+        let mut faces = Vec::new();
+        for face in mesh.faces().iter() {
+            let v: Vec<usize> = face.verticies().iter().map(|x| x.index()).collect();
+            let e = |a: usize, b: usize| edge_base + topology.index[&edge_key(a, b)];
+            let (ab, bc, ca) = (e(v[0], v[1]), e(v[1], v[2]), e(v[2], v[0]));
+            for tri in [[v[0], ab, ca], [ab, v[1], bc], [ca, bc, v[2]], [ab, bc, ca]].iter() {
+                faces.push(Face::new(tri.iter().map(|&i| PointIndex::new(i)).collect()));
+            }
+        }
+
+        let averages: Vec<Vec<usize>> = topology.edges.iter().map(|(e, _)| vec![e.0, e.1]).collect();
+        creases = split_creases(&creases, &topology, edge_base);
+        mesh.replace_geometry(verticies.into_iter().map(Vertex::from_vec3).collect(), faces, &averages);
+    }
+}
+
+/// filter that applies Catmull-Clark subdivision
+#[derive(Debug)]
+pub struct CatmullClark {
+    options: SubdivideOptions,
+}
+
+impl CatmullClark {
+    pub fn new(options: SubdivideOptions) -> Self {
+        Self {
+            options
+        }
+    }
+}
+
+impl<T> Filter<T> for CatmullClark where T: GeoNum {
+    fn apply(&self, mesh: &mut Mesh<T>) {
+        catmull_clark(mesh, &self.options);
+    }
+}
+
+/// filter that applies Loop subdivision
+#[derive(Debug)]
+pub struct LoopSubdivision {
+    options: SubdivideOptions,
+}
+
+impl LoopSubdivision {
+    pub fn new(options: SubdivideOptions) -> Self {
+        Self {
+            options
+        }
+    }
+}
+
+impl<T> Filter<T> for LoopSubdivision where T: GeoNum {
+    fn apply(&self, mesh: &mut Mesh<T>) {
+        loop_subdivision(mesh, &self.options);
+    }
+}
+
+/// edge adjacency for one level of subdivision. Edges are kept in first-seen order so new vertex indices are stable
+struct Topology {
+    edges: Vec<(EdgeKey, Vec<usize>)>,
+    index: HashMap<EdgeKey, usize>,
+    creases: HashSet<EdgeKey>,
+    vertex_edges: Vec<Vec<EdgeKey>>,
+    vertex_faces: Vec<Vec<usize>>,
+}
+
+impl Topology {
+    fn new<T: GeoNum>(mesh: &Mesh<T>, creases: &HashSet<EdgeKey>) -> Self {
+        let count = mesh.verticies().len();
+        let mut topology = Self {
+            edges: Vec::new(),
+            index: HashMap::new(),
+            creases: creases.clone(),
+            vertex_edges: vec![Vec::new(); count],
+            vertex_faces: vec![Vec::new(); count],
+        };
+        for (f, face) in mesh.faces().iter().enumerate() {
+            for (a, b) in face_edges(face) {
+                let key = edge_key(a, b);
+                let i = match topology.index.get(&key) {
+                    Some(&i) => i,
+                    None => {
+                        let i = topology.edges.len();
+                        topology.edges.push((key, Vec::new()));
+                        topology.index.insert(key, i);
+                        topology.vertex_edges[a].push(key);
+                        topology.vertex_edges[b].push(key);
+                        i
+                    },
+                };
+                topology.edges[i].1.push(f);
+                topology.vertex_faces[a].push(f);
+            }
+        }
+        topology
+    }
+
+    /// open, non-manifold and creased edges all use the crease rules
+    fn sharp(&self, e: &EdgeKey) -> bool {
+        self.edges[self.index[e]].1.len() != 2 || self.creases.contains(e)
+    }
+
+    fn sharp_neighbours(&self, v: usize) -> Vec<usize> {
+        self.vertex_edges[v].iter()
+            .filter(|e| self.sharp(e))
+            .map(|e| if e.0 == v { e.1 } else { e.0 })
+            .collect()
+    }
+
+    fn on_boundary(&self, v: usize) -> bool {
+        self.vertex_edges[v].iter().any(|e| self.edges[self.index[e]].1.len() != 2)
+    }
+}
+
+fn crease_set(options: &SubdivideOptions) -> HashSet<EdgeKey> {
+    options.creases.iter().map(|(a, b)| edge_key(a.index(), b.index())).collect()
+}
+
+/// each creased edge becomes two creased halves meeting at its new edge point
+fn split_creases(creases: &HashSet<EdgeKey>, topology: &Topology, edge_base: usize) -> HashSet<EdgeKey> {
+    let mut next = HashSet::new();
+    for e in creases.iter() {
+        if let Some(&i) = topology.index.get(e) {
+            next.insert(edge_key(e.0, edge_base + i));
+            next.insert(edge_key(edge_base + i, e.1));
+        }
+    }
+    next
+}
+
+fn crease_rule<T: GeoNum>(p: Vec3<T>, a: Vec3<T>, b: Vec3<T>) -> Vec3<T> {
+    p * T::from_f64(0.75).unwrap() + (a + b) * T::from_f64(0.125).unwrap()
+}
+
+fn positions<T: GeoNum>(mesh: &Mesh<T>) -> Vec<Vec3<T>> {
+    mesh.verticies().iter().map(|v| v.to_vec3()).collect()
+}
+
+fn average<T: GeoNum, I: Iterator<Item = Vec3<T>>>(points: I) -> Vec3<T> {
+    let mut sum = Vec3::zero();
+    let mut count = T::zero();
+    for p in points {
+        sum = sum + p;
+        count = count + T::one();
+    }
+    if count > T::zero() { sum / count } else { sum }
+}
+
+#[test]
+fn catmull_clark_test() {
+    // a unit cube shrinks towards a sphere-like shape and gains 4x the faces per level
+    let mut mesh = Mesh::<f64>::new();
+    for i in 0..8 {
+        mesh.add_vertex(Vertex::new((i & 1) as f64, ((i >> 1) & 1) as f64, ((i >> 2) & 1) as f64));
+    }
+    let quads = [[0, 2, 3, 1], [4, 5, 7, 6], [0, 1, 5, 4], [2, 6, 7, 3], [0, 4, 6, 2], [1, 3, 7, 5]];
+    for q in quads.iter() {
+        mesh.add_face(Face::new(q.iter().map(|&i| PointIndex::new(i)).collect()));
+    }
+
+    mesh.set_channel("height".into(), mesh.verticies().iter().map(|v| v.z).collect());
+
+    catmull_clark(&mut mesh, &SubdivideOptions::new(2));
+    assert_eq!(mesh.faces().len(), 6 * 16);
+    assert_eq!(mesh.verticies().len(), 98);
+    // the channel is carried along linearly, so the middle of the top face sits at the top
+    let height = mesh.channel("height").unwrap();
+    assert_eq!(height.len(), 98);
+    assert_eq!(height[8 + 12 + 1], 1.);
+    assert_eq!(height[0], 0.);
+    // the original corner moves in to 5/9 of the way along its diagonal after one level, and further after two
+    let corner = mesh.verticies()[0];
+    assert!(corner.x > 0.2 && corner.x == corner.y && corner.y == corner.z);
+    assert!(super::validate(&mesh).is_valid());
+}
+
+#[test]
+fn loop_test() {
+    let mut mesh = Mesh::<f64>::new();
+    mesh.make_face(vec![Vertex::new(0., 0., 0.), Vertex::new(1., 0., 0.), Vertex::new(0., 1., 0.)]);
+    mesh.set_uvs(vec![vek::Vec2::new(0., 0.), vek::Vec2::new(1., 0.), vek::Vec2::new(0., 1.)]);
+
+    // a lone triangle is all boundary, so with a fixed outline the corners stay put and edges split at their midpoints
+    let mut options = SubdivideOptions::new(1);
+    options.fix_boundary = true;
+    loop_subdivision(&mut mesh, &options);
+    assert_eq!(mesh.faces().len(), 4);
+    assert_eq!(mesh.verticies()[0], Vertex::new(0., 0., 0.));
+    assert!(mesh.verticies().contains(&Vertex::new(0.5, 0.5, 0.)));
+    // and the uvs, laid out like the positions, split the same way
+    for (v, uv) in mesh.verticies().iter().zip(mesh.uvs().unwrap().iter()) {
+        assert_eq!((v.x, v.y), (uv.x, uv.y));
+    }
+}
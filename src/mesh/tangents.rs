@@ -0,0 +1,275 @@
+use super::{Face, Mesh, Primitive};
+use crate::core::{GeoNum, Point, PointIndex};
+use std::collections::HashMap;
+use std::{error, fmt};
+use vek::{Vec3, Vec4};
+
+/// reasons tangents could not be generated
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TangentError {
+    /// the mesh has no per-vertex normals to build the tangent frame around
+    MissingNormals,
+    /// the mesh has no uvs to take the tangent direction from
+    MissingUvs,
+}
+
+impl fmt::Display for TangentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TangentError::MissingNormals => write!(f, "Mesh has no normals"),
+            TangentError::MissingUvs => write!(f, "Mesh has no uvs"),
+        }
+    }
+}
+
+impl error::Error for TangentError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// one triangle of the fans the faces are split into
+struct Triangle<T> where T: GeoNum {
+    corners: [usize; 3],
+    face: usize,
+    /// whether the uvs wind the same way round as the positions
+    right: bool,
+    /// direction of increasing u across the triangle, None when its uvs have no area
+    tangent: Option<Vec3<T>>,
+}
+
+/// builds per-vertex tangents from the mesh's normals and uvs following MikkTSpace, so normal maps baked against it
+/// line up. Each triangle takes its handedness from the winding of its uvs. At every corner the triangle's direction
+/// of increasing u is made perpendicular to the vertex normal and summed, weighted by the corner's angle measured in
+/// the plane of the normal, over a group of triangles: those around the vertex with the same handedness that are
+/// joined to each other through edges at the vertex. A vertex with more than one group is split so each copy has
+/// one tangent, and w holds the handedness. Polygons are treated as the fans exporters triangulate them into, and a
+/// polygon whose triangles disagree on handedness is split into those triangles
+pub fn generate_tangents<T>(mesh: &mut Mesh<T>) -> Result<(), TangentError> where T: GeoNum {
+    let normals = mesh.normals().ok_or(TangentError::MissingNormals)?.clone();
+    let uvs = mesh.uvs().ok_or(TangentError::MissingUvs)?.clone();
+    let points: Vec<Vec3<T>> = mesh.verticies().iter().map(|v| v.to_vec3()).collect();
+    let faces = mesh.faces().clone();
+
+    let mut triangles: Vec<Triangle<T>> = Vec::new();
+    for (f, face) in faces.iter().enumerate() {
+        let first = triangles.len();
+        for t in face.triangles() {
+            let i = [t[0].index(), t[1].index(), t[2].index()];
+            let (e1, e2) = (points[i[1]] - points[i[0]], points[i[2]] - points[i[0]]);
+            let (d1, d2) = (uvs[i[1]] - uvs[i[0]], uvs[i[2]] - uvs[i[0]]);
+            let r = d1.x * d2.y - d2.x * d1.y;
+            let tangent = if r == T::zero() { None } else { Some((e1 * d2.y - e2 * d1.y) / r) };
+            triangles.push(Triangle { corners: i, face: f, right: r > T::zero(), tangent });
+        }
+        // triangles without uv area go along with the rest of their polygon
+        let right = triangles[first..].iter().find(|t| t.tangent.is_some()).map(|t| t.right).unwrap_or(true);
+        for t in triangles[first..].iter_mut().filter(|t| t.tangent.is_none()) {
+            t.right = right;
+        }
+    }
+
+    let mut around = vec![Vec::new(); points.len()];
+    for (t, tri) in triangles.iter().enumerate() {
+        for &v in tri.corners.iter() {
+            around[v].push(t);
+        }
+    }
+
+    // the groups around each vertex, found by joining triangles of one handedness that share an edge at it
+    let mut groups: Vec<(usize, bool)> = Vec::new();
+    let mut group_of: HashMap<(usize, usize), usize> = HashMap::new();
+    for (v, tris) in around.iter().enumerate() {
+        let mut parent: Vec<usize> = (0..tris.len()).collect();
+        for a in 0..tris.len() {
+            for b in a + 1..tris.len() {
+                let (ta, tb) = (&triangles[tris[a]], &triangles[tris[b]]);
+                let shared = ta.corners.iter().filter(|&&c| c != v && tb.corners.contains(&c)).count();
+                if ta.right == tb.right && shared > 0 {
+                    let (ra, rb) = (root(&mut parent, a), root(&mut parent, b));
+                    parent[ra.max(rb)] = ra.min(rb);
+                }
+            }
+        }
+        let mut roots: HashMap<usize, usize> = HashMap::new();
+        for (k, &t) in tris.iter().enumerate() {
+            let r = root(&mut parent, k);
+            let g = *roots.entry(r).or_insert_with(|| {
+                groups.push((v, triangles[t].right));
+                groups.len() - 1
+            });
+            group_of.insert((t, v), g);
+        }
+    }
+
+    let mut sums = vec![Vec3::<T>::zero(); groups.len()];
+    for (t, tri) in triangles.iter().enumerate() {
+        let tangent = match tri.tangent {
+            Some(tangent) => tangent,
+            None => continue,
+        };
+        for k in 0..3 {
+            let v = tri.corners[k];
+            let n = normals[v];
+            let flat = |d: Vec3<T>| {
+                let d = d - n * n.dot(d);
+                let length = d.magnitude();
+                if length > T::zero() { Some(d / length) } else { None }
+            };
+            let along = flat(tangent);
+            let a = flat(points[tri.corners[(k + 2) % 3]] - points[v]);
+            let b = flat(points[tri.corners[(k + 1) % 3]] - points[v]);
+            if let (Some(along), Some(a), Some(b)) = (along, a, b) {
+                let angle = crate::core::maths::clamp(-T::one(), T::one(), a.dot(b)).acos();
+                let sum = &mut sums[group_of[&(t, v)]];
+                *sum = *sum + along * angle;
+            }
+        }
+    }
+
+    // the first group around a vertex keeps it, and any others get a copy of their own
+    let mut index = vec![0; groups.len()];
+    let mut kept = vec![false; points.len()];
+    for (g, &(v, _)) in groups.iter().enumerate() {
+        index[g] = if kept[v] {
+            mesh.duplicate_vertex(PointIndex::new(v), mesh.verticies()[v]).index()
+        } else {
+            kept[v] = true;
+            v
+        };
+    }
+
+    let mut rebuilt = Vec::with_capacity(faces.len());
+    let mut t = 0;
+    for (f, face) in faces.iter().enumerate() {
+        let mut tris = Vec::new();
+        while t < triangles.len() && triangles[t].face == f {
+            tris.push(t);
+            t += 1;
+        }
+        let corner = |t: usize, v: usize| PointIndex::new(index[group_of[&(t, v)]]);
+        if tris.iter().all(|&t| triangles[t].right == triangles[tris[0]].right) {
+            let verts = face.verticies().iter().map(|v| {
+                match tris.iter().find(|&&t| triangles[t].corners.contains(&v.index())) {
+                    Some(&t) => corner(t, v.index()),
+                    None => *v,
+                }
+            }).collect();
+            rebuilt.push(Face::new(verts));
+        } else {
+            for &t in tris.iter() {
+                rebuilt.push(Face::new(triangles[t].corners.iter().map(|&v| corner(t, v)).collect()));
+            }
+        }
+    }
+
+    let normals = mesh.normals().unwrap().clone();
+    let mut tangents: Vec<Vec4<T>> = normals.iter().map(|&n| {
+        let t = perpendicular(n);
+        Vec4::new(t.x, t.y, t.z, T::one())
+    }).collect();
+    for (g, &(_, right)) in groups.iter().enumerate() {
+        let v = index[g];
+        let length = sums[g].magnitude();
+        // without any uv direction to follow, any tangent perpendicular to the normal will do
+        let t = if length > T::zero() { sums[g] / length } else { perpendicular(normals[v]) };
+        let w = if right { T::one() } else { -T::one() };
+        tangents[v] = Vec4::new(t.x, t.y, t.z, w);
+    }
+
+    mesh.set_faces(rebuilt);
+    mesh.set_tangents(tangents);
+    Ok(())
+}
+
+/// the representative of x's set in a union find forest, halving the path on the way
+fn root(parent: &mut [usize], mut x: usize) -> usize {
+    while parent[x] != x {
+        parent[x] = parent[parent[x]];
+        x = parent[x];
+    }
+    x
+}
+
+fn perpendicular<T: GeoNum>(n: Vec3<T>) -> Vec3<T> {
+    let axis = if n.x.abs() < T::from_f64(0.9).unwrap() { Vec3::unit_x() } else { Vec3::unit_y() };
+    let t = n.cross(axis);
+    let length = t.magnitude();
+    if length > T::zero() { t / length } else { Vec3::unit_x() }
+}
+
+#[test]
+fn generate_tangents_test() {
+    use crate::core::Vertex;
+    use vek::Vec2;
+
+    // two quads side by side, the second with its uvs mirrored across the shared edge
+    let mut mesh = Mesh::<f64>::new();
+    for &(x, y) in [(0., 0.), (1., 0.), (1., 1.), (0., 1.), (2., 0.), (2., 1.)].iter() {
+        mesh.add_vertex(Vertex::new(x, y, 0.));
+    }
+    mesh.add_face(Face::new(vec![PointIndex::new(0), PointIndex::new(1), PointIndex::new(2), PointIndex::new(3)]));
+    mesh.add_face(Face::new(vec![PointIndex::new(1), PointIndex::new(4), PointIndex::new(5), PointIndex::new(2)]));
+    mesh.set_normals(vec![Vec3::unit_z(); 6]);
+    assert_eq!(generate_tangents(&mut mesh), Err(TangentError::MissingUvs));
+
+    let uvs = [(0., 0.), (1., 0.), (1., 1.), (0., 1.), (0., 0.), (0., 1.)];
+    mesh.set_uvs(uvs.iter().map(|&(u, v)| Vec2::new(u, v)).collect());
+    generate_tangents(&mut mesh).unwrap();
+
+    // the shared edge is split so each side keeps its own handedness
+    assert_eq!(mesh.verticies().len(), 8);
+    let tangents = mesh.tangents().unwrap();
+    for v in mesh.faces()[0].verticies() {
+        assert_eq!(tangents[v.index()], Vec4::new(1., 0., 0., 1.));
+    }
+    for v in mesh.faces()[1].verticies() {
+        assert_eq!(tangents[v.index()], Vec4::new(-1., 0., 0., -1.));
+    }
+
+    // tangents follow a mirroring transform, flipping their handedness
+    mesh.transform(vek::Mat4::scaling_3d(Vec3::new(-1., 1., 1.)));
+    assert_eq!(mesh.tangents().unwrap()[0], Vec4::new(-1., 0., 0., -1.));
+
+    // a bent quad and triangle with skewed uvs, against values worked through MikkTSpace's steps: per triangle
+    // uv derivatives, projected onto each vertex normal and weighted by the corner angle in the normal's plane
+    let mut mesh = Mesh::<f64>::new();
+    for &(x, y, z) in [(0., 0., 0.), (1., 0., 0.2), (1., 1., 0.5), (0., 1., 0.1), (2., 0.5, 0.)].iter() {
+        mesh.add_vertex(Vertex::new(x, y, z));
+    }
+    mesh.add_face(Face::new(vec![PointIndex::new(0), PointIndex::new(1), PointIndex::new(2), PointIndex::new(3)]));
+    mesh.add_face(Face::new(vec![PointIndex::new(1), PointIndex::new(4), PointIndex::new(2)]));
+    let normals = [(0., 0., 1.), (-0.2, 0., 1.), (-0.3, -0.2, 1.), (0., -0.1, 1.), (0.2, 0.1, 1.)];
+    mesh.set_normals(normals.iter().map(|&(x, y, z)| Vec3::new(x, y, z).normalized()).collect());
+    let uvs = [(0., 0.), (0.8, 0.1), (0.9, 0.9), (0.1, 0.7), (1., 0.4)];
+    mesh.set_uvs(uvs.iter().map(|&(u, v)| Vec2::new(u, v)).collect());
+    generate_tangents(&mut mesh).unwrap();
+
+    let expected = [
+        (0.979802015457608, -0.199970024016625, 0.),
+        (0.980310970735437, -0.023452434457334, 0.196062194147087),
+        (0.957911087217122, -0.108965609815966, 0.265580204201944),
+        (0.970747523712826, -0.238910988862846, -0.023891098886285),
+        (0.969968557754252, 0.128044914750269, -0.206798203025877),
+    ];
+    assert_eq!(mesh.verticies().len(), 5);
+    for (t, &(x, y, z)) in mesh.tangents().unwrap().iter().zip(expected.iter()) {
+        assert!((Vec3::new(t.x, t.y, t.z) - Vec3::new(x, y, z)).magnitude() < 1e-12);
+        assert_eq!(t.w, 1.);
+    }
+
+    // handedness belongs to each triangle, so a quad whose uvs fold over its diagonal is split along it
+    let mut mesh = Mesh::<f64>::new();
+    for &(x, y) in [(0., 0.), (1., 0.), (1., 1.), (0., 1.)].iter() {
+        mesh.add_vertex(Vertex::new(x, y, 0.));
+    }
+    mesh.add_face(Face::new(vec![PointIndex::new(0), PointIndex::new(1), PointIndex::new(2), PointIndex::new(3)]));
+    mesh.set_normals(vec![Vec3::unit_z(); 4]);
+    mesh.set_uvs(vec![Vec2::new(0., 0.), Vec2::new(1., 0.), Vec2::new(0.5, 0.2), Vec2::new(0.6, 0.1)]);
+    generate_tangents(&mut mesh).unwrap();
+    assert_eq!(mesh.faces().len(), 2);
+    assert_eq!(mesh.verticies().len(), 6);
+    let tangents = mesh.tangents().unwrap();
+    assert!(mesh.faces()[0].verticies().iter().all(|v| tangents[v.index()].w == 1.));
+    assert!(mesh.faces()[1].verticies().iter().all(|v| tangents[v.index()].w == -1.));
+}
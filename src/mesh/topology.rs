@@ -0,0 +1,54 @@
+use super::Face;
+use std::collections::HashMap;
+
+/// an undirected edge, smallest vertex first
+pub(crate) type EdgeKey = (usize, usize);
+
+pub(crate) fn edge_key(a: usize, b: usize) -> EdgeKey {
+    if a < b { (a, b) } else { (b, a) }
+}
+
+/// the directed edges around a face, following its winding
+pub(crate) fn face_edges(face: &Face) -> Vec<(usize, usize)> {
+    let verts = face.verticies();
+    (0..verts.len()).map(|i| (verts[i].index(), verts[(i + 1) % verts.len()].index())).collect()
+}
+
+/// every undirected edge and the faces that use it, along with whether each face runs along it from the smaller vertex to the larger
+pub(crate) fn edge_faces(faces: &[Face]) -> HashMap<EdgeKey, Vec<(usize, bool)>> {
+    let mut edges: HashMap<EdgeKey, Vec<(usize, bool)>> = HashMap::new();
+    for (f, face) in faces.iter().enumerate() {
+        for (a, b) in face_edges(face) {
+            edges.entry(edge_key(a, b)).or_default().push((f, a < b));
+        }
+    }
+    edges
+}
+
+/// the verticies joined to each vertex by an edge, without repeats
+pub(crate) fn vertex_neighbours(count: usize, faces: &[Face]) -> Vec<Vec<usize>> {
+    let mut neighbours = vec![Vec::new(); count];
+    for face in faces.iter() {
+        for (a, b) in face_edges(face) {
+            if !neighbours[a].contains(&b) {
+                neighbours[a].push(b);
+            }
+            if !neighbours[b].contains(&a) {
+                neighbours[b].push(a);
+            }
+        }
+    }
+    neighbours
+}
+
+/// flags every vertex that sits on an edge used by only one face
+pub(crate) fn boundary_verticies(count: usize, faces: &[Face]) -> Vec<bool> {
+    let mut boundary = vec![false; count];
+    for (edge, uses) in edge_faces(faces).iter() {
+        if uses.len() == 1 {
+            boundary[edge.0] = true;
+            boundary[edge.1] = true;
+        }
+    }
+    boundary
+}
@@ -0,0 +1,96 @@
+use vek::Vec2;
+
+/// splits a flat polygon into triangles by ear clipping. The outer ring winds anticlockwise and any holes wind
+/// clockwise; each ring lists indices into points. Holes are first bridged into the outer ring so one pass clips
+/// everything. Triangles come back as indices into points, wound like the outer ring
+pub(crate) fn triangulate(points: &[Vec2<f64>], outer: &[usize], holes: &[Vec<usize>]) -> Vec<[usize; 3]> {
+    let mut ring = outer.to_vec();
+    let mut holes: Vec<&Vec<usize>> = holes.iter().filter(|h| h.len() >= 3).collect();
+    let rightmost = |hole: &Vec<usize>| *hole.iter().max_by(|&&a, &&b| points[a].x.total_cmp(&points[b].x)).unwrap();
+    holes.sort_by(|a, b| points[rightmost(b)].x.total_cmp(&points[rightmost(a)].x));
+
+    for (h, hole) in holes.iter().enumerate() {
+        let m = rightmost(hole);
+        let pm = points[m];
+        let blocked = |p: usize| {
+            let pp = points[p];
+            let edges = ring.iter().zip(ring.iter().cycle().skip(1))
+                .chain(holes[h..].iter().flat_map(|h| h.iter().zip(h.iter().cycle().skip(1))));
+            for (&a, &b) in edges {
+                if a == p || b == p || a == m || b == m {
+                    continue;
+                }
+                if crosses(pm, pp, points[a], points[b]) {
+                    return true;
+                }
+            }
+            false
+        };
+        // bridge to the nearest outer vertex that can be seen from the hole, preferring ones to its right
+        let mut candidates: Vec<usize> = (0..ring.len()).collect();
+        candidates.sort_by(|&a, &b| {
+            let (pa, pb) = (points[ring[a]], points[ring[b]]);
+            (pa.x < pm.x).cmp(&(pb.x < pm.x)).then(pa.distance_squared(pm).total_cmp(&pb.distance_squared(pm)))
+        });
+        let bridge = match candidates.into_iter().find(|&i| !blocked(ring[i])) {
+            Some(i) => i,
+            None => continue,
+        };
+        let at = hole.iter().position(|&v| v == m).unwrap();
+        let mut merged = ring[..=bridge].to_vec();
+        merged.extend(hole[at..].iter().chain(hole[..=at].iter()));
+        merged.extend(ring[bridge..].iter());
+        ring = merged;
+    }
+
+    clip_ears(points, ring)
+}
+
+fn cross(o: Vec2<f64>, a: Vec2<f64>, b: Vec2<f64>) -> f64 {
+    (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+}
+
+/// true when segments ab and cd cross at a point inside both
+fn crosses(a: Vec2<f64>, b: Vec2<f64>, c: Vec2<f64>, d: Vec2<f64>) -> bool {
+    let (d1, d2) = (cross(a, b, c), cross(a, b, d));
+    let (d3, d4) = (cross(c, d, a), cross(c, d, b));
+    ((d1 > 0. && d2 < 0.) || (d1 < 0. && d2 > 0.)) && ((d3 > 0. && d4 < 0.) || (d3 < 0. && d4 > 0.))
+}
+
+fn clip_ears(points: &[Vec2<f64>], mut ring: Vec<usize>) -> Vec<[usize; 3]> {
+    let mut triangles = Vec::new();
+    while ring.len() > 3 {
+        let n = ring.len();
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = (ring[(i + n - 1) % n], ring[i], ring[(i + 1) % n]);
+            let (pa, pb, pc) = (points[a], points[b], points[c]);
+            if cross(pa, pb, pc) <= 0. {
+                return false;
+            }
+            // nothing else may sit inside or on the triangle, bar copies of its own corners from bridging
+            !ring.iter().any(|&p| {
+                let pp = points[p];
+                if pp == pa || pp == pb || pp == pc {
+                    return false;
+                }
+                cross(pa, pb, pp) >= 0. && cross(pb, pc, pp) >= 0. && cross(pc, pa, pp) >= 0.
+            })
+        });
+        // a ring with no ear left is degenerate; dropping its flattest corner keeps things moving
+        let i = ear.unwrap_or_else(|| {
+            (0..n).min_by(|&x, &y| {
+                let flat = |i: usize| cross(points[ring[(i + n - 1) % n]], points[ring[i]], points[ring[(i + 1) % n]]).abs();
+                flat(x).total_cmp(&flat(y))
+            }).unwrap()
+        });
+        let (a, b, c) = (ring[(i + n - 1) % n], ring[i], ring[(i + 1) % n]);
+        if cross(points[a], points[b], points[c]) > 0. {
+            triangles.push([a, b, c]);
+        }
+        ring.remove(i);
+    }
+    if ring.len() == 3 && cross(points[ring[0]], points[ring[1]], points[ring[2]]) > 0. {
+        triangles.push([ring[0], ring[1], ring[2]]);
+    }
+    triangles
+}
@@ -0,0 +1,427 @@
+use super::{Filter, Mesh, Primitive};
+use super::topology::{edge_faces, face_edges, edge_key};
+use crate::core::GeoNum;
+use std::collections::{HashMap, VecDeque};
+use vek::{Vec2, Vec3};
+
+/// settings for automatic unwrapping
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UnwrapOptions<T> where T: GeoNum {
+    /// the furthest a face's normal may turn from the first face of its chart, in radians. Smaller angles give
+    /// more, flatter charts with less stretch
+    pub max_angle: T,
+    /// space left around every chart in the packed layout, in uv units
+    pub padding: T,
+}
+
+impl<T> UnwrapOptions<T> where T: GeoNum {
+    /// charts within 60 degrees, one hundredth of the square apart
+    pub fn new() -> Self {
+        Self {
+            max_angle: T::pi() / T::from_f64(3.).unwrap(),
+            padding: T::from_f64(0.01).unwrap(),
+        }
+    }
+}
+
+impl<T> Default for UnwrapOptions<T> where T: GeoNum {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// gives the mesh uvs without projecting it: faces are grown into charts of similar facing, each chart is
+/// flattened with a least squares conformal map, and the charts are packed into the unit square. Verticies are
+/// split along chart borders. Returns the number of charts
+pub fn unwrap<T>(mesh: &mut Mesh<T>, options: &UnwrapOptions<T>) -> usize where T: GeoNum {
+    let points: Vec<Vec3<f64>> = mesh.verticies().iter()
+        .map(|p| Vec3::new(p.x.to_f64().unwrap(), p.y.to_f64().unwrap(), p.z.to_f64().unwrap()))
+        .collect();
+    let faces = mesh.faces().clone();
+    let normals: Vec<Vec3<f64>> = faces.iter().map(|f| {
+        let n = mesh.newell(f);
+        let n = Vec3::new(n.x.to_f64().unwrap(), n.y.to_f64().unwrap(), n.z.to_f64().unwrap());
+        if n.magnitude_squared() > 0. { n.normalized() } else { n }
+    }).collect();
+
+    let charts = segment(&faces, &normals, options.max_angle.to_f64().unwrap().cos());
+    let mut flat: Vec<HashMap<usize, Vec2<f64>>> = charts.iter()
+        .map(|chart| flatten(&points, &faces, &normals, chart))
+        .collect();
+    pack(&mut flat, options.padding.to_f64().unwrap());
+
+    let mut chart_of = vec![0; faces.len()];
+    for (c, chart) in charts.iter().enumerate() {
+        for &f in chart.iter() {
+            chart_of[f] = c;
+        }
+    }
+    let t = |v: f64| T::from_f64(v).unwrap();
+    let corners: Vec<Vec<Vec2<T>>> = faces.iter().enumerate().map(|(f, face)| {
+        face.verticies().iter().map(|v| {
+            let uv = flat[chart_of[f]][&v.index()];
+            Vec2::new(t(uv.x), t(uv.y))
+        }).collect()
+    }).collect();
+    mesh.set_corner_uvs(&corners);
+    charts.len()
+}
+
+/// grows charts face by face across shared edges, keeping every face within the cone of its chart's first face
+fn segment(faces: &[super::Face], normals: &[Vec3<f64>], min_cos: f64) -> Vec<Vec<usize>> {
+    let edges = edge_faces(faces);
+    let mut chart = vec![usize::MAX; faces.len()];
+    let mut charts = Vec::new();
+    for seed in 0..faces.len() {
+        if chart[seed] != usize::MAX {
+            continue;
+        }
+        let id = charts.len();
+        let mut members = vec![seed];
+        chart[seed] = id;
+        let mut queue = VecDeque::from(vec![seed]);
+        while let Some(f) = queue.pop_front() {
+            for (a, b) in face_edges(&faces[f]) {
+                let uses = &edges[&edge_key(a, b)];
+                // only grow across plain two sided edges, so charts never fold over a non-manifold seam
+                if uses.len() != 2 {
+                    continue;
+                }
+                for &(g, _) in uses.iter() {
+                    if chart[g] == usize::MAX && normals[g].dot(normals[seed]) >= min_cos {
+                        chart[g] = id;
+                        members.push(g);
+                        queue.push_back(g);
+                    }
+                }
+            }
+        }
+        charts.push(members);
+    }
+    charts
+}
+
+/// least squares conformal map of one chart, scaled so its uv area matches its surface area
+fn flatten(points: &[Vec3<f64>], faces: &[super::Face], normals: &[Vec3<f64>], chart: &[usize]) -> HashMap<usize, Vec2<f64>> {
+    let mut local: HashMap<usize, usize> = HashMap::new();
+    let mut verts = Vec::new();
+    let mut triangles = Vec::new();
+    for &f in chart.iter() {
+        for tri in faces[f].triangles() {
+            let t: Vec<usize> = tri.iter().map(|v| {
+                *local.entry(v.index()).or_insert_with(|| {
+                    verts.push(v.index());
+                    verts.len() - 1
+                })
+            }).collect();
+            triangles.push([t[0], t[1], t[2]]);
+        }
+    }
+    for &f in chart.iter() {
+        for v in faces[f].verticies() {
+            local.entry(v.index()).or_insert_with(|| {
+                verts.push(v.index());
+                verts.len() - 1
+            });
+        }
+    }
+
+    // a first guess from projecting onto the plane the chart faces
+    let normal = chart.iter().fold(Vec3::<f64>::zero(), |sum, &f| sum + normals[f]);
+    let normal = if normal.magnitude_squared() > 0. { normal.normalized() } else { Vec3::unit_z() };
+    let u = if normal.x.abs() < 0.9 { Vec3::unit_x() } else { Vec3::unit_y() }.cross(normal).normalized();
+    let w = normal.cross(u);
+    let mut uv: Vec<Vec2<f64>> = verts.iter().map(|&v| Vec2::new(points[v].dot(u), points[v].dot(w))).collect();
+
+    // pin the two verticies furthest apart along the chart's longest side
+    let (min, max) = verts.iter().fold((Vec3::broadcast(f64::INFINITY), Vec3::broadcast(f64::NEG_INFINITY)),
+        |(l, h), &v| (Vec3::partial_min(l, points[v]), Vec3::partial_max(h, points[v])));
+    let extent = max - min;
+    let axis = if extent.x >= extent.y && extent.x >= extent.z { 0 } else if extent.y >= extent.z { 1 } else { 2 };
+    let pin0 = (0..verts.len()).min_by(|&a, &b| points[verts[a]][axis].total_cmp(&points[verts[b]][axis])).unwrap();
+    let pin1 = (0..verts.len()).max_by(|&a, &b| points[verts[a]][axis].total_cmp(&points[verts[b]][axis])).unwrap();
+
+    if pin0 != pin1 && !triangles.is_empty() {
+        // turn the guess so the pins sit where they will be held
+        let span = uv[pin1] - uv[pin0];
+        let target = Vec2::new(points[verts[pin0]].distance(points[verts[pin1]]), 0.);
+        if span.magnitude_squared() > 0. {
+            let rotate = complex_div(target, span);
+            let origin = uv[pin0];
+            for p in uv.iter_mut() {
+                *p = complex_mul(*p - origin, rotate);
+            }
+        }
+        uv[pin0] = Vec2::zero();
+        uv[pin1] = target;
+        solve_lscm(points, &verts, &triangles, &mut uv, [pin0, pin1]);
+    }
+
+    // match the surface area so texel density is even between charts
+    let (mut area3, mut area2) = (0., 0.);
+    for t in triangles.iter() {
+        let (a, b, c) = (points[verts[t[0]]], points[verts[t[1]]], points[verts[t[2]]]);
+        area3 += (b - a).cross(c - a).magnitude() / 2.;
+        area2 += cross2(uv[t[1]] - uv[t[0]], uv[t[2]] - uv[t[0]]) / 2.;
+    }
+    if area2 < 0. {
+        for p in uv.iter_mut() {
+            p.x = -p.x;
+        }
+    }
+    let scale = if area2 != 0. { (area3 / area2.abs()).sqrt() } else { 1. };
+    verts.iter().enumerate().map(|(i, &v)| (v, uv[i] * scale)).collect()
+}
+
+fn cross2(a: Vec2<f64>, b: Vec2<f64>) -> f64 {
+    a.x * b.y - a.y * b.x
+}
+
+fn complex_mul(a: Vec2<f64>, b: Vec2<f64>) -> Vec2<f64> {
+    Vec2::new(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x)
+}
+
+fn complex_div(a: Vec2<f64>, b: Vec2<f64>) -> Vec2<f64> {
+    let d = b.magnitude_squared();
+    Vec2::new((a.x * b.x + a.y * b.y) / d, (a.y * b.x - a.x * b.y) / d)
+}
+
+/// minimises the conformal energy of the chart by conjugate gradients on the least squares system. Every triangle
+/// asks that the gradient of v be the gradient of u turned a quarter, weighted by its area
+fn solve_lscm(points: &[Vec3<f64>], verts: &[usize], triangles: &[[usize; 3]], uv: &mut [Vec2<f64>], pins: [usize; 2]) {
+    let n = verts.len();
+    // unknowns are u then v for every unpinned vertex
+    let mut column = vec![usize::MAX; n];
+    let mut free = 0;
+    for (i, c) in column.iter_mut().enumerate() {
+        if !pins.contains(&i) {
+            *c = free;
+            free += 1;
+        }
+    }
+    if free == 0 {
+        return;
+    }
+
+    let mut rows: Vec<Vec<(usize, f64)>> = Vec::new();
+    let mut rhs: Vec<f64> = Vec::new();
+    for t in triangles.iter() {
+        let (a, b, c) = (points[verts[t[0]]], points[verts[t[1]]], points[verts[t[2]]]);
+        let normal = (b - a).cross(c - a);
+        let double_area = normal.magnitude();
+        if double_area <= 0. {
+            continue;
+        }
+        let x = (b - a).normalized();
+        let y = normal.normalized().cross(x);
+        let flat = [Vec2::zero(), Vec2::new((b - a).dot(x), 0.), Vec2::new((c - a).dot(x), (c - a).dot(y))];
+        let weight = (double_area / 2.).sqrt();
+
+        // gradient of the hat function of each corner
+        let grads: Vec<Vec2<f64>> = (0..3).map(|k| {
+            let e = flat[(k + 2) % 3] - flat[(k + 1) % 3];
+            Vec2::new(-e.y, e.x) / double_area
+        }).collect();
+
+        for component in 0..2 {
+            let mut row = Vec::new();
+            let mut constant = 0.;
+            for k in 0..3 {
+                let g = grads[k];
+                let turned = Vec2::new(-g.y, g.x);
+                let (cu, cv) = if component == 0 { (-turned.x, g.x) } else { (-turned.y, g.y) };
+                let i = t[k];
+                if column[i] == usize::MAX {
+                    constant += (cu * uv[i].x + cv * uv[i].y) * weight;
+                } else {
+                    row.push((column[i], cu * weight));
+                    row.push((column[i] + free, cv * weight));
+                }
+            }
+            rows.push(row);
+            rhs.push(-constant);
+        }
+    }
+
+    let mut x = vec![0.; 2 * free];
+    for i in 0..n {
+        if column[i] != usize::MAX {
+            x[column[i]] = uv[i].x;
+            x[column[i] + free] = uv[i].y;
+        }
+    }
+    cgls(&rows, &rhs, &mut x);
+    for i in 0..n {
+        if column[i] != usize::MAX {
+            uv[i] = Vec2::new(x[column[i]], x[column[i] + free]);
+        }
+    }
+}
+
+/// conjugate gradients on the normal equations of a sparse least squares problem, starting from x
+fn cgls(rows: &[Vec<(usize, f64)>], b: &[f64], x: &mut [f64]) {
+    let size = x.len();
+    let multiply = |v: &[f64]| -> Vec<f64> { rows.iter().map(|r| r.iter().map(|&(c, a)| a * v[c]).sum()).collect() };
+    let transpose = |v: &[f64]| -> Vec<f64> {
+        let mut out = vec![0.; size];
+        for (r, &value) in rows.iter().zip(v.iter()) {
+            for &(c, a) in r.iter() {
+                out[c] += a * value;
+            }
+        }
+        out
+    };
+    let dot = |a: &[f64], b: &[f64]| a.iter().zip(b.iter()).map(|(x, y)| x * y).sum::<f64>();
+
+    let ax = multiply(x);
+    let mut r: Vec<f64> = b.iter().zip(ax.iter()).map(|(b, a)| b - a).collect();
+    let mut s = transpose(&r);
+    let mut p = s.clone();
+    let mut gamma = dot(&s, &s);
+    let tolerance = gamma * 1e-24;
+    for _ in 0..(size * 4).max(100) {
+        if gamma <= tolerance || gamma == 0. {
+            break;
+        }
+        let q = multiply(&p);
+        let qq = dot(&q, &q);
+        if qq == 0. {
+            break;
+        }
+        let alpha = gamma / qq;
+        for (xi, pi) in x.iter_mut().zip(p.iter()) {
+            *xi += alpha * pi;
+        }
+        for (ri, qi) in r.iter_mut().zip(q.iter()) {
+            *ri -= alpha * qi;
+        }
+        s = transpose(&r);
+        let next = dot(&s, &s);
+        let beta = next / gamma;
+        gamma = next;
+        for (pi, si) in p.iter_mut().zip(s.iter()) {
+            *pi = si + beta * *pi;
+        }
+    }
+}
+
+/// lays the charts out in shelves and scales the lot into the unit square. Padding is given in final uv units, so
+/// the layout is repeated until the scale it was padded for holds
+fn pack(charts: &mut [HashMap<usize, Vec2<f64>>], padding: f64) {
+    let bounds: Vec<(Vec2<f64>, Vec2<f64>)> = charts.iter().map(|chart| {
+        chart.values().fold((Vec2::broadcast(f64::INFINITY), Vec2::broadcast(f64::NEG_INFINITY)),
+            |(l, h), &p| (Vec2::partial_min(l, p), Vec2::partial_max(h, p)))
+    }).map(|(l, h)| if l.x > h.x { (Vec2::zero(), Vec2::zero()) } else { (l, h) }).collect();
+    let mut order: Vec<usize> = (0..charts.len()).collect();
+    order.sort_by(|&a, &b| (bounds[b].1.y - bounds[b].0.y).total_cmp(&(bounds[a].1.y - bounds[a].0.y)));
+
+    let layout = |gap: f64| {
+        let sizes: Vec<Vec2<f64>> = bounds.iter().map(|(l, h)| *h - *l + Vec2::broadcast(gap)).collect();
+        let total: f64 = sizes.iter().map(|s| s.x * s.y).sum();
+        let limit = total.sqrt().max(sizes.iter().fold(0f64, |m, s| m.max(s.x)));
+        let mut places = vec![Vec2::zero(); sizes.len()];
+        let (mut cursor, mut shelf, mut width) = (Vec2::zero(), 0f64, 0f64);
+        for &c in order.iter() {
+            if cursor.x + sizes[c].x > limit && cursor.x > 0. {
+                cursor = Vec2::new(0., cursor.y + shelf);
+                shelf = 0.;
+            }
+            places[c] = cursor;
+            cursor.x += sizes[c].x;
+            shelf = shelf.max(sizes[c].y);
+            width = width.max(cursor.x);
+        }
+        let side = width.max(cursor.y + shelf);
+        (places, if side > 0. { 1. / side } else { 1. })
+    };
+
+    let (mut places, mut scale) = layout(0.);
+    let mut gap = 0.;
+    for _ in 0..32 {
+        if gap * scale >= padding * (1. - 1e-9) {
+            break;
+        }
+        gap = padding / scale;
+        let (p, s) = layout(gap);
+        places = p;
+        scale = s;
+    }
+
+    for (c, chart) in charts.iter_mut().enumerate() {
+        let shift = places[c] + Vec2::broadcast(gap / 2.) - bounds[c].0;
+        for p in chart.values_mut() {
+            *p = (*p + shift) * scale;
+        }
+    }
+}
+
+/// filter that unwraps the mesh into packed charts
+#[derive(Debug)]
+pub struct Unwrap<T> where T: GeoNum {
+    options: UnwrapOptions<T>,
+}
+
+impl<T> Unwrap<T> where T: GeoNum {
+    pub fn new(options: UnwrapOptions<T>) -> Self {
+        Self {
+            options,
+        }
+    }
+}
+
+impl<T> Filter<T> for Unwrap<T> where T: GeoNum {
+    fn apply(&self, mesh: &mut Mesh<T>) {
+        unwrap(mesh, &self.options);
+    }
+}
+
+#[test]
+fn unwrap_test() {
+    use super::Face;
+    use super::fixture::cube;
+    use crate::core::{PointIndex, Vertex};
+
+    let mut cube = cube(0., 1.);
+
+    let options = UnwrapOptions { padding: 0.05, ..UnwrapOptions::new() };
+    assert_eq!(unwrap(&mut cube, &options), 6);
+    assert_eq!(cube.verticies().len(), 24);
+    let uvs = cube.uvs().unwrap();
+    assert!(uvs.iter().all(|uv| uv.x >= 0. && uv.y >= 0. && uv.x <= 1. && uv.y <= 1.));
+
+    // each side flattens to a square, kept at least the padding away from the others
+    let boxes: Vec<(Vec2<f64>, Vec2<f64>)> = cube.faces().iter().map(|f| {
+        let corners: Vec<Vec2<f64>> = f.verticies().iter().map(|v| uvs[v.index()]).collect();
+        let side = corners[0].distance(corners[1]);
+        assert!((corners[1].distance(corners[2]) - side).abs() < 1e-6);
+        assert!((corners[0].distance(corners[2]) - side * 2f64.sqrt()).abs() < 1e-6);
+        corners.iter().fold((Vec2::broadcast(2.), Vec2::broadcast(-1.)), |(l, h), &p| (Vec2::partial_min(l, p), Vec2::partial_max(h, p)))
+    }).collect();
+    for a in 0..boxes.len() {
+        for b in a + 1..boxes.len() {
+            let apart = (boxes[b].0.x - boxes[a].1.x).max(boxes[a].0.x - boxes[b].1.x)
+                .max(boxes[b].0.y - boxes[a].1.y).max(boxes[a].0.y - boxes[b].1.y);
+            assert!(apart >= 0.05 - 1e-9);
+        }
+    }
+
+    // a gently rolling sheet becomes a single chart, flattened without shear
+    let mut sheet = Mesh::<f64>::new();
+    for y in 0..5 {
+        for x in 0..5 {
+            sheet.add_vertex(Vertex::new(x as f64, y as f64, (x as f64 * 0.5).sin() * 0.5));
+        }
+    }
+    for y in 0..4 {
+        for x in 0..4 {
+            let i = y * 5 + x;
+            sheet.add_face(Face::new(vec![PointIndex::new(i), PointIndex::new(i + 1), PointIndex::new(i + 6), PointIndex::new(i + 5)]));
+        }
+    }
+    assert_eq!(unwrap(&mut sheet, &UnwrapOptions::new()), 1);
+    let uvs = sheet.uvs().unwrap();
+    for y in 0..4 {
+        let (a, b, c) = (uvs[y * 5], uvs[y * 5 + 1], uvs[y * 5 + 5]);
+        assert!(((b - a).dot(c - a)).abs() < 1e-3 * (b - a).magnitude_squared());
+    }
+}
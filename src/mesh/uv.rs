@@ -0,0 +1,179 @@
+use super::{Filter, Mesh, Primitive};
+use crate::core::{GeoNum, Point};
+use vek::{Vec2, Vec3};
+
+/// the shape texture coordinates are projected from
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Projection {
+    /// straight along the axis onto a flat plane
+    Planar,
+    /// onto the three axis planes, each face taking whichever it faces most. Also known as triplanar
+    Box,
+    /// around the axis, u running round and v along it
+    Cylindrical,
+    /// around the centre, u as longitude about the axis and v as latitude
+    Spherical,
+}
+
+/// where a projection sits and how its coordinates are laid out
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UvOptions<T> where T: GeoNum {
+    /// the direction planar projection looks along, and the pole of cylindrical and spherical projection
+    pub axis: Vec3<T>,
+    /// the middle of cylindrical and spherical projection
+    pub centre: Vec3<T>,
+    /// multiplies the projected coordinates
+    pub scale: Vec2<T>,
+    /// added after scaling
+    pub offset: Vec2<T>,
+}
+
+impl<T> UvOptions<T> where T: GeoNum {
+    /// projects along z, corale's up axis, with no scale or offset
+    pub fn new() -> Self {
+        Self {
+            axis: Vec3::unit_z(),
+            centre: Vec3::zero(),
+            scale: Vec2::one(),
+            offset: Vec2::zero(),
+        }
+    }
+}
+
+impl<T> Default for UvOptions<T> where T: GeoNum {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// replaces the mesh's uvs with a projection. Verticies are split where faces meeting at them need different uvs,
+/// such as along the wrap of cylindrical and spherical projection or between the sides of box projection
+pub fn project_uvs<T>(mesh: &mut Mesh<T>, projection: Projection, options: &UvOptions<T>) where T: GeoNum {
+    let axis = options.axis.normalized();
+    let tangent = if axis.x.abs() < T::from_f64(0.9).unwrap() { Vec3::unit_x() } else { Vec3::unit_y() };
+    let tangent = axis.cross(tangent).cross(axis).normalized();
+    let bitangent = axis.cross(tangent);
+    let half = T::from_f64(0.5).unwrap();
+
+    let corners: Vec<Vec<Vec2<T>>> = mesh.faces().iter().map(|face| {
+        let points: Vec<Vec3<T>> = face.verticies().iter().map(|v| mesh.verticies()[v.index()].to_vec3()).collect();
+        let mut uvs: Vec<Vec2<T>> = match projection {
+            Projection::Planar => points.iter().map(|p| Vec2::new(p.dot(tangent), p.dot(bitangent))).collect(),
+            Projection::Box => {
+                let n = mesh.face_normal(face);
+                let (ax, ay, az) = (n.x.abs(), n.y.abs(), n.z.abs());
+                points.iter().map(|p| {
+                    // mirrored on the far sides so text reads the right way round from outside
+                    if ax >= ay && ax >= az {
+                        Vec2::new(p.y * n.x.signum(), p.z)
+                    } else if ay >= az {
+                        Vec2::new(-p.x * n.y.signum(), p.z)
+                    } else {
+                        Vec2::new(p.x, p.y * n.z.signum())
+                    }
+                }).collect()
+            },
+            Projection::Cylindrical | Projection::Spherical => {
+                let mut uvs: Vec<Vec2<T>> = points.iter().map(|p| {
+                    let d = *p - options.centre;
+                    let u = d.dot(bitangent).atan2(d.dot(tangent)) / T::rad() + half;
+                    let v = if projection == Projection::Cylindrical {
+                        d.dot(axis)
+                    } else {
+                        let length = d.magnitude();
+                        let cos = if length > T::zero() { d.dot(axis) / length } else { T::one() };
+                        T::one() - crate::core::maths::clamp(-T::one(), T::one(), cos).acos() / T::pi()
+                    };
+                    Vec2::new(u, v)
+                }).collect();
+
+                // a face straddling the wrap takes the copies of its corners from the far side
+                let (low, high) = uvs.iter().fold((T::infinity(), T::neg_infinity()), |(l, h), uv| (l.min(uv.x), h.max(uv.x)));
+                if high - low > half {
+                    for uv in uvs.iter_mut().filter(|uv| uv.x < half) {
+                        uv.x = uv.x + T::one();
+                    }
+                }
+                // points on the axis have no longitude of their own, so borrow the face's
+                let on_axis: Vec<bool> = points.iter().map(|p| {
+                    let d = *p - options.centre;
+                    (d - axis * d.dot(axis)).magnitude() <= T::epsilon() * (T::one() + d.magnitude())
+                }).collect();
+                let others: Vec<T> = (0..uvs.len()).filter(|&i| !on_axis[i]).map(|i| uvs[i].x).collect();
+                if !others.is_empty() && others.len() < uvs.len() {
+                    let mean = others.iter().fold(T::zero(), |sum, &u| sum + u) / T::from_usize(others.len()).unwrap();
+                    for (uv, _) in uvs.iter_mut().zip(on_axis.iter()).filter(|(_, &axis)| axis) {
+                        uv.x = mean;
+                    }
+                }
+                uvs
+            },
+        };
+        for uv in uvs.iter_mut() {
+            *uv = *uv * options.scale + options.offset;
+        }
+        uvs
+    }).collect();
+
+    mesh.set_corner_uvs(&corners);
+}
+
+/// filter that projects uvs onto the mesh
+#[derive(Debug)]
+pub struct UvProjection<T> where T: GeoNum {
+    projection: Projection,
+    options: UvOptions<T>,
+}
+
+impl<T> UvProjection<T> where T: GeoNum {
+    pub fn new(projection: Projection, options: UvOptions<T>) -> Self {
+        Self {
+            projection,
+            options,
+        }
+    }
+}
+
+impl<T> Filter<T> for UvProjection<T> where T: GeoNum {
+    fn apply(&self, mesh: &mut Mesh<T>) {
+        project_uvs(mesh, self.projection, &self.options);
+    }
+}
+
+#[test]
+fn project_uvs_test() {
+    use super::fixture::cube;
+
+    let cube = cube(0., 1.);
+
+    let mut planar = cube.clone();
+    let options = UvOptions { scale: Vec2::new(2., 2.), offset: Vec2::new(0.5, 0.), ..UvOptions::new() };
+    project_uvs(&mut planar, Projection::Planar, &options);
+    assert_eq!(planar.verticies().len(), 8);
+    assert_eq!(planar.uvs().unwrap()[7], Vec2::new(2.5, 2.));
+
+    // each side of a box mapped cube covers a whole unit square, so corners split between sides
+    let mut boxed = cube.clone();
+    project_uvs(&mut boxed, Projection::Box, &UvOptions::new());
+    assert!(boxed.verticies().len() > 8);
+    for face in boxed.faces() {
+        let uvs: Vec<Vec2<f64>> = face.verticies().iter().map(|v| boxed.uvs().unwrap()[v.index()]).collect();
+        let (min, max) = uvs.iter().fold((Vec2::broadcast(9.), Vec2::broadcast(-9.)), |(l, h), &uv| (Vec2::partial_min(l, uv), Vec2::partial_max(h, uv)));
+        assert_eq!(max - min, Vec2::one());
+    }
+
+    // a cylinder mapped tube splits along the wrap
+    let options = UvOptions { centre: Vec3::new(0.5, 0.5, 0.), ..UvOptions::new() };
+    let mut tube = cube.clone();
+    tube.set_faces(tube.faces()[2..].to_vec());
+    project_uvs(&mut tube, Projection::Cylindrical, &options);
+    assert_eq!(tube.verticies().len(), 10);
+    for face in tube.faces() {
+        let u: Vec<f64> = face.verticies().iter().map(|v| tube.uvs().unwrap()[v.index()].x).collect();
+        assert!(u.iter().fold(0f64, |m, &x| m.max(x)) - u.iter().fold(2f64, |m, &x| m.min(x)) <= 0.5);
+    }
+
+    let mut out = Vec::new();
+    crate::wavefront::write(&tube, &mut out, &crate::wavefront::ExportOptions::default()).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap().matches("\nvt ").count(), 10);
+}
@@ -0,0 +1,256 @@
+use super::{Face, FaceIndex, Mesh, Primitive};
+use super::topology::{edge_faces, face_edges, edge_key};
+use crate::core::{GeoNum, PointIndex};
+use std::collections::{HashMap, VecDeque};
+
+/// a single problem found in a mesh
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MeshIssue {
+    /// the face refers to a vertex that does not exist
+    InvalidIndex { face: FaceIndex, vertex: PointIndex },
+    /// the face has fewer than three corners
+    TooFewVerticies(FaceIndex),
+    /// the face visits the same vertex more than once
+    RepeatedIndex { face: FaceIndex, vertex: PointIndex },
+    /// the face has no area
+    ZeroArea(FaceIndex),
+    /// the face uses exactly the same verticies as an earlier face
+    DuplicateFace { face: FaceIndex, original: FaceIndex },
+    /// two faces share an edge but run along it in the same direction
+    InconsistentWinding { face: FaceIndex, neighbour: FaceIndex },
+}
+
+/// every issue found by validate, in face order
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub issues: Vec<MeshIssue>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// counts of what repair changed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RepairReport {
+    /// out of range vertex references dropped from faces
+    pub invalid_references: usize,
+    /// faces removed for having repeated corners, too few corners or no area
+    pub degenerate_faces: usize,
+    /// faces removed for repeating an earlier face
+    pub duplicate_faces: usize,
+    /// faces whose winding was reversed to match their neighbours
+    pub flipped_faces: usize,
+}
+
+/// checks every face of the mesh and reports everything that is wrong with it
+pub fn validate<T>(mesh: &Mesh<T>) -> ValidationReport where T: GeoNum {
+    let mut issues = Vec::new();
+    let count = mesh.verticies().len();
+    let mut seen: HashMap<Vec<usize>, usize> = HashMap::new();
+
+    for (f, face) in mesh.faces().iter().enumerate() {
+        let index = FaceIndex::new(f);
+        let verts = face.verticies();
+        let mut valid = true;
+
+        for v in verts.iter() {
+            if v.index() >= count {
+                issues.push(MeshIssue::InvalidIndex { face: index, vertex: *v });
+                valid = false;
+            }
+        }
+        if verts.len() < 3 {
+            issues.push(MeshIssue::TooFewVerticies(index));
+            valid = false;
+        }
+        for (i, v) in verts.iter().enumerate() {
+            if verts[..i].contains(v) {
+                issues.push(MeshIssue::RepeatedIndex { face: index, vertex: *v });
+                valid = false;
+                break;
+            }
+        }
+        if valid && is_zero_area(mesh, face) {
+            issues.push(MeshIssue::ZeroArea(index));
+        }
+
+        let key = sorted(face);
+        match seen.get(&key) {
+            Some(&original) => issues.push(MeshIssue::DuplicateFace { face: index, original: FaceIndex::new(original) }),
+            None => {
+                seen.insert(key, f);
+            },
+        }
+    }
+
+    for uses in edge_faces(mesh.faces()).values() {
+        for (i, &(a, forward_a)) in uses.iter().enumerate() {
+            for &(b, forward_b) in uses[i + 1..].iter() {
+                if a != b && forward_a == forward_b {
+                    issues.push(MeshIssue::InconsistentWinding { face: FaceIndex::new(b.max(a)), neighbour: FaceIndex::new(a.min(b)) });
+                }
+            }
+        }
+    }
+    issues.sort_by_key(|i| match i {
+        MeshIssue::InvalidIndex { face, .. } | MeshIssue::TooFewVerticies(face) | MeshIssue::RepeatedIndex { face, .. } |
+        MeshIssue::ZeroArea(face) | MeshIssue::DuplicateFace { face, .. } | MeshIssue::InconsistentWinding { face, .. } => *face,
+    });
+
+    ValidationReport { issues }
+}
+
+/// fixes what it can: drops invalid references, removes degenerate and duplicate faces, then makes the winding
+/// agree across each connected piece. Closed pieces are turned so their faces point outwards
+pub fn repair<T>(mesh: &mut Mesh<T>) -> RepairReport where T: GeoNum {
+    let mut report = RepairReport::default();
+    let count = mesh.verticies().len();
+    let mut faces = Vec::with_capacity(mesh.faces().len());
+    let mut seen = HashMap::new();
+
+    for face in mesh.faces().iter() {
+        let mut verts: Vec<PointIndex> = face.verticies();
+        let before = verts.len();
+        verts.retain(|v| v.index() < count);
+        report.invalid_references += before - verts.len();
+
+        // collapse runs of the same vertex, including across the wrap
+        verts.dedup();
+        while verts.len() > 1 && verts.first() == verts.last() {
+            verts.pop();
+        }
+
+        let repeated = (0..verts.len()).any(|i| verts[..i].contains(&verts[i]));
+        let face = Face::new(verts);
+        if face.len() < 3 || repeated || is_zero_area(mesh, &face) {
+            report.degenerate_faces += 1;
+            continue;
+        }
+        if seen.insert(sorted(&face), ()).is_some() {
+            report.duplicate_faces += 1;
+            continue;
+        }
+        faces.push(face);
+    }
+
+    report.flipped_faces = unify_winding(mesh, &mut faces);
+    mesh.set_faces(faces);
+    report
+}
+
+/// walks each connected piece breadth first from its first face, flipping neighbours that disagree with it.
+/// edges shared by more than two faces are not walked across
+fn unify_winding<T: GeoNum>(mesh: &Mesh<T>, faces: &mut [Face]) -> usize {
+    let edges = edge_faces(faces);
+    let mut flipped = vec![false; faces.len()];
+    let mut visited = vec![false; faces.len()];
+
+    for seed in 0..faces.len() {
+        if visited[seed] {
+            continue;
+        }
+        visited[seed] = true;
+        let mut component = vec![seed];
+        let mut closed = true;
+        let mut queue = VecDeque::new();
+        queue.push_back(seed);
+
+        while let Some(f) = queue.pop_front() {
+            for (a, b) in face_edges(&faces[f]) {
+                let uses = &edges[&edge_key(a, b)];
+                if uses.len() != 2 {
+                    closed = false;
+                    continue;
+                }
+                let (other, _) = *uses.iter().find(|u| u.0 != f).unwrap_or(&uses[0]);
+                if other == f || visited[other] {
+                    continue;
+                }
+                // after any flips so far, f runs a -> b; a consistent neighbour must run b -> a
+                if face_edges(&faces[other]).contains(&(a, b)) {
+                    faces[other].flip();
+                    flipped[other] = !flipped[other];
+                }
+                visited[other] = true;
+                component.push(other);
+                queue.push_back(other);
+            }
+        }
+
+        if closed && signed_volume(mesh, faces, &component) < T::zero() {
+            for &f in component.iter() {
+                faces[f].flip();
+                flipped[f] = !flipped[f];
+            }
+        }
+    }
+    flipped.iter().filter(|&&f| f).count()
+}
+
+fn signed_volume<T: GeoNum>(mesh: &Mesh<T>, faces: &[Face], component: &[usize]) -> T {
+    let verts = mesh.verticies();
+    let mut volume = T::zero();
+    for &f in component.iter() {
+        for tri in faces[f].triangles() {
+            let a = verts[tri[0].index()];
+            let b = verts[tri[1].index()];
+            let c = verts[tri[2].index()];
+            volume = volume + a.x * (b.y * c.z - b.z * c.y) - a.y * (b.x * c.z - b.z * c.x) + a.z * (b.x * c.y - b.y * c.x);
+        }
+    }
+    volume
+}
+
+/// a face has no area when its Newell vector vanishes relative to the size of the face
+fn is_zero_area<T: GeoNum>(mesh: &Mesh<T>, face: &Face) -> bool {
+    let verts = mesh.verticies();
+    let mut longest = T::zero();
+    for (a, b) in face_edges(face) {
+        let (a, b) = (verts[a], verts[b]);
+        let d = (a.x - b.x) * (a.x - b.x) + (a.y - b.y) * (a.y - b.y) + (a.z - b.z) * (a.z - b.z);
+        longest = longest.max(d);
+    }
+    mesh.newell(face).magnitude() <= T::epsilon() * longest
+}
+
+fn sorted(face: &Face) -> Vec<usize> {
+    let mut key: Vec<usize> = face.verticies().iter().map(|v| v.index()).collect();
+    key.sort_unstable();
+    key
+}
+
+#[test]
+fn repair_test() {
+    use crate::core::Vertex;
+
+    // a tetrahedron with one face wound the wrong way, plus a duplicate, a degenerate face and a bad index
+    let mut mesh = Mesh::<f64>::new();
+    for v in [(0., 0., 0.), (1., 0., 0.), (0., 1., 0.), (0., 0., 1.)].iter() {
+        mesh.add_vertex(Vertex::from_tripple(*v));
+    }
+    let face = |v: &[usize]| Face::new(v.iter().map(|&i| PointIndex::new(i)).collect());
+    mesh.add_face(face(&[0, 2, 1]));
+    mesh.add_face(face(&[0, 1, 3]));
+    mesh.add_face(face(&[1, 2, 3]));
+    mesh.add_face(face(&[0, 2, 3]));
+    mesh.add_face(face(&[1, 0, 2]));
+    mesh.add_face(face(&[0, 1, 1]));
+    mesh.add_face(face(&[0, 3, 9, 1]));
+
+    let report = validate(&mesh);
+    assert!(report.issues.contains(&MeshIssue::InconsistentWinding { face: FaceIndex::new(3), neighbour: FaceIndex::new(1) }));
+    assert!(report.issues.contains(&MeshIssue::DuplicateFace { face: FaceIndex::new(4), original: FaceIndex::new(0) }));
+    assert!(report.issues.contains(&MeshIssue::RepeatedIndex { face: FaceIndex::new(5), vertex: PointIndex::new(1) }));
+    assert!(report.issues.contains(&MeshIssue::InvalidIndex { face: FaceIndex::new(6), vertex: PointIndex::new(9) }));
+
+    let repaired = repair(&mut mesh);
+    assert_eq!(repaired.invalid_references, 1);
+    assert_eq!(repaired.degenerate_faces, 1);
+    assert_eq!(repaired.duplicate_faces, 2);
+    assert_eq!(repaired.flipped_faces, 1);
+    assert!(validate(&mesh).is_valid());
+    assert!(signed_volume(&mesh, mesh.faces(), &[0, 1, 2, 3]) > 0.);
+}
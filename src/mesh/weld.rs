@@ -0,0 +1,131 @@
+use super::{Face, Filter, Mesh, Primitive};
+use crate::core::{GeoNum, PointIndex};
+use std::collections::HashMap;
+
+/// merges verticies that lie within epsilon of each other and returns how many were merged away.
+/// faces are remapped onto the surviving verticies, and any vertex left unused afterwards is removed.
+/// the first vertex of each cluster keeps its attributes. Edges welded shut lose their repeated corner, and
+/// faces left without three distinct corners are removed. Verticies with non-finite coordinates are never merged
+pub fn weld<T>(mesh: &mut Mesh<T>, epsilon: T) -> usize where T: GeoNum {
+    let verts = mesh.verticies();
+    // cells are at least epsilon wide, and wide enough that every cell index fits comfortably in an i64
+    let reach = verts.iter()
+        .flat_map(|v| [v.x, v.y, v.z])
+        .filter(|x| x.is_finite())
+        .fold(T::zero(), |m, x| m.max(x.abs()));
+    let size = epsilon.max(reach / T::from_f64(1e15).unwrap()).max(T::min_positive_value());
+    let cell = |x: T| (x / size).floor().to_i64();
+    let limit = epsilon * epsilon;
+
+    // spatial hash of cells epsilon wide, so any match lies in the 27 cells around a vertex
+    let mut grid: HashMap<(i64, i64, i64), Vec<usize>> = HashMap::new();
+    let mut remap = Vec::with_capacity(verts.len());
+    let mut merged = 0;
+
+    for (i, v) in verts.iter().enumerate() {
+        let key = match (cell(v.x), cell(v.y), cell(v.z)) {
+            (Some(x), Some(y), Some(z)) => (x, y, z),
+            _ => {
+                remap.push(i);
+                continue;
+            },
+        };
+        let mut found = None;
+        'search: for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    if let Some(bucket) = grid.get(&(key.0 + dx, key.1 + dy, key.2 + dz)) {
+                        for &other in bucket.iter() {
+                            let o = verts[other];
+                            let d = (o.x - v.x) * (o.x - v.x) + (o.y - v.y) * (o.y - v.y) + (o.z - v.z) * (o.z - v.z);
+                            if d <= limit {
+                                found = Some(other);
+                                break 'search;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        match found {
+            Some(other) => {
+                remap.push(other);
+                merged += 1;
+            },
+            None => {
+                grid.entry(key).or_default().push(i);
+                remap.push(i);
+            },
+        }
+    }
+
+    if merged > 0 {
+        let faces: Vec<Face> = mesh.faces().iter().filter_map(|f| {
+            let mut verts: Vec<PointIndex> = f.verticies().iter().map(|v| PointIndex::new(remap[v.index()])).collect();
+            verts.dedup();
+            while verts.len() > 1 && verts.first() == verts.last() {
+                verts.pop();
+            }
+            let mut distinct = verts.clone();
+            distinct.sort_unstable();
+            distinct.dedup();
+            if verts.len() < 3 || distinct.len() != verts.len() {
+                return None;
+            }
+            Some(Face::new(verts))
+        }).collect();
+        mesh.set_faces(faces);
+    }
+    mesh.remove_unused_verticies();
+    merged
+}
+
+/// filter that welds verticies closer than epsilon
+#[derive(Debug)]
+pub struct Weld<T> where T: GeoNum {
+    epsilon: T,
+}
+
+impl<T> Weld<T> where T: GeoNum {
+    pub fn new(epsilon: T) -> Self {
+        Self {
+            epsilon
+        }
+    }
+}
+
+impl<T> Filter<T> for Weld<T> where T: GeoNum {
+    fn apply(&self, mesh: &mut Mesh<T>) {
+        weld(mesh, self.epsilon);
+    }
+}
+
+#[test]
+fn weld_test() {
+    use crate::core::Vertex;
+
+    // a quad built as two separate triangles, with one corner slightly off
+    let mut mesh = Mesh::<f64>::new();
+    mesh.make_face(vec![Vertex::new(0., 0., 0.), Vertex::new(1., 0., 0.), Vertex::new(1., 1., 0.)]);
+    mesh.make_face(vec![Vertex::new(0., 0., 0.), Vertex::new(1.0001, 1., 0.), Vertex::new(0., 1., 0.)]);
+
+    assert_eq!(weld(&mut mesh, 0.001), 2);
+    assert_eq!(mesh.verticies().len(), 4);
+    assert_eq!(mesh.faces()[1].verticies(), vec![PointIndex::new(0), PointIndex::new(2), PointIndex::new(3)]);
+
+    // welding the short edge of a sliver turns a quad into a triangle and drops the sliver itself
+    let mut mesh = Mesh::<f64>::new();
+    mesh.make_face(vec![Vertex::new(0., 0., 0.), Vertex::new(1., 0., 0.), Vertex::new(1.0001, 0., 0.), Vertex::new(0., 1., 0.)]);
+    mesh.make_face(vec![Vertex::new(1., 0., 0.), Vertex::new(1.0001, 0., 0.), Vertex::new(1., 1., 0.)]);
+    assert_eq!(weld(&mut mesh, 0.001), 3);
+    assert_eq!(mesh.faces().len(), 1);
+    assert_eq!(mesh.faces()[0].verticies().len(), 3);
+
+    // far from the origin the cells widen rather than overflow, and verticies that are not finite are left alone
+    let mut mesh = Mesh::<f64>::new();
+    mesh.make_face(vec![Vertex::new(1e300, 0., 0.), Vertex::new(0., 1e300, 0.), Vertex::new(0., 0., f64::NAN)]);
+    mesh.make_face(vec![Vertex::new(1e300, 0., 0.), Vertex::new(0., 0., 1e300), Vertex::new(0., 0., f64::NAN)]);
+    assert_eq!(weld(&mut mesh, 0.), 1);
+    assert_eq!(mesh.verticies().len(), 5);
+}
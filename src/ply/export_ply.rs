@@ -0,0 +1,207 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use crate::core::*;
+use crate::mesh::*;
+use std::convert::TryFrom;
+
+/// vertex properties the writer or reader gives a meaning of their own, so custom channels cannot take them
+const RESERVED: [&str; 16] = ["x", "y", "z", "nx", "ny", "nz", "u", "v", "s", "t", "texture_u", "texture_v", "red", "green", "blue", "alpha"];
+
+/// the encodings a .ply body can be stored in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// writes the mesh to a .ply file at file_path, appending the extension
+pub fn export<T>(mesh: &Mesh<T>, file_path: String, format: PlyFormat) -> io::Result<()>
+    where T: GeoNum
+{
+    let file = File::create(format!("{}.ply", file_path))?;
+    let mut writer = BufWriter::new(file);
+    write(mesh, &mut writer, format)?;
+    writer.flush()
+}
+
+/// streams the mesh as .ply data into any writer. Normals, uvs, colours and custom channels are
+/// written as vertex properties when the mesh has them. Meshes that cannot be written, such as ones with a channel
+/// named like a standard property or a face of more than 255 corners, are refused before anything is written
+pub fn write<T, W>(mesh: &Mesh<T>, writer: &mut W, format: PlyFormat) -> io::Result<()>
+    where
+        T: GeoNum,
+        W: Write
+{
+    let channels = mesh.channels();
+    // header lines are split on whitespace, so a name containing any could not be read back
+    if let Some(name) = channels.iter().find(|n| n.is_empty() || n.contains(char::is_whitespace)) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("ply property names cannot be empty or contain whitespace: {:?}", name)));
+    }
+    if let Some(name) = channels.iter().find(|n| RESERVED.contains(&n.as_str())) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("channel {:?} clashes with a standard ply property", name)));
+    }
+    for face in mesh.faces().iter() {
+        let verts = face.verticies();
+        if verts.len() > u8::MAX as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "ply faces are limited to 255 corners"));
+        }
+        for &v in verts.iter() {
+            index(v)?;
+        }
+    }
+
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format {} 1.0", match format {
+        PlyFormat::Ascii => "ascii",
+        PlyFormat::BinaryLittleEndian => "binary_little_endian",
+        PlyFormat::BinaryBigEndian => "binary_big_endian",
+    })?;
+    if let Some(name) = mesh.name() {
+        writeln!(writer, "obj_info {}", name)?;
+    }
+    writeln!(writer, "element vertex {}", mesh.verticies().len())?;
+    for name in ["x", "y", "z"].iter() {
+        writeln!(writer, "property float {}", name)?;
+    }
+    if mesh.normals().is_some() {
+        for name in ["nx", "ny", "nz"].iter() {
+            writeln!(writer, "property float {}", name)?;
+        }
+    }
+    if mesh.uvs().is_some() {
+        for name in ["s", "t"].iter() {
+            writeln!(writer, "property float {}", name)?;
+        }
+    }
+    if mesh.colours().is_some() {
+        for name in ["red", "green", "blue", "alpha"].iter() {
+            writeln!(writer, "property uchar {}", name)?;
+        }
+    }
+    for name in channels.iter() {
+        writeln!(writer, "property float {}", name)?;
+    }
+    writeln!(writer, "element face {}", mesh.faces().len())?;
+    writeln!(writer, "property list uchar int vertex_indices")?;
+    writeln!(writer, "end_header")?;
+
+    let mut out = ValueWriter { writer, format };
+    for (i, vert) in mesh.verticies().iter().enumerate() {
+        out.float(vert.x)?;
+        out.float(vert.y)?;
+        out.float(vert.z)?;
+        if let Some(normals) = mesh.normals() {
+            out.float(normals[i].x)?;
+            out.float(normals[i].y)?;
+            out.float(normals[i].z)?;
+        }
+        if let Some(uvs) = mesh.uvs() {
+            out.float(uvs[i].x)?;
+            out.float(uvs[i].y)?;
+        }
+        if let Some(colours) = mesh.colours() {
+            let c = colours[i];
+            for value in [c.r, c.g, c.b, c.a].iter() {
+                let byte = (maths::clamp01(*value).to_f64().unwrap() * 255.).round() as u8;
+                out.byte(byte)?;
+            }
+        }
+        for name in channels.iter() {
+            out.float(mesh.channel(name).unwrap()[i])?;
+        }
+        out.end()?;
+    }
+
+    for face in mesh.faces().iter() {
+        let verts = face.verticies();
+        out.byte(verts.len() as u8)?;
+        for v in verts {
+            out.int(index(v)?)?;
+        }
+        out.end()?;
+    }
+    Ok(())
+}
+
+/// vertex indices are written as ints, so larger ones cannot be stored
+fn index(v: PointIndex) -> io::Result<i32> {
+    i32::try_from(v.index()).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "ply vertex indices are limited to 2^31 - 1"))
+}
+
+struct ValueWriter<'a, W: Write> {
+    writer: &'a mut W,
+    format: PlyFormat,
+}
+
+impl<'a, W: Write> ValueWriter<'a, W> {
+    fn float<T: GeoNum>(&mut self, value: T) -> io::Result<()> {
+        let value = value.to_f32().unwrap();
+        match self.format {
+            PlyFormat::Ascii => write!(self.writer, "{} ", value),
+            PlyFormat::BinaryLittleEndian => self.writer.write_all(&value.to_le_bytes()),
+            PlyFormat::BinaryBigEndian => self.writer.write_all(&value.to_be_bytes()),
+        }
+    }
+
+    fn int(&mut self, value: i32) -> io::Result<()> {
+        match self.format {
+            PlyFormat::Ascii => write!(self.writer, "{} ", value),
+            PlyFormat::BinaryLittleEndian => self.writer.write_all(&value.to_le_bytes()),
+            PlyFormat::BinaryBigEndian => self.writer.write_all(&value.to_be_bytes()),
+        }
+    }
+
+    fn byte(&mut self, value: u8) -> io::Result<()> {
+        match self.format {
+            PlyFormat::Ascii => write!(self.writer, "{} ", value),
+            _ => self.writer.write_all(&[value]),
+        }
+    }
+
+    /// ends a row; only ascii bodies are line based
+    fn end(&mut self) -> io::Result<()> {
+        match self.format {
+            PlyFormat::Ascii => writeln!(self.writer),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[test]
+fn binary_round_trip_test() {
+    let mut mesh = Mesh::<f32>::new();
+    mesh.make_face(vec![Vertex::new(0., 0., 0.), Vertex::new(1., 0., 0.), Vertex::new(0., 1., 2.)]);
+    mesh.set_normals(vec![vek::Vec3::unit_z(); 3]);
+    mesh.set_channel("weight".into(), vec![0.25, 0.5, 0.75]);
+
+    for format in [PlyFormat::BinaryBigEndian, PlyFormat::BinaryLittleEndian, PlyFormat::Ascii].iter() {
+        let mut data = Vec::new();
+        write(&mesh, &mut data, *format).unwrap();
+        let parsed = crate::ply::parse::<f32, _>(data.as_slice()).unwrap();
+
+        assert_eq!(parsed.verticies(), mesh.verticies());
+        assert_eq!(parsed.faces(), mesh.faces());
+        assert_eq!(parsed.normals(), mesh.normals());
+        assert_eq!(parsed.channel("weight"), mesh.channel("weight"));
+    }
+
+    // meshes that cannot be written are refused before a single byte goes out
+    let refused = |mesh: &Mesh<f32>| {
+        let mut data = Vec::new();
+        let kind = write(mesh, &mut data, PlyFormat::Ascii).unwrap_err().kind();
+        kind == io::ErrorKind::InvalidInput && data.is_empty()
+    };
+    let mut spaced = mesh.clone();
+    spaced.set_channel("bone weight".into(), vec![0.; 3]);
+    assert!(refused(&spaced));
+    let mut clash = mesh.clone();
+    clash.set_channel("x".into(), vec![0.; 3]);
+    assert!(refused(&clash));
+    let mut wide = mesh.clone();
+    wide.add_face(Face::new(vec![PointIndex::new(0); 256]));
+    assert!(refused(&wide));
+    let mut far = mesh;
+    far.add_face(Face::new(vec![PointIndex::new(0), PointIndex::new(1), PointIndex::new(1 << 31)]));
+    assert!(refused(&far));
+}
@@ -0,0 +1,5 @@
+mod parse_ply;
+mod export_ply;
+
+pub use parse_ply::parse;
+pub use export_ply::{export, write, PlyFormat};
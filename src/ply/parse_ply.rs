@@ -0,0 +1,393 @@
+use crate::mesh::*;
+use crate::core::{GeoNum, PointIndex, Vertex};
+use super::PlyFormat;
+
+use std::io::{Error, ErrorKind, BufRead};
+use vek::{Rgba, Vec2, Vec3};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+#[derive(Clone, Debug)]
+enum Property {
+    Scalar(String, Scalar),
+    List(String, Scalar, Scalar),
+}
+
+#[derive(Clone, Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// reads an ascii or binary .ply file into a mesh. Positions, normals, texture coordinates and colours
+/// are mapped onto the mesh, and any other vertex property is kept as a custom channel of the same name
+pub fn parse<T, B>(mut input: B) -> Result<Mesh<T>, Error>
+    where
+        T: GeoNum,
+        B: BufRead
+{
+    let (format, elements) = parse_header(&mut input)?;
+    let mut body = Vec::new();
+    input.read_to_end(&mut body)?;
+
+    let mut reader: Box<dyn ValueReader> = match format {
+        PlyFormat::Ascii => {
+            let text = String::from_utf8(body).map_err(|_| invalid("ascii ply body is not valid utf-8"))?;
+            Box::new(AsciiReader { tokens: text.split_whitespace().map(String::from).collect::<Vec<_>>().into_iter() })
+        },
+        PlyFormat::BinaryLittleEndian => Box::new(BinaryReader { data: body, offset: 0, little: true }),
+        PlyFormat::BinaryBigEndian => Box::new(BinaryReader { data: body, offset: 0, little: false }),
+    };
+
+    let mut mesh = Mesh::<T>::new();
+    for element in elements.iter() {
+        match element.name.as_str() {
+            "vertex" => read_verticies(&mut mesh, element, reader.as_mut())?,
+            "face" => read_faces(&mut mesh, element, reader.as_mut())?,
+            _ => skip(element, reader.as_mut())?,
+        }
+    }
+    Ok(mesh)
+}
+
+fn parse_header<B: BufRead>(input: &mut B) -> Result<(PlyFormat, Vec<Element>), Error> {
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    if line.trim() != "ply" {
+        return Err(invalid("missing ply magic number"));
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Err(invalid("ply header is not terminated"));
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", "ascii", _] => format = Some(PlyFormat::Ascii),
+            ["format", "binary_little_endian", _] => format = Some(PlyFormat::BinaryLittleEndian),
+            ["format", "binary_big_endian", _] => format = Some(PlyFormat::BinaryBigEndian),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| invalid("malformed element count"))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => current(&mut elements)?.properties.push(
+                Property::List(name.to_string(), scalar(count)?, scalar(item)?)
+            ),
+            ["property", ty, name] => current(&mut elements)?.properties.push(
+                Property::Scalar(name.to_string(), scalar(ty)?)
+            ),
+            ["end_header"] => break,
+            ["comment", ..] | ["obj_info", ..] | [] => {},
+            _ => return Err(invalid("unrecognised line in ply header")),
+        }
+    }
+
+    let format = format.ok_or_else(|| invalid("ply header has no format"))?;
+    Ok((format, elements))
+}
+
+fn current(elements: &mut [Element]) -> Result<&mut Element, Error> {
+    elements.last_mut().ok_or_else(|| invalid("ply property declared before any element"))
+}
+
+fn scalar(name: &str) -> Result<Scalar, Error> {
+    Ok(match name {
+        "char" | "int8" => Scalar::I8,
+        "uchar" | "uint8" => Scalar::U8,
+        "short" | "int16" => Scalar::I16,
+        "ushort" | "uint16" => Scalar::U16,
+        "int" | "int32" => Scalar::I32,
+        "uint" | "uint32" => Scalar::U32,
+        "float" | "float32" => Scalar::F32,
+        "double" | "float64" => Scalar::F64,
+        _ => return Err(invalid("unknown ply property type")),
+    })
+}
+
+fn read_verticies<T: GeoNum>(mesh: &mut Mesh<T>, element: &Element, reader: &mut dyn ValueReader) -> Result<(), Error> {
+    if !mesh.verticies().is_empty() {
+        return Err(invalid("ply file declares more than one vertex element"));
+    }
+    let names: Vec<&str> = element.properties.iter().map(|p| match p {
+        Property::Scalar(name, _) | Property::List(name, _, _) => name.as_str(),
+    }).collect();
+    let has = |list: &[&str]| list.iter().all(|n| names.contains(n));
+    let uv_names = if has(&["u", "v"]) {
+        ["u", "v"]
+    } else if has(&["s", "t"]) {
+        ["s", "t"]
+    } else {
+        ["texture_u", "texture_v"]
+    };
+
+    let standard = |name: &str| {
+        ["x", "y", "z"].contains(&name)
+            || (has(&["nx", "ny", "nz"]) && ["nx", "ny", "nz"].contains(&name))
+            || (has(&uv_names) && uv_names.contains(&name))
+            || (has(&["red", "green", "blue"]) && ["red", "green", "blue", "alpha"].contains(&name))
+    };
+
+    let mut positions = Vec::with_capacity(element.count);
+    let mut normals = Vec::with_capacity(element.count);
+    let mut uvs = Vec::with_capacity(element.count);
+    let mut colours = Vec::with_capacity(element.count);
+    let mut custom: Vec<(String, Vec<T>)> = names.iter()
+        .filter(|n| !standard(n))
+        .map(|n| (n.to_string(), Vec::with_capacity(element.count)))
+        .collect();
+
+    for _ in 0..element.count {
+        let mut position = Vec3::<f64>::zero();
+        let mut normal = Vec3::<f64>::zero();
+        let mut uv = Vec2::<f64>::zero();
+        let mut colour = Rgba::<f64>::new(1., 1., 1., 1.);
+
+        for property in element.properties.iter() {
+            let (name, value) = match property {
+                Property::Scalar(name, ty) => {
+                    let value = reader.read(*ty)?;
+                    let value = if colour_channel(name) && is_integer(*ty) {
+                        value / max_value(*ty)
+                    } else {
+                        value
+                    };
+                    (name.as_str(), value)
+                },
+                Property::List(name, count, item) => {
+                    // lists on a vertex have no mesh equivalent, so only their first entry survives
+                    let n = reader.read(*count)? as usize;
+                    let mut first = 0.;
+                    for i in 0..n {
+                        let value = reader.read(*item)?;
+                        if i == 0 {
+                            first = value;
+                        }
+                    }
+                    (name.as_str(), first)
+                },
+            };
+
+            match name {
+                "x" => position.x = value,
+                "y" => position.y = value,
+                "z" => position.z = value,
+                "nx" => normal.x = value,
+                "ny" => normal.y = value,
+                "nz" => normal.z = value,
+                "red" => colour.r = value,
+                "green" => colour.g = value,
+                "blue" => colour.b = value,
+                "alpha" => colour.a = value,
+                _ if name == uv_names[0] => uv.x = value,
+                _ if name == uv_names[1] => uv.y = value,
+                _ => {},
+            }
+            if let Some(channel) = custom.iter_mut().find(|c| c.0 == name) {
+                channel.1.push(num(value));
+            }
+        }
+
+        positions.push(Vertex::new(num(position.x), num(position.y), num(position.z)));
+        normals.push(normal.map(num));
+        uvs.push(uv.map(num));
+        colours.push(colour.map(num));
+    }
+
+    for p in positions {
+        mesh.add_vertex(p);
+    }
+    if has(&["nx", "ny", "nz"]) {
+        mesh.set_normals(normals);
+    }
+    if has(&uv_names) {
+        mesh.set_uvs(uvs);
+    }
+    if has(&["red", "green", "blue"]) {
+        mesh.set_colours(colours);
+    }
+    for (name, values) in custom {
+        mesh.set_channel(name, values);
+    }
+    Ok(())
+}
+
+fn read_faces<T: GeoNum>(mesh: &mut Mesh<T>, element: &Element, reader: &mut dyn ValueReader) -> Result<(), Error> {
+    let vertex_count = mesh.verticies().len();
+    for _ in 0..element.count {
+        let mut face = None;
+        for property in element.properties.iter() {
+            match property {
+                Property::List(name, count, item) => {
+                    let n = reader.read(*count)? as usize;
+                    let mut verts = Vec::with_capacity(n);
+                    for _ in 0..n {
+                        verts.push(reader.read(*item)?);
+                    }
+                    if name == "vertex_indices" || name == "vertex_index" {
+                        face = Some(verts);
+                    }
+                },
+                Property::Scalar(_, ty) => {
+                    reader.read(*ty)?;
+                },
+            }
+        }
+
+        let verts = face.ok_or_else(|| invalid("ply face has no vertex_indices"))?;
+        // checked before casting, which would quietly turn a negative index into vertex 0
+        if verts.iter().any(|&v| !(v >= 0. && v.fract() == 0. && v < vertex_count as f64)) {
+            return Err(invalid("ply face references a vertex that does not exist"));
+        }
+        mesh.add_face(Face::new(verts.into_iter().map(|v| PointIndex::new(v as usize)).collect()));
+    }
+    Ok(())
+}
+
+fn skip(element: &Element, reader: &mut dyn ValueReader) -> Result<(), Error> {
+    for _ in 0..element.count {
+        for property in element.properties.iter() {
+            match property {
+                Property::Scalar(_, ty) => {
+                    reader.read(*ty)?;
+                },
+                Property::List(_, count, item) => {
+                    let n = reader.read(*count)? as usize;
+                    for _ in 0..n {
+                        reader.read(*item)?;
+                    }
+                },
+            }
+        }
+    }
+    Ok(())
+}
+
+fn colour_channel(name: &str) -> bool {
+    ["red", "green", "blue", "alpha"].contains(&name)
+}
+
+fn is_integer(ty: Scalar) -> bool {
+    ty != Scalar::F32 && ty != Scalar::F64
+}
+
+fn max_value(ty: Scalar) -> f64 {
+    match ty {
+        Scalar::I8 => i8::MAX as f64,
+        Scalar::U8 => u8::MAX as f64,
+        Scalar::I16 => i16::MAX as f64,
+        Scalar::U16 => u16::MAX as f64,
+        Scalar::I32 => i32::MAX as f64,
+        Scalar::U32 => u32::MAX as f64,
+        _ => 1.,
+    }
+}
+
+fn num<T: GeoNum>(value: f64) -> T {
+    T::from_f64(value).unwrap()
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+trait ValueReader {
+    fn read(&mut self, ty: Scalar) -> Result<f64, Error>;
+}
+
+struct AsciiReader {
+    tokens: std::vec::IntoIter<String>,
+}
+
+impl ValueReader for AsciiReader {
+    fn read(&mut self, _ty: Scalar) -> Result<f64, Error> {
+        self.tokens.next()
+            .and_then(|t| t.parse().ok())
+            .ok_or_else(|| invalid("ply body ended early or holds a malformed value"))
+    }
+}
+
+struct BinaryReader {
+    data: Vec<u8>,
+    offset: usize,
+    little: bool,
+}
+
+impl BinaryReader {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        if self.offset + N > self.data.len() {
+            return Err(invalid("ply body ended early"));
+        }
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(&self.data[self.offset..self.offset + N]);
+        self.offset += N;
+        if !self.little {
+            bytes.reverse();
+        }
+        Ok(bytes)
+    }
+}
+
+impl ValueReader for BinaryReader {
+    fn read(&mut self, ty: Scalar) -> Result<f64, Error> {
+        Ok(match ty {
+            Scalar::I8 => i8::from_le_bytes(self.take()?) as f64,
+            Scalar::U8 => u8::from_le_bytes(self.take()?) as f64,
+            Scalar::I16 => i16::from_le_bytes(self.take()?) as f64,
+            Scalar::U16 => u16::from_le_bytes(self.take()?) as f64,
+            Scalar::I32 => i32::from_le_bytes(self.take()?) as f64,
+            Scalar::U32 => u32::from_le_bytes(self.take()?) as f64,
+            Scalar::F32 => f32::from_le_bytes(self.take()?) as f64,
+            Scalar::F64 => f64::from_le_bytes(self.take()?),
+        })
+    }
+}
+
+#[test]
+fn parse_ascii_test() {
+    let data = "ply
+format ascii 1.0
+comment made by hand
+element vertex 3
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+property float quality
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 0 0.5
+1 0 0 0 255 0 0.25
+0 1 0 0 0 255 1
+3 0 1 2
+";
+
+    let mesh = parse::<f64, _>(data.as_bytes()).unwrap();
+    assert_eq!(mesh.verticies().len(), 3);
+    assert_eq!(mesh.faces().len(), 1);
+    assert_eq!(mesh.colours().unwrap()[1], Rgba::new(0., 1., 0., 1.));
+    assert_eq!(mesh.channel("quality").unwrap(), &vec![0.5, 0.25, 1.]);
+    assert!(mesh.normals().is_none());
+
+    for face in ["3 0 1 -1", "3 0 1 3"].iter() {
+        let broken = data.replace("3 0 1 2", face);
+        assert_eq!(parse::<f64, _>(broken.as_bytes()).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,6 @@
+mod node;
+#[allow(clippy::module_inception)]
+mod scene;
+
+pub use node::{Node, NodeIndex};
+pub use scene::Scene;
@@ -0,0 +1,98 @@
+use vek::Mat4;
+use crate::core::{GeoNum, IndexType};
+use crate::mesh::Mesh;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub struct NodeIndex<Ix = crate::core::DefaultIx>(Ix);
+
+/// a named entry in a scene. Holds an optional mesh and a transform relative to its parent
+#[derive(Clone, Debug)]
+pub struct Node<T> where T: GeoNum {
+    name: Option<String>,
+    mesh: Option<Mesh<T>>,
+    transform: Mat4<T>,
+    parent: Option<NodeIndex>,
+    children: Vec<NodeIndex>,
+}
+
+impl<T> Node<T> where T: GeoNum {
+    /// an empty node, useful for grouping children under a shared transform
+    pub fn new(name: Option<String>) -> Self {
+        Self {
+            name,
+            mesh: None,
+            transform: Mat4::identity(),
+            parent: None,
+            children: Vec::new(),
+        }
+    }
+
+    /// a node holding a mesh. The node takes its name from the mesh
+    pub fn with_mesh(mesh: Mesh<T>) -> Self {
+        let mut node = Self::new(mesh.name());
+        node.mesh = Some(mesh);
+        node
+    }
+
+    pub fn name(&self) -> Option<String> {
+        self.name.clone()
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.name = Some(name);
+    }
+
+    pub fn mesh(&self) -> Option<&Mesh<T>> {
+        self.mesh.as_ref()
+    }
+
+    pub fn mesh_mut(&mut self) -> Option<&mut Mesh<T>> {
+        self.mesh.as_mut()
+    }
+
+    pub fn set_mesh(&mut self, mesh: Mesh<T>) {
+        self.mesh = Some(mesh);
+    }
+
+    /// the transform from this node's space into its parent's
+    pub fn transform(&self) -> Mat4<T> {
+        self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Mat4<T>) {
+        self.transform = transform;
+    }
+
+    pub fn parent(&self) -> Option<NodeIndex> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &Vec<NodeIndex> {
+        &self.children
+    }
+
+    pub(crate) fn set_parent(&mut self, parent: Option<NodeIndex>) {
+        self.parent = parent;
+    }
+
+    pub(crate) fn add_child(&mut self, child: NodeIndex) {
+        self.children.push(child);
+    }
+}
+
+impl<Ix: IndexType> NodeIndex<Ix> {
+    #[inline]
+    pub fn new(x: usize) -> Self {
+        NodeIndex(IndexType::new(x))
+    }
+
+    #[inline]
+    pub fn index(self) -> usize {
+        self.0.index()
+    }
+
+    #[inline]
+    pub fn end() -> Self {
+        NodeIndex(IndexType::max())
+    }
+}
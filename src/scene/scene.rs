@@ -0,0 +1,133 @@
+use vek::Mat4;
+use super::{Node, NodeIndex};
+use crate::core::GeoNum;
+use crate::mesh::Mesh;
+
+/// a collection of named meshes arranged in a hierarchy of transforms
+#[derive(Clone, Debug)]
+pub struct Scene<T> where T: GeoNum {
+    nodes: Vec<Node<T>>,
+    roots: Vec<NodeIndex>,
+}
+
+impl<T> Scene<T> where T: GeoNum {
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            roots: Vec::new(),
+        }
+    }
+
+    /// adds a node to the scene, either at the top level or beneath the given parent
+    pub fn add_node(&mut self, mut node: Node<T>, parent: Option<NodeIndex>) -> NodeIndex {
+        let index = NodeIndex::new(self.nodes.len());
+        node.set_parent(parent);
+        match parent {
+            Some(p) => self.nodes[p.index()].add_child(index),
+            None => self.roots.push(index),
+        }
+        self.nodes.push(node);
+        index
+    }
+
+    /// shorthand for adding a node that holds the given mesh
+    pub fn add_mesh(&mut self, mesh: Mesh<T>, parent: Option<NodeIndex>) -> NodeIndex {
+        self.add_node(Node::with_mesh(mesh), parent)
+    }
+
+    pub fn node(&self, index: NodeIndex) -> Option<&Node<T>> {
+        self.nodes.get(index.index())
+    }
+
+    pub fn node_mut(&mut self, index: NodeIndex) -> Option<&mut Node<T>> {
+        self.nodes.get_mut(index.index())
+    }
+
+    /// every node in the scene, in the order they were added. Parents always come before their children
+    pub fn nodes(&self) -> &Vec<Node<T>> {
+        &self.nodes
+    }
+
+    /// the nodes that have no parent
+    pub fn roots(&self) -> &Vec<NodeIndex> {
+        &self.roots
+    }
+
+    /// finds the first node with the given name
+    pub fn find(&self, name: &str) -> Option<NodeIndex> {
+        self.nodes.iter()
+            .position(|n| n.name().as_deref() == Some(name))
+            .map(NodeIndex::new)
+    }
+
+    /// the transform from a node's space into scene space, combining it with all of its ancestors
+    pub fn world_transform(&self, index: NodeIndex) -> Mat4<T> {
+        let node = &self.nodes[index.index()];
+        match node.parent() {
+            Some(parent) => self.world_transform(parent) * node.transform(),
+            None => node.transform(),
+        }
+    }
+
+    /// copies of every mesh in the scene with their world transforms applied, paired with the node they came from
+    pub fn world_meshes(&self) -> Vec<(NodeIndex, Mesh<T>)> {
+        let mut meshes = Vec::new();
+        for (i, node) in self.nodes.iter().enumerate() {
+            if let Some(mesh) = node.mesh() {
+                let index = NodeIndex::new(i);
+                let mut mesh = mesh.clone();
+                mesh.transform(self.world_transform(index));
+                if mesh.name().is_none() {
+                    if let Some(name) = node.name() {
+                        mesh.set_name(name);
+                    }
+                }
+                meshes.push((index, mesh));
+            }
+        }
+        meshes
+    }
+
+    /// collapses the hierarchy into a single mesh in scene space
+    pub fn flatten(&self) -> Mesh<T> {
+        let mut flat = Mesh::new();
+        for (_, mesh) in self.world_meshes() {
+            flat.append(&mesh);
+        }
+        flat
+    }
+}
+
+impl<T> Default for Scene<T> where T: GeoNum {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[test]
+fn scene_test() {
+    use crate::mesh::Primitive;
+    use crate::core::Vertex;
+    use vek::Vec3;
+
+    let mut tri = Mesh::<f64>::new();
+    tri.make_face(vec![Vertex::new(0., 0., 0.), Vertex::new(1., 0., 0.), Vertex::new(0., 1., 0.)]);
+
+    let mut scene = Scene::new();
+    let mut root = Node::new(Some("root".into()));
+    root.set_transform(Mat4::translation_3d(Vec3::new(0., 0., 5.)));
+    let root = scene.add_node(root, None);
+    let child = scene.add_mesh(tri.clone(), Some(root));
+    scene.node_mut(child).unwrap().set_transform(Mat4::scaling_3d(Vec3::broadcast(2.)));
+    scene.add_mesh(tri, None);
+
+    assert_eq!(scene.roots().len(), 2);
+    assert_eq!(scene.node(root).unwrap().children(), &vec![child]);
+    assert_eq!(scene.find("root"), Some(root));
+
+    let flat = scene.flatten();
+    assert_eq!(flat.verticies().len(), 6);
+    assert_eq!(flat.faces().len(), 2);
+    assert_eq!(flat.verticies()[1], Vertex::new(2., 0., 5.));
+    assert_eq!(flat.faces()[1].verticies()[0].index(), 3);
+}
@@ -0,0 +1,131 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use crate::core::*;
+use crate::mesh::*;
+use vek::Vec3;
+
+/// the two flavours of .stl file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StlFormat {
+    Ascii,
+    Binary,
+}
+
+/// writes the mesh to an .stl file at file_path, appending the extension
+pub fn export<T>(mesh: &Mesh<T>, file_path: String, format: StlFormat) -> io::Result<()>
+    where T: GeoNum
+{
+    let file = File::create(format!("{}.stl", file_path))?;
+    let mut writer = BufWriter::new(file);
+    write(mesh, &mut writer, format)?;
+    writer.flush()
+}
+
+/// streams the mesh as .stl data into any writer. N-gons are split into triangles by ear clipping,
+/// so concave ones come out right, and every triangle is written with the normal of the face it came from
+pub fn write<T, W>(mesh: &Mesh<T>, writer: &mut W, format: StlFormat) -> io::Result<()>
+    where
+        T: GeoNum,
+        W: Write
+{
+    let name = mesh.name().unwrap_or_else(|| "corale".into());
+    let triangles = triangles(mesh);
+
+    match format {
+        StlFormat::Ascii => {
+            writeln!(writer, "solid {}", name)?;
+            for (normal, corners) in triangles.iter() {
+                writeln!(writer, "  facet normal {} {} {}", normal.x, normal.y, normal.z)?;
+                writeln!(writer, "    outer loop")?;
+                for c in corners.iter() {
+                    writeln!(writer, "      vertex {} {} {}", c.x, c.y, c.z)?;
+                }
+                writeln!(writer, "    endloop")?;
+                writeln!(writer, "  endfacet")?;
+            }
+            writeln!(writer, "endsolid {}", name)?;
+        },
+        StlFormat::Binary => {
+            // the header must not start with "solid" or readers may mistake it for ascii
+            let mut header = [0u8; 80];
+            let label = format!("binary stl: {}", name);
+            let len = label.len().min(80);
+            header[..len].copy_from_slice(&label.as_bytes()[..len]);
+            writer.write_all(&header)?;
+            writer.write_all(&(triangles.len() as u32).to_le_bytes())?;
+
+            for (normal, corners) in triangles.iter() {
+                write_vec(writer, *normal)?;
+                for c in corners.iter() {
+                    write_vec(writer, *c)?;
+                }
+                writer.write_all(&0u16.to_le_bytes())?;
+            }
+        },
+    }
+    Ok(())
+}
+
+fn triangles<T: GeoNum>(mesh: &Mesh<T>) -> Vec<(Vec3<T>, [Vec3<T>; 3])> {
+    let verts = mesh.verticies();
+    let mut triangles = Vec::new();
+    for face in mesh.faces().iter() {
+        let normal = mesh.face_normal(face);
+        for tri in mesh.face_triangles(face) {
+            let corners = [
+                verts[tri[0].index()].to_vec3(),
+                verts[tri[1].index()].to_vec3(),
+                verts[tri[2].index()].to_vec3(),
+            ];
+            triangles.push((normal, corners));
+        }
+    }
+    triangles
+}
+
+fn write_vec<T: GeoNum, W: Write>(writer: &mut W, v: Vec3<T>) -> io::Result<()> {
+    for x in [v.x, v.y, v.z].iter() {
+        writer.write_all(&x.to_f32().unwrap().to_le_bytes())?;
+    }
+    Ok(())
+}
+
+#[test]
+fn binary_round_trip_test() {
+    let mut mesh = Mesh::<f64>::new();
+    mesh.make_face(vec![
+        Vertex::new(0., 0., 0.),
+        Vertex::new(1., 0., 0.),
+        Vertex::new(1., 1., 0.),
+        Vertex::new(0., 1., 0.),
+    ]);
+
+    let mut data = Vec::new();
+    write(&mesh, &mut data, StlFormat::Binary).unwrap();
+    assert_eq!(data.len(), 84 + 2 * 50);
+    assert_eq!(&data[84..96], &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 128, 63]);
+
+    let parsed = crate::stl::parse::<f64, _>(data.as_slice(), true).unwrap();
+    assert_eq!(parsed.faces().len(), 2);
+    assert_eq!(parsed.verticies().len(), 4);
+}
+
+#[test]
+fn concave_test() {
+    // an arrowhead whose notch sits opposite the first corner, where a fan would spill out of the outline
+    let mut mesh = Mesh::<f64>::new();
+    mesh.make_face(vec![
+        Vertex::new(0., 0., 0.),
+        Vertex::new(2., 1., 0.),
+        Vertex::new(0., 2., 0.),
+        Vertex::new(1., 1., 0.),
+    ]);
+
+    let mut data = Vec::new();
+    write(&mesh, &mut data, StlFormat::Binary).unwrap();
+    let parsed = crate::stl::parse::<f64, _>(data.as_slice(), true).unwrap();
+    assert_eq!(parsed.faces().len(), 2);
+    assert!(parsed.faces().iter().all(|f| parsed.face_normal(f).z > 0.));
+    let area: f64 = parsed.faces().iter().map(|f| parsed.face_area(f)).sum();
+    assert!((area - 1.).abs() < 1e-12);
+}
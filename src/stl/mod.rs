@@ -0,0 +1,5 @@
+mod parse_stl;
+mod export_stl;
+
+pub use parse_stl::parse;
+pub use export_stl::{export, write, StlFormat};
@@ -0,0 +1,151 @@
+use crate::mesh::*;
+use crate::core::{GeoNum, Vertex};
+
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Read};
+
+/// reads an ascii or binary .stl file into a mesh. The format is detected from the data.
+/// when weld is set, verticies with identical positions are shared between triangles
+pub fn parse<T, R>(mut input: R, weld: bool) -> Result<Mesh<T>, Error>
+    where
+        T: GeoNum,
+        R: Read
+{
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+
+    let triangles = if is_binary(&data) {
+        parse_binary(&data)?
+    } else {
+        parse_ascii(&data)?
+    };
+
+    let mut mesh = Mesh::<T>::new();
+    let mut welded = HashMap::new();
+
+    for tri in triangles {
+        let mut face = Face::capacity(3);
+        for p in tri.iter() {
+            let index = if weld {
+                *welded.entry(key(p)).or_insert_with(|| mesh.add_vertex(vertex(p)))
+            } else {
+                mesh.add_vertex(vertex(p))
+            };
+            face.add_vert(index);
+        }
+        mesh.add_face(face);
+    }
+    Ok(mesh)
+}
+
+/// a binary file has an 80 byte header, a triangle count and 50 bytes per triangle. Ascii files
+/// start with "solid", but so do plenty of binary headers, so the size check wins
+fn is_binary(data: &[u8]) -> bool {
+    if data.len() >= 84 {
+        let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+        if data.len() == 84 + count * 50 {
+            return true;
+        }
+    }
+    !data.trim_ascii_start().starts_with(b"solid")
+}
+
+fn parse_binary(data: &[u8]) -> Result<Vec<[[f64; 3]; 3]>, Error> {
+    if data.len() < 84 {
+        return Err(invalid("binary stl is missing its header"));
+    }
+    let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+    if data.len() < 84 + count * 50 {
+        return Err(invalid("binary stl is shorter than its triangle count"));
+    }
+
+    let read = |offset: usize| {
+        f32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]) as f64
+    };
+
+    let mut triangles = Vec::with_capacity(count);
+    for t in 0..count {
+        // skip the 12 byte normal; it is recomputed on export
+        let base = 84 + t * 50 + 12;
+        let mut tri = [[0.; 3]; 3];
+        for (v, corner) in tri.iter_mut().enumerate() {
+            for (a, axis) in corner.iter_mut().enumerate() {
+                *axis = read(base + v * 12 + a * 4);
+            }
+        }
+        triangles.push(tri);
+    }
+    Ok(triangles)
+}
+
+fn parse_ascii(data: &[u8]) -> Result<Vec<[[f64; 3]; 3]>, Error> {
+    let text = std::str::from_utf8(data).map_err(|_| invalid("ascii stl is not valid utf-8"))?;
+    let mut triangles = Vec::new();
+    let mut corners = Vec::new();
+
+    for line in text.lines() {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("vertex") => {
+                let mut p = [0.; 3];
+                for axis in p.iter_mut() {
+                    *axis = words.next()
+                        .and_then(|w| w.parse().ok())
+                        .ok_or_else(|| invalid("malformed vertex in ascii stl"))?;
+                }
+                corners.push(p);
+            },
+            Some("endloop") => {
+                if corners.len() != 3 {
+                    return Err(invalid("ascii stl facet does not have three verticies"));
+                }
+                triangles.push([corners[0], corners[1], corners[2]]);
+                corners.clear();
+            },
+            _ => {},
+        }
+    }
+    Ok(triangles)
+}
+
+fn vertex<T: GeoNum>(p: &[f64; 3]) -> Vertex<T> {
+    Vertex::new(T::from_f64(p[0]).unwrap(), T::from_f64(p[1]).unwrap(), T::from_f64(p[2]).unwrap())
+}
+
+/// bitwise key for exact welding. Negative zero is folded into zero so they weld together
+fn key(p: &[f64; 3]) -> [u64; 3] {
+    let bits = |x: f64| if x == 0. { 0 } else { x.to_bits() };
+    [bits(p[0]), bits(p[1]), bits(p[2])]
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+#[test]
+fn parse_ascii_test() {
+    let data = "solid test
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 0 1 0
+    endloop
+  endfacet
+  facet normal 0 0 1
+    outer loop
+      vertex 1 0 0
+      vertex 1 1 0
+      vertex 0 1 0
+    endloop
+  endfacet
+endsolid test";
+
+    let mesh = parse::<f64, _>(data.as_bytes(), false).unwrap();
+    assert_eq!(mesh.verticies().len(), 6);
+    assert_eq!(mesh.faces().len(), 2);
+
+    let welded = parse::<f64, _>(data.as_bytes(), true).unwrap();
+    assert_eq!(welded.verticies().len(), 4);
+    assert_eq!(welded.faces()[1].verticies(), [1, 3, 2].iter().map(|&i| crate::core::PointIndex::new(i)).collect::<Vec<_>>());
+}
@@ -1,34 +1,254 @@
 use std::fs::File;
-use std::io::prelude::*;
+use std::io::{self, BufWriter, Write};
 use crate::core::*;
 use crate::mesh::*;
+use crate::scene::Scene;
 
-pub fn export<T>(mesh: &Mesh<T>, file_path: String) -> std::io::Result<()> 
+/// settings that control how a mesh is written out as an .obj file
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportOptions {
+    /// number of decimal places to write. None uses the shortest exact representation
+    pub precision: Option<usize>,
+    /// the up axis of the written file. Z writes positions as they are, Y turns them to stand y-up. Give parse the
+    /// same axis to read the file back
+    pub axis: UpAxis,
+    /// write `vn` records when the mesh has normals
+    pub normals: bool,
+    /// write `vt` records when the mesh has uvs
+    pub uvs: bool,
+    /// write a `g` record alongside the object name
+    pub groups: bool,
+    /// material library to reference. When set, `mtllib` and `usemtl` records are written
+    pub material_library: Option<String>,
+    /// comment written at the top of the file, one `#` line per line of text
+    pub header: Option<String>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            precision: None,
+            axis: UpAxis::Z,
+            normals: true,
+            uvs: true,
+            groups: false,
+            material_library: None,
+            header: None,
+        }
+    }
+}
+
+/// writes the mesh to an .obj file at file_path, appending the extension
+pub fn export<T>(mesh: &Mesh<T>, file_path: String) -> io::Result<()>
     where T: GeoNum
 {
-    let mut file = File::create(format!("{}.obj", file_path))?;
-    let mut data = Vec::new();
-    data.push(format!("# Generated for use in Torus"));
-    data.push(format!("mtllib {}.mtl", file_path));
-    let name = if mesh.name().is_some() {
-        mesh.name().unwrap()
-    } else {
-        file_path.into()
-    };
-    data.push(format!("o {}", name));
+    export_with(mesh, file_path, &ExportOptions::default())
+}
+
+/// writes the mesh to an .obj file at file_path using the given options, appending the extension
+pub fn export_with<T>(mesh: &Mesh<T>, file_path: String, options: &ExportOptions) -> io::Result<()>
+    where T: GeoNum
+{
+    let file = File::create(format!("{}.obj", file_path))?;
+    let mut writer = BufWriter::new(file);
+    write_obj(mesh, &mut writer, options, Some(&file_path))?;
+    writer.flush()
+}
+
+/// streams the mesh as .obj data into any writer
+pub fn write<T, W>(mesh: &Mesh<T>, writer: &mut W, options: &ExportOptions) -> io::Result<()>
+    where
+        T: GeoNum,
+        W: Write
+{
+    write_obj(mesh, writer, options, None)
+}
+
+/// streams every mesh in the scene into one .obj, each as its own object. Obj has no hierarchy,
+/// so node transforms are baked into the verticies. Unnamed meshes take their node's name, or
+/// object_<n> by their place in the file, so they stay apart when read back
+pub fn write_scene<T, W>(scene: &Scene<T>, writer: &mut W, options: &ExportOptions) -> io::Result<()>
+    where
+        T: GeoNum,
+        W: Write
+{
+    write_header(writer, options)?;
+    let mut offsets = Offsets::default();
+    for (n, (index, mesh)) in scene.world_meshes().into_iter().enumerate() {
+        let fallback = scene.node(index).and_then(|node| node.name()).unwrap_or_else(|| format!("object_{}", n));
+        write_object(&mesh, writer, options, Some(&fallback), &mut offsets)?;
+    }
+    Ok(())
+}
+
+/// writes the scene to an .obj file at file_path using the given options, appending the extension
+pub fn export_scene<T>(scene: &Scene<T>, file_path: String, options: &ExportOptions) -> io::Result<()>
+    where T: GeoNum
+{
+    let file = File::create(format!("{}.obj", file_path))?;
+    let mut writer = BufWriter::new(file);
+    write_scene(scene, &mut writer, options)?;
+    writer.flush()
+}
+
+/// how many of each record have been written so far. Obj indices are global to the file
+#[derive(Default)]
+struct Offsets {
+    verticies: usize,
+    uvs: usize,
+    normals: usize,
+}
+
+fn write_obj<T, W>(mesh: &Mesh<T>, w: &mut W, options: &ExportOptions, fallback_name: Option<&str>) -> io::Result<()>
+    where
+        T: GeoNum,
+        W: Write
+{
+    write_header(w, options)?;
+    write_object(mesh, w, options, fallback_name, &mut Offsets::default())
+}
+
+fn write_header<W: Write>(w: &mut W, options: &ExportOptions) -> io::Result<()> {
+    if let Some(header) = &options.header {
+        for line in header.lines() {
+            writeln!(w, "# {}", line)?;
+        }
+    }
+    if let Some(library) = &options.material_library {
+        writeln!(w, "mtllib {}", library)?;
+    }
+    Ok(())
+}
+
+fn write_object<T, W>(mesh: &Mesh<T>, w: &mut W, options: &ExportOptions, fallback_name: Option<&str>, offsets: &mut Offsets) -> io::Result<()>
+    where
+        T: GeoNum,
+        W: Write
+{
+    let name = mesh.name().or_else(|| fallback_name.map(String::from));
+    if let Some(name) = &name {
+        writeln!(w, "o {}", name)?;
+        if options.groups {
+            writeln!(w, "g {}", name)?;
+        }
+    }
+
     for vert in mesh.verticies().iter() {
-        data.push(format!("v {} {} {}", vert.x, vert.y, vert.z));
+        let v = options.axis.from_z_up(vert.to_vec3());
+        writeln!(w, "v {} {} {}", num(v.x, options), num(v.y, options), num(v.z, options))?;
+    }
+
+    let uvs = mesh.uvs().filter(|_| options.uvs);
+    if let Some(uvs) = uvs {
+        for uv in uvs.iter() {
+            writeln!(w, "vt {} {}", num(uv.x, options), num(uv.y, options))?;
+        }
+    }
+
+    let normals = mesh.normals().filter(|_| options.normals);
+    if let Some(normals) = normals {
+        for normal in normals.iter() {
+            let n = options.axis.from_z_up(*normal);
+            writeln!(w, "vn {} {} {}", num(n.x, options), num(n.y, options), num(n.z, options))?;
+        }
+    }
+
+    if options.material_library.is_some() {
+        if let Some(material) = mesh.material() {
+            writeln!(w, "usemtl {}", material)?;
+        }
     }
 
     for face in mesh.faces().iter() {
-        let mut list = Vec::new();
+        write!(w, "f")?;
         for v in face.verticies() {
             // Offset the indexing as .obj files start at index 1, not 0
-            list.push(format!("{}", v.index() + 1));
+            let i = v.index() + 1;
+            let (p, t, n) = (i + offsets.verticies, i + offsets.uvs, i + offsets.normals);
+            match (uvs.is_some(), normals.is_some()) {
+                (false, false) => write!(w, " {}", p)?,
+                (true, false) => write!(w, " {}/{}", p, t)?,
+                (false, true) => write!(w, " {}//{}", p, n)?,
+                (true, true) => write!(w, " {}/{}/{}", p, t, n)?,
+            }
         };
-        data.push(format!("f {}", list.join(" ")));
+        writeln!(w)?;
     }
 
-    file.write(data.join("\n").as_bytes())?;
+    offsets.verticies += mesh.verticies().len();
+    offsets.uvs += uvs.map(|u| u.len()).unwrap_or(0);
+    offsets.normals += normals.map(|n| n.len()).unwrap_or(0);
     Ok(())
-}
\ No newline at end of file
+}
+
+fn num<T: GeoNum>(value: T, options: &ExportOptions) -> String {
+    match options.precision {
+        Some(p) => format!("{:.*}", p, value),
+        None => format!("{}", value),
+    }
+}
+
+#[test]
+fn export_test() {
+    let mut mesh = Mesh::<f64>::new();
+    mesh.set_name("tri".into());
+    mesh.make_face(vec![Vertex::new(0., 0., 0.), Vertex::new(1., 0., 0.), Vertex::new(0., 1., 0.5)]);
+    mesh.set_uvs(vec![vek::Vec2::new(0., 0.), vek::Vec2::new(1., 0.), vek::Vec2::new(0., 1.)]);
+
+    let options = ExportOptions {
+        precision: Some(2),
+        header: Some("test".into()),
+        ..ExportOptions::default()
+    };
+    let mut out = Vec::new();
+    write(&mesh, &mut out, &options).unwrap();
+    let text = String::from_utf8(out).unwrap();
+
+    assert!(text.starts_with("# test\no tri\n"));
+    assert!(text.contains("v 0.00 1.00 0.50\n"));
+    assert!(text.contains("vt 1.00 0.00\n"));
+    assert!(text.ends_with("f 1/1 2/2 3/3\n"));
+    assert!(!text.contains("mtllib"));
+
+    // turning to y-up keeps the face wound the same way round its normal
+    let options = ExportOptions { axis: UpAxis::Y, ..ExportOptions::default() };
+    let mut out = Vec::new();
+    write(&mesh, &mut out, &options).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.contains("v 0 0.5 -1\n"));
+    assert!(text.ends_with("f 1/1 2/2 3/3\n"));
+}
+
+#[test]
+fn export_scene_test() {
+    let mut tri = Mesh::<f64>::new();
+    tri.make_face(vec![Vertex::new(0., 0., 0.), Vertex::new(1., 0., 0.), Vertex::new(0., 1., 0.)]);
+    let mut scene = Scene::new();
+    let mut first = tri.clone();
+    first.set_name("a".into());
+    scene.add_mesh(first, None);
+    let mut second = tri;
+    second.set_name("b".into());
+    scene.add_mesh(second, None);
+
+    let mut out = Vec::new();
+    write_scene(&scene, &mut out, &ExportOptions::default()).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.contains("o b\n"));
+    assert!(text.ends_with("f 4 5 6\n"));
+
+    // unnamed meshes still get an object each, so reading the file back keeps them apart
+    let mut unnamed = Scene::new();
+    let mut plain = Mesh::<f64>::new();
+    plain.make_face(vec![Vertex::new(0., 0., 0.), Vertex::new(1., 0., 0.), Vertex::new(0., 1., 0.)]);
+    unnamed.add_mesh(plain.clone(), None);
+    let second = unnamed.add_mesh(plain, None);
+    unnamed.node_mut(second).unwrap().set_name("node".into());
+    let mut out = Vec::new();
+    write_scene(&unnamed, &mut out, &ExportOptions::default()).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.starts_with("o object_0\n"));
+    assert!(text.contains("o node\n"));
+    let back = crate::wavefront::parse_scene::<f64, _>(text.as_bytes(), UpAxis::Z).unwrap();
+    assert_eq!(back.roots().len(), 2);
+}
@@ -1,5 +1,5 @@
 mod parse_obj;
 mod export_obj;
 
-pub use parse_obj::parse;
-pub use export_obj::export;
\ No newline at end of file
+pub use parse_obj::{parse, parse_scene};
+pub use export_obj::{export, export_with, export_scene, write, write_scene, ExportOptions};
@@ -1,10 +1,15 @@
 use crate::mesh::*;
-use crate::core::{GeoNum, PointIndex, Vertex};
+use crate::core::{GeoNum, Point, PointIndex, UpAxis, Vertex};
+use crate::scene::{Node, Scene};
 
-use std::io::{Error, BufRead};
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, BufRead};
 use obj::{raw::{parse_obj as parse_external, object::Polygon}};
+use vek::Vec3;
 
-pub fn parse<T, B>(input: B) -> Result<Mesh<T>, Error> 
+/// parses a wavefront file into one mesh. axis is the up axis the file was written with, the same one given to
+/// the exporter, and positions are turned from it into corale's z-up space
+pub fn parse<T, B>(input: B, axis: UpAxis) -> Result<Mesh<T>, Error> 
     where 
         T: GeoNum, 
         B: BufRead 
@@ -14,10 +19,7 @@ pub fn parse<T, B>(input: B) -> Result<Mesh<T>, Error>
     let mut mesh = Mesh::<T>::new();
 
     for p in raw.positions {
-        let x = T::from_f32(p.0).unwrap();
-        let y = T::from_f32(p.2).unwrap();
-        let z = T::from_f32(p.1).unwrap();
-        mesh.add_vertex(Vertex::new(x, y, z));
+        mesh.add_vertex(position(p, axis));
     }
 
     for p in raw.polygons {
@@ -37,9 +39,164 @@ pub fn parse<T, B>(input: B) -> Result<Mesh<T>, Error>
         }
     }
 
-    return Ok(mesh);
-}  
+    Ok(mesh)
+}
 
 fn mesh_add_face<T: GeoNum>(mesh: &mut Mesh<T>, verts: Vec<usize>) {
     mesh.add_face(Face::new(verts.iter().map(|x| PointIndex::new(*x)).collect()));
-}
\ No newline at end of file
+}
+
+fn position<T: GeoNum>(p: (f32, f32, f32, f32), axis: UpAxis) -> Vertex<T> {
+    let v = Vec3::new(T::from_f32(p.0).unwrap(), T::from_f32(p.1).unwrap(), T::from_f32(p.2).unwrap());
+    Vertex::from_vec3(axis.to_z_up(v))
+}
+
+/// a run of faces sharing an `o` object and a `g` group
+struct Section {
+    name: Option<String>,
+    polygons: Vec<usize>,
+}
+
+/// parses a wavefront file into a scene. Every `o` object becomes a node; objects split into
+/// several `g` groups get a child node per group. Each node only holds the verticies its faces use. axis is the
+/// up axis the file was written with, as for parse
+pub fn parse_scene<T, B>(mut input: B, axis: UpAxis) -> Result<Scene<T>, Error>
+    where
+        T: GeoNum,
+        B: BufRead
+{
+    let mut text = String::new();
+    input.read_to_string(&mut text)?;
+    let raw = parse_external(text.as_bytes()).map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+
+    // obj-rs only keeps the last object name, so walk the statements ourselves to find which object and group each face is in
+    let mut objects: Vec<(Option<String>, Vec<Section>)> = vec![(None, Vec::new())];
+    let mut polygon = 0;
+    let mut group: Option<String> = None;
+    for line in text.replace("\\\n", " ").lines() {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("o") => {
+                let name = words.collect::<Vec<_>>().join(" ");
+                objects.push((Some(name).filter(|n| !n.is_empty()), Vec::new()));
+                group = None;
+            },
+            Some("g") => group = words.next().map(String::from),
+            Some("f") | Some("fo") => {
+                let sections = &mut objects.last_mut().unwrap().1;
+                match sections.iter_mut().find(|s| s.name == group) {
+                    Some(section) => section.polygons.push(polygon),
+                    None => sections.push(Section { name: group.clone(), polygons: vec![polygon] }),
+                }
+                polygon += 1;
+            },
+            _ => {},
+        }
+    }
+
+    let mut scene = Scene::new();
+    for (name, sections) in objects {
+        match sections.len() {
+            0 => {},
+            1 => {
+                let section = &sections[0];
+                let mut mesh = section_mesh(&raw.positions, &raw.polygons, &section.polygons, axis);
+                if let Some(name) = name.or_else(|| section.name.clone()) {
+                    mesh.set_name(name);
+                }
+                scene.add_mesh(mesh, None);
+            },
+            _ => {
+                let parent = scene.add_node(Node::new(name), None);
+                for section in sections.iter() {
+                    let mut mesh = section_mesh(&raw.positions, &raw.polygons, &section.polygons, axis);
+                    if let Some(name) = section.name.clone() {
+                        mesh.set_name(name);
+                    }
+                    scene.add_mesh(mesh, Some(parent));
+                }
+            },
+        }
+    }
+    Ok(scene)
+}
+
+fn section_mesh<T: GeoNum>(positions: &[(f32, f32, f32, f32)], polygons: &[Polygon], selected: &[usize], axis: UpAxis) -> Mesh<T> {
+    let mut mesh = Mesh::<T>::new();
+    let mut remap = HashMap::new();
+    for &p in selected.iter() {
+        let verts: Vec<usize> = match &polygons[p] {
+            Polygon::P(face) => face.clone(),
+            Polygon::PT(face) => face.iter().map(|x| x.0).collect(),
+            Polygon::PN(face) => face.iter().map(|x| x.0).collect(),
+            Polygon::PTN(face) => face.iter().map(|x| x.0).collect(),
+        };
+        let face = verts.iter().map(|v| *remap.entry(*v).or_insert_with(|| mesh.add_vertex(position(positions[*v], axis)))).collect();
+        mesh.add_face(Face::new(face));
+    }
+    mesh
+}
+
+#[test]
+fn parse_scene_test() {
+    let data = "o cube
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+g top
+f 1 2 3
+g bottom
+f 1 3 4
+o tri
+v 5 5 5
+f 1 2 5
+";
+    let scene = parse_scene::<f64, _>(data.as_bytes(), UpAxis::Z).unwrap();
+    assert_eq!(scene.roots().len(), 2);
+
+    let cube = scene.find("cube").unwrap();
+    assert!(scene.node(cube).unwrap().mesh().is_none());
+    assert_eq!(scene.node(cube).unwrap().children().len(), 2);
+
+    let bottom = scene.node(scene.find("bottom").unwrap()).unwrap().mesh().unwrap();
+    assert_eq!(bottom.verticies().len(), 3);
+
+    let tri = scene.node(scene.find("tri").unwrap()).unwrap().mesh().unwrap();
+    assert_eq!(tri.verticies()[2], Vertex::new(5., 5., 5.));
+}
+
+#[test]
+fn parse_test() {
+    use super::{write, ExportOptions};
+
+    let mut tri = Mesh::<f64>::new();
+    tri.make_face(vec![Vertex::new(0., 0., 0.), Vertex::new(1., 0., 0.), Vertex::new(0., 1., 0.5)]);
+
+    // reading with the axis a file was written with gives back the same verticies wound the same way
+    for &axis in [UpAxis::Z, UpAxis::Y].iter() {
+        let mut out = Vec::new();
+        write(&tri, &mut out, &ExportOptions { axis, ..ExportOptions::default() }).unwrap();
+        let back = parse::<f64, _>(out.as_slice(), axis).unwrap();
+        assert_eq!(back.verticies(), tri.verticies());
+        assert_eq!(back.newell(&back.faces()[0]), tri.newell(&tri.faces()[0]));
+    }
+}
+
+#[test]
+fn scene_round_trip_test() {
+    use super::{write_scene, ExportOptions};
+
+    let mut tri = Mesh::<f64>::new();
+    tri.set_name("tri".into());
+    tri.make_face(vec![Vertex::new(0., 0., 0.), Vertex::new(1., 0., 0.), Vertex::new(0., 1., 0.5)]);
+    let mut scene = Scene::new();
+    scene.add_mesh(tri.clone(), None);
+
+    let mut out = Vec::new();
+    write_scene(&scene, &mut out, &ExportOptions { axis: UpAxis::Y, ..ExportOptions::default() }).unwrap();
+    let back = parse_scene::<f64, _>(out.as_slice(), UpAxis::Y).unwrap();
+    let mesh = back.node(back.find("tri").unwrap()).unwrap().mesh().unwrap();
+    assert_eq!(mesh.verticies(), tri.verticies());
+    assert_eq!(mesh.newell(&mesh.faces()[0]), tri.newell(&tri.faces()[0]));
+}